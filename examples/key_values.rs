@@ -0,0 +1,13 @@
+use fern_format::Format;
+
+fn main() {
+    fern::Dispatch::new()
+        .format(Format::new().callback())
+        .chain(std::io::stdout())
+        .apply()
+        .unwrap();
+
+    log::info!(user_id = 42, path = "/index.html"; "request");
+    log::warn!(reason = "took too long", latency_ms = 1700; "slow request");
+    log::error!(query = "SELECT \"name\" FROM users"; "query failed");
+}
@@ -0,0 +1,19 @@
+use fern_format::{Format, NonBlocking};
+
+fn main() {
+    let (logger, _guard) =
+        NonBlocking::new(Format::new().thread_names(), std::io::stdout()).start();
+    fern::Dispatch::new()
+        .chain(Box::new(logger) as Box<dyn log::Log>)
+        .apply()
+        .unwrap();
+
+    let _scope = fern_format::scope("startup");
+    log::info!("logged from main");
+    std::thread::Builder::new()
+        .name("worker".into())
+        .spawn(|| log::warn!("logged from a thread"))
+        .unwrap()
+        .join()
+        .unwrap();
+}
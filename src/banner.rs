@@ -0,0 +1,29 @@
+use crate::syslog::hostname;
+
+/// Logs a header record with the name and version of the application, the process id,
+/// the name of the machine and the log level, so that it is clear at the top of a log
+/// file what wrote it. Call it after the logger is set up:
+/// ```no_run
+/// fern_format::init(log::LevelFilter::Info).unwrap();
+/// fern_format::banner(env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+/// // ... [INFO] banner: starting myapp 1.2.0 (pid 4242 on web-1, logging at info)
+/// ```
+///
+/// The record has the target `banner` and is logged at info, or at the most verbose
+/// level that is enabled if info isn't.
+pub fn banner(app: &str, version: &str) {
+    let Some(level) = log::max_level().to_level() else {
+        return;
+    };
+    let host = hostname();
+    log::log!(
+        target: "banner",
+        level.min(log::Level::Info),
+        "starting {} {} (pid {} on {}, logging at {})",
+        app,
+        version,
+        std::process::id(),
+        host.as_deref().unwrap_or("an unknown host"),
+        level.as_str().to_lowercase(),
+    );
+}
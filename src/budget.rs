@@ -0,0 +1,81 @@
+/// Ends lines that were cut to fit the budget
+const MARKER: &str = " [truncated]";
+
+/// Ends colored lines that were cut, in case the cut was in the middle of a style
+const COLORED_MARKER: &str = "\x1b[0m [truncated]";
+
+/// `line` cut to fit in `max` bytes with the marker that has to follow it, or `line` and
+/// nothing if it fits already. The first `head` bytes are always kept, even if the line
+/// doesn't fit that way.
+pub(crate) fn fit(line: &str, head: usize, max: usize) -> (&str, &'static str) {
+    if line.len() <= max {
+        return (line, "");
+    }
+    let marker = if line.contains('\x1b') {
+        COLORED_MARKER
+    } else {
+        MARKER
+    };
+    let mut cut = max.saturating_sub(marker.len()).max(head);
+    while !line.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    // don't leave half of an escape sequence behind
+    if let Some(escape) = line[head..cut].rfind('\x1b') {
+        if !line[head + escape..cut].contains('m') {
+            cut = head + escape;
+        }
+    }
+    if cut >= line.len() {
+        return (line, "");
+    }
+    (&line[..cut], marker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fitting_lines_are_kept() {
+        assert_eq!(fit("[INFO] short", 7, 12), ("[INFO] short", ""));
+    }
+
+    #[test]
+    fn cuts_at_the_budget() {
+        let line = "[INFO] app: a message that is too long";
+        let (kept, marker) = fit(line, 12, 30);
+        assert_eq!((kept, marker), ("[INFO] app: a mess", MARKER));
+        assert_eq!(kept.len() + marker.len(), 30);
+    }
+
+    #[test]
+    fn keeps_the_head_whole() {
+        let line = "2024-01-01 [INFO] app: message";
+        assert_eq!(fit(line, 23, 10), ("2024-01-01 [INFO] app: ", MARKER));
+    }
+
+    #[test]
+    fn doesnt_split_characters() {
+        // the budget ends in the middle of the second é
+        let (kept, marker) = fit("[INFO] éééééééé", 7, 10 + MARKER.len());
+        assert_eq!((kept, marker), ("[INFO] é", MARKER));
+        let (kept, _) = fit("[INFO] 😀😀😀😀😀", 7, 12 + MARKER.len());
+        assert_eq!(kept, "[INFO] 😀");
+    }
+
+    #[test]
+    fn doesnt_split_escape_sequences() {
+        let line = "\x1b[1m[INFO]\x1b[0m \x1b[38;5;208mcolored message\x1b[0m";
+        let head = "\x1b[1m[INFO]\x1b[0m ".len();
+        // the budget ends inside of `\x1b[38;5;208m`
+        let (kept, marker) = fit(line, head, head + 6 + COLORED_MARKER.len());
+        assert_eq!((kept, marker), ("\x1b[1m[INFO]\x1b[0m ", COLORED_MARKER));
+        // and right after it
+        let max = head + "\x1b[38;5;208mco".len() + COLORED_MARKER.len();
+        assert_eq!(
+            fit(line, head, max).0,
+            "\x1b[1m[INFO]\x1b[0m \x1b[38;5;208mco"
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use std::{
+    io::{self, BufWriter, Write},
+    sync::{Arc, Mutex, Weak},
+    time::Duration,
+};
+
+use crate::LineEnding;
+
+type Buffer = Mutex<BufWriter<Box<dyn Write + Send>>>;
+
+/// An output that collects the pieces of each formatted line in a buffer and writes
+/// them with a single call, instead of one write per piece. This matters most for
+/// stdout on Windows, which isn't buffered at all.
+///
+/// By default the buffer is written at the end of every record:
+/// ```no_run
+/// use fern_format::{Buffered, Format};
+///
+/// fern::Dispatch::new()
+///     .format(Format::new().callback())
+///     .chain(Buffered::stdout())
+///     .apply()
+///     .unwrap();
+/// ```
+///
+/// With [`flush_every`](Self::flush_every) records are instead written in batches.
+/// Clones share the same buffer, so keep one to [`flush`](Write::flush) what is left
+/// before the program exits.
+#[derive(Clone)]
+pub struct Buffered {
+    inner: Arc<Buffer>,
+    per_record: bool,
+    line_ending: LineEnding,
+}
+
+impl Buffered {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(BufWriter::new(Box::new(writer)))),
+            per_record: true,
+            line_ending: LineEnding::Lf,
+        }
+    }
+
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+
+    pub fn stderr() -> Self {
+        Self::new(io::stderr())
+    }
+
+    /// Write the buffer once every `interval` from a background thread instead of after
+    /// every record. The buffer is also written whenever it is full. The thread stops
+    /// when all clones are dropped.
+    pub fn flush_every(mut self, interval: Duration) -> Self {
+        self.per_record = false;
+        let inner = Arc::downgrade(&self.inner);
+        std::thread::Builder::new()
+            .name("fern-format flush".into())
+            .spawn(move || flush_periodically(inner, interval))
+            .expect("failed to spawn the flushing thread");
+        self
+    }
+
+    /// Separate records with `ending`, see [`Format::get_line_ending`](crate::Format::get_line_ending)
+    pub fn line_ending(mut self, ending: LineEnding) -> Self {
+        self.line_ending = ending;
+        self
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BufWriter<Box<dyn Write + Send>>> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn flush_periodically(inner: Weak<Buffer>, interval: Duration) {
+    loop {
+        std::thread::sleep(interval);
+        let Some(inner) = inner.upgrade() else {
+            return;
+        };
+        let _ = inner.lock().unwrap_or_else(|e| e.into_inner()).flush();
+    }
+}
+
+/// A handle that fern writes to. Its flushes, which fern does after every record, are
+/// only passed on when flushing per record.
+struct Handle(Buffered);
+
+impl Write for Handle {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.0.lock().write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.0.per_record {
+            self.0.lock().flush()
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Write for Buffered {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock().flush()
+    }
+}
+
+impl From<Buffered> for fern::Output {
+    fn from(buffered: Buffered) -> Self {
+        let line_ending = buffered.line_ending.as_str();
+        fern::Output::writer(Box::new(Handle(buffered)), line_ending)
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt::Display;
+
+/// The length of a [`Checksum`] that is shown
+pub(crate) const LEN: usize = " crc32=".len() + 8;
+
+/// ` crc32=` and the checksum, if there is one
+pub(crate) struct Checksum(pub(crate) Option<u32>);
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(crc) => write!(f, " crc32={crc:08x}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// If `record` ends with the checksum of the rest of it, see
+/// [`Format::checksums`](crate::Format::checksums). The checksum covers the whole
+/// record, so a multi-line record has to be passed with all of its lines, as they were
+/// written. A line ending after the checksum is ignored.
+///
+/// ```
+/// use fern_format::checksum_matches;
+///
+/// assert!(checksum_matches("[INFO] app: started crc32=604531f1\n"));
+/// assert!(!checksum_matches("[INFO] app: sta"));
+/// assert!(!checksum_matches("[INFO] app: stopped crc32=604531f1"));
+///
+/// assert!(checksum_matches("[WARN] app: two\nlines crc32=9e5b533f"));
+/// assert!(!checksum_matches("lines crc32=9e5b533f"));
+/// ```
+pub fn checksum_matches(record: &str) -> bool {
+    let record = record.trim_end_matches(['\r', '\n']);
+    let Some((rest, crc)) = record.rsplit_once(" crc32=") else {
+        return false;
+    };
+    crc.len() == 8 && u32::from_str_radix(crc, 16).is_ok_and(|crc| crc == crc32(&[rest]))
+}
+
+/// The CRC-32 of the concatenation of `parts`, as used by zlib and gzip
+pub(crate) fn crc32(parts: &[&str]) -> u32 {
+    !parts
+        .iter()
+        .flat_map(|part| part.bytes())
+        .fold(!0, |crc, byte| {
+            TABLE[usize::from(crc as u8 ^ byte)] ^ (crc >> 8)
+        })
+}
+
+/// The CRC-32 of every byte, with the reversed polynomial 0xedb88320
+const TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut crc = n as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                0xedb88320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[n] = crc;
+        n += 1;
+    }
+    table
+};
@@ -0,0 +1,695 @@
+//! The parts of a line as [`Display`] building blocks, for writing your own fern
+//! callback. All but [`Time`] begin with a space so that they can be written right
+//! after each other:
+//!
+//! ```no_run
+//! use fern_format::components::{Level, Message, ThreadName, Time};
+//! use fern_format::Timestamp;
+//!
+//! let time = Time::new(Timestamp::Time);
+//! let thread_name = ThreadName::new().colorize(true);
+//! fern::Dispatch::new()
+//!     .format(move |out, message, record| {
+//!         out.finish(format_args!(
+//!             "{}{}{}{}",
+//!             time,
+//!             thread_name,
+//!             Level::new(record.level()),
+//!             Message::new(message, record.level()),
+//!         ))
+//!     })
+//!     .chain(std::io::stdout())
+//!     .apply()
+//!     .unwrap();
+//! ```
+
+use std::{
+    fmt::{Display, Write},
+    sync::{
+        atomic::{AtomicU8, AtomicUsize, Ordering},
+        OnceLock,
+    },
+    thread::ThreadId,
+};
+
+use owo_colors::{Style, XtermColors};
+
+use crate::{
+    line_ending::{CrLfWriter, NulEscaper},
+    non_blocking::with_origin,
+    sanitize::StripEscapes,
+    styled::Styled,
+    thread_label, width,
+    width::AutoWidth,
+    LineEnding,
+};
+
+pub use crate::timestamp::Time;
+
+/// ` message`, colored by level
+pub struct Message<'a> {
+    colorize: bool,
+    basic_colors: bool,
+    warnings_only: bool,
+    style: Option<Style>,
+    level: log::Level,
+    message: &'a std::fmt::Arguments<'a>,
+    width: Option<usize>,
+    strip_escapes: bool,
+    line_ending: LineEnding,
+    level_escapes: Option<&'a LevelEscapes>,
+}
+
+impl<'a> Message<'a> {
+    pub fn new(message: &'a std::fmt::Arguments<'a>, level: log::Level) -> Self {
+        Self {
+            colorize: false,
+            basic_colors: false,
+            warnings_only: false,
+            style: None,
+            level,
+            message,
+            width: None,
+            strip_escapes: false,
+            line_ending: LineEnding::Lf,
+            level_escapes: None,
+        }
+    }
+
+    /// Color the message by its level, like journald
+    pub fn colorize(mut self, colorize: bool) -> Self {
+        self.colorize = colorize;
+        self
+    }
+
+    /// Only use the 8 basic colors for the levels, without bright variants
+    pub fn basic_colors(mut self, basic: bool) -> Self {
+        self.basic_colors = basic;
+        self
+    }
+
+    /// Only color warnings and errors by their level, and leave the rest as is
+    pub fn color_warnings_only(mut self, only: bool) -> Self {
+        self.warnings_only = only;
+        self
+    }
+
+    /// Use `style` instead of the color of the level, if colored
+    pub fn style(mut self, style: Option<Style>) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Truncate the message if it is wider than `width` columns
+    pub fn max_width(mut self, width: Option<usize>) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Remove ANSI escape sequences from the message
+    pub fn strip_escapes(mut self, strip: bool) -> Self {
+        self.strip_escapes = strip;
+        self
+    }
+
+    /// Terminate the lines of multi-line messages with `ending`
+    pub fn line_ending(mut self, ending: LineEnding) -> Self {
+        self.line_ending = ending;
+        self
+    }
+
+    /// Color by level with `escapes` instead of the default styles
+    pub(crate) fn level_escapes(mut self, escapes: &'a LevelEscapes) -> Self {
+        self.level_escapes = Some(escapes);
+        self
+    }
+
+    fn write_styled(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        message: impl Display,
+    ) -> std::fmt::Result {
+        if let (true, Some(style)) = (self.colorize, self.style) {
+            let message = Styled(message, Some(style));
+            return match self.line_ending {
+                LineEnding::Lf => write!(f, " {}", message),
+                LineEnding::CrLf => write!(CrLfWriter::new(f), " {}", message),
+                LineEnding::Nul => write!(NulEscaper(f), " {}", message),
+            };
+        }
+
+        let colored = !self.warnings_only || self.level <= log::Level::Warn;
+        let level = (self.colorize && colored).then_some(self.level);
+        let escapes = (self.level_escapes)
+            .unwrap_or_else(|| default_level_escapes(self.basic_colors));
+        let message = LevelStyled(message, level, escapes);
+        match self.line_ending {
+            LineEnding::Lf => write!(f, " {}", message),
+            LineEnding::CrLf => write!(CrLfWriter::new(f), " {}", message),
+            LineEnding::Nul => write!(NulEscaper(f), " {}", message),
+        }
+    }
+}
+
+impl<'a> Display for Message<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.width, self.strip_escapes) {
+            (Some(w), strip) => {
+                // the escapes are stripped first so that they don't count as columns
+                let message = match strip {
+                    true => StripEscapes(self.message).to_string(),
+                    false => self.message.to_string(),
+                };
+                self.write_styled(f, width::truncate(&message, w))
+            }
+            (None, true) => self.write_styled(f, StripEscapes(self.message)),
+            (None, false) => self.write_styled(f, self.message),
+        }
+    }
+}
+
+/// The index of the color of the next thread that logs
+static NEXT_COLOR: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The color of this thread, picked the first time it logs
+    static THREAD_STYLE: ThreadStyle = ThreadStyle {
+        index: NEXT_COLOR.fetch_add(1, Ordering::Relaxed),
+        overflow: overflow(),
+    };
+}
+
+/// The style of a thread, by the order in which it first logged
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ThreadStyle {
+    index: usize,
+    overflow: ThreadStyleOverflow,
+}
+
+impl ThreadStyle {
+    /// The style from `palette`, or from the built-in styles without one
+    pub(crate) fn get(self, palette: Option<&[Style]>, basic_colors: bool) -> Style {
+        let i = self.index;
+        let built_in = match basic_colors {
+            true => BASIC_THREAD_STYLES,
+            false => THREAD_STYLES,
+        };
+        let len = palette.map_or(built_in, <[Style]>::len);
+        let wrapped = || match palette {
+            Some(palette) => (i.checked_rem(len))
+                .and_then(|i| palette.get(i))
+                .copied()
+                .unwrap_or_default(),
+            None => gen_color((i % built_in) as u8, basic_colors),
+        };
+        if i < len {
+            return wrapped();
+        }
+        match self.overflow {
+            ThreadStyleOverflow::Wrap => wrapped(),
+            ThreadStyleOverflow::Neutral => Style::new(),
+            ThreadStyleOverflow::Hashed if basic_colors => wrapped(),
+            ThreadStyleOverflow::Hashed => {
+                // a bright corner of the 6x6x6 color cube
+                let n = ((i as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 58) as u8;
+                let (r, g, b) = (2 + n / 16, 2 + n / 4 % 4, 2 + n % 4);
+                Style::new().color(XtermColors::from(16 + 36 * r + 6 * g + b))
+            }
+        }
+    }
+}
+
+/// How many threads get distinct styles before the [`ThreadStyleOverflow`] applies,
+/// unless the [`Theme`](crate::Theme) has a palette of its own. With only the [basic
+/// colors](crate::Format::basic_colors) there are half as many, 14, since italics are
+/// left out, and the overflow applies from the 15th thread on.
+pub const THREAD_STYLES: usize = 28;
+
+/// The distinct thread styles with only the basic colors, which are the first half of
+/// the styles of [`gen_color`]
+const BASIC_THREAD_STYLES: usize = THREAD_STYLES / 2;
+
+/// The style of threads that log after all [`THREAD_STYLES`] are taken
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ThreadStyleOverflow {
+    /// Start over with the first style, so that several threads share each style
+    #[default]
+    Wrap,
+    /// No style, so that only the first threads stand out
+    Neutral,
+    /// One of the 256 colors picked by the order of the thread, which most terminals
+    /// support. Threads can still share colors, but less often. The styles wrap around
+    /// with only the [basic colors](crate::Format::basic_colors).
+    Hashed,
+}
+
+static OVERFLOW: AtomicU8 = AtomicU8::new(ThreadStyleOverflow::Wrap as u8);
+
+/// Sets the style of threads that log after all [`THREAD_STYLES`] are taken, for all
+/// formats. Threads keep the style they already have.
+pub fn set_thread_style_overflow(overflow: ThreadStyleOverflow) {
+    OVERFLOW.store(overflow as u8, Ordering::Relaxed);
+}
+
+fn overflow() -> ThreadStyleOverflow {
+    match OVERFLOW.load(Ordering::Relaxed) {
+        o if o == ThreadStyleOverflow::Neutral as u8 => ThreadStyleOverflow::Neutral,
+        o if o == ThreadStyleOverflow::Hashed as u8 => ThreadStyleOverflow::Hashed,
+        _ => ThreadStyleOverflow::Wrap,
+    }
+}
+
+/// ` (name)` of the current thread, or its id if it has no name. A
+/// [label](crate::set_thread_label) takes the place of the name.
+#[derive(Clone, Copy, Debug)]
+pub struct ThreadName<'a> {
+    colorize: bool,
+    print: bool,
+    width: Option<usize>,
+    /// The number of the thread to call `main` if it has no name
+    main: Option<u64>,
+    main_style: Option<Style>,
+    punctuation_style: Option<Style>,
+    basic_colors: bool,
+    palette: Option<&'a [Style]>,
+    placeholder: Option<&'static str>,
+}
+
+impl ThreadName<'static> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            colorize: false,
+            print: true,
+            width: None,
+            main: None,
+            main_style: None,
+            punctuation_style: None,
+            basic_colors: false,
+            palette: None,
+            placeholder: None,
+        }
+    }
+}
+
+impl<'a> ThreadName<'a> {
+    /// Give each thread its own color. A thread keeps its color for as long as it lives.
+    pub fn colorize(mut self, colorize: bool) -> Self {
+        self.colorize = colorize;
+        self
+    }
+
+    /// Pad or truncate the names so that they are exactly `width` columns wide
+    pub fn width(mut self, width: Option<usize>) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Call the thread that calls this `main` if it has no name, which is the case for the
+    /// main thread of programs that aren't started by Rust
+    pub fn label_main(mut self, label: bool) -> Self {
+        self.main = label.then(|| threadid_as_u64(std::thread::current().id()));
+        self
+    }
+
+    /// Call the thread with the number `main` `main` if it has no name
+    pub(crate) fn main_thread(mut self, main: Option<u64>) -> Self {
+        self.main = main;
+        self
+    }
+
+    /// Give the main thread `style` instead of a color of its own, if threads are colored
+    pub fn main_style(mut self, style: Option<Style>) -> Self {
+        self.main_style = style;
+        self
+    }
+
+    /// Give the parentheses `style` instead of the style of the thread
+    pub fn punctuation_style(mut self, style: Option<Style>) -> Self {
+        self.punctuation_style = style;
+        self
+    }
+
+    /// Only use the 8 basic colors, without bright variants or italics
+    pub fn basic_colors(mut self, basic: bool) -> Self {
+        self.basic_colors = basic;
+        self
+    }
+
+    /// Give the threads the styles of `palette` instead of the built-in ones, see
+    /// [`Theme::thread_palette`](crate::Theme::thread_palette)
+    pub fn palette(self, palette: &[Style]) -> ThreadName<'_> {
+        ThreadName {
+            palette: Some(palette),
+            ..self
+        }
+    }
+
+    /// Show `placeholder` instead of the name of every thread
+    pub(crate) fn placeholder(mut self, placeholder: Option<&'static str>) -> Self {
+        self.placeholder = placeholder;
+        self
+    }
+
+    /// Render nothing unless `print`
+    pub(crate) fn print(mut self, print: bool) -> Self {
+        self.print = print;
+        self
+    }
+
+    /// If anything is rendered
+    pub(crate) fn prints(&self) -> bool {
+        self.print
+    }
+}
+
+impl Display for ThreadName<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.print {
+            return Ok(());
+        }
+
+        self.with_label(|name, thread_style| {
+            let fitted;
+            let name: &dyn Display = match self.width {
+                Some(w) => {
+                    fitted = width::fit(name, w);
+                    &fitted
+                }
+                None => &name,
+            };
+            match self.punctuation_style {
+                Some(style) => write!(
+                    f,
+                    " {}{}{}",
+                    Styled("(", Some(style)),
+                    Styled(name, thread_style),
+                    Styled(")", Some(style))
+                ),
+                None => {
+                    write!(f, " {}", Styled(format_args!("({})", name), thread_style))
+                }
+            }
+        })
+    }
+}
+
+impl ThreadName<'_> {
+    /// Widens `column` to the name of the current thread, and shows the name in its width
+    pub(crate) fn auto_width(self, column: &AutoWidth) -> Self {
+        if !self.print {
+            return self;
+        }
+        let width = self.with_label(|name, _| column.widen(name));
+        self.width(Some(width))
+    }
+
+    /// Calls `f` with the name to show for the current thread, and its style
+    fn with_label<R>(&self, f: impl FnOnce(&str, Option<Style>) -> R) -> R {
+        if let Some(placeholder) = self.placeholder {
+            return f(placeholder, self.style(false, thread_style));
+        }
+        with_origin(|origin| match origin {
+            Some(origin) => {
+                let name = origin.thread_name.as_deref();
+                let main = self.is_main(name, &origin.thread_number);
+                f(
+                    self.label(name, main).unwrap_or(&origin.thread_number),
+                    self.style(main, || origin.thread_style),
+                )
+            }
+            None => thread_label::with_name(|name| {
+                with_thread_number(|number| {
+                    let main = self.is_main(name, number);
+                    f(
+                        self.label(name, main).unwrap_or(number),
+                        self.style(main, thread_style),
+                    )
+                })
+            }),
+        })
+    }
+
+    fn is_main(&self, name: Option<&str>, number: &str) -> bool {
+        name == Some("main") || self.main.is_some_and(|main| number.parse() == Ok(main))
+    }
+
+    /// The name to show instead of the number, if any
+    fn label<'a>(&self, name: Option<&'a str>, main: bool) -> Option<&'a str> {
+        match name {
+            Some(name) => Some(name),
+            None if main => Some("main"),
+            None => None,
+        }
+    }
+
+    fn style(
+        &self,
+        main: bool,
+        thread_style: impl FnOnce() -> ThreadStyle,
+    ) -> Option<Style> {
+        if !self.colorize {
+            return None;
+        }
+        match self.main_style {
+            Some(style) if main => Some(style),
+            _ => Some(thread_style().get(self.palette, self.basic_colors)),
+        }
+    }
+}
+
+/// ` [LEVEL]`. Nothing is written when colored, since [`Message`] is colored by level
+/// instead.
+pub struct Level {
+    level: log::Level,
+    use_color: bool,
+}
+
+impl Level {
+    pub fn new(level: log::Level) -> Self {
+        Self {
+            level,
+            use_color: false,
+        }
+    }
+
+    /// Leave out the level in favor of a colored [`Message`]
+    pub fn colorize(mut self, colorize: bool) -> Self {
+        self.use_color = colorize;
+        self
+    }
+}
+
+impl Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.use_color {
+            f.write_str(level_label(self.level))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// ` [LEVEL]`
+fn level_label(level: log::Level) -> &'static str {
+    match level {
+        log::Level::Error => " [ERROR]",
+        log::Level::Warn => " [WARN]",
+        log::Level::Info => " [INFO]",
+        log::Level::Debug => " [DEBUG]",
+        log::Level::Trace => " [TRACE]",
+    }
+}
+
+/// Writes `.0` in the style of `.1` from the escapes `.2`, or as is without a level
+pub(crate) struct LevelStyled<'a, T>(
+    pub(crate) T,
+    pub(crate) Option<log::Level>,
+    pub(crate) &'a LevelEscapes,
+);
+
+impl<T: Display> Display for LevelStyled<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(level) = self.1 else {
+            return self.0.fmt(f);
+        };
+
+        let (prefix, suffix) = &self.2[level as usize - 1];
+        f.write_str(prefix)?;
+        self.0.fmt(f)?;
+        f.write_str(suffix)
+    }
+}
+
+/// The escape sequences that start and end the style of each level, in the order of
+/// [`log::Level`]
+pub(crate) type LevelEscapes = [(String, String); 5];
+
+/// The escapes of the styles that `style` gives each level
+pub(crate) fn level_escapes(style: impl Fn(log::Level) -> Style) -> LevelEscapes {
+    struct Prefix(Style);
+
+    impl Display for Prefix {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            self.0.fmt_prefix(f)
+        }
+    }
+
+    struct Suffix(Style);
+
+    impl Display for Suffix {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            self.0.fmt_suffix(f)
+        }
+    }
+
+    use log::Level::*;
+    [Error, Warn, Info, Debug, Trace].map(|level| {
+        let style = style(level);
+        (Prefix(style).to_string(), Suffix(style).to_string())
+    })
+}
+
+/// The escapes of the [`level_style`]s, which are only formatted once
+pub(crate) fn default_level_escapes(basic_colors: bool) -> &'static LevelEscapes {
+    static ESCAPES: [OnceLock<LevelEscapes>; 2] = [OnceLock::new(), OnceLock::new()];
+    ESCAPES[basic_colors as usize]
+        .get_or_init(|| level_escapes(|level| level_style(level, basic_colors)))
+}
+
+/// Mimics the color style of journald
+pub(crate) fn level_style(level: log::Level, basic_colors: bool) -> Style {
+    match (level, basic_colors) {
+        (log::Level::Error, false) => Style::new().bright_red().bold(),
+        (log::Level::Error, true) => Style::new().red().bold(),
+        (log::Level::Warn, false) => Style::new().bright_yellow().bold(),
+        (log::Level::Warn, true) => Style::new().yellow().bold(),
+        (log::Level::Info, false) => Style::new().bright_white().bold(),
+        (log::Level::Info, true) => Style::new().white().bold(),
+        (log::Level::Debug, _) => Style::new().white(),
+        (log::Level::Trace, _) => Style::new().dimmed(),
+    }
+}
+
+/// The color of the current thread
+pub(crate) fn thread_style() -> ThreadStyle {
+    THREAD_STYLE.with(|style| *style)
+}
+
+thread_local! {
+    static THREAD_NUMBER: String = threadid_as_u64(std::thread::current().id()).to_string();
+}
+
+/// Calls `f` with the name of the current thread, or the number in its id if it has no
+/// name
+pub(crate) fn with_thread_name<R>(f: impl FnOnce(&str) -> R) -> R {
+    with_origin(|origin| match origin {
+        Some(origin) => f(origin.thread_name.as_ref().unwrap_or(&origin.thread_number)),
+        None => thread_label::with_name(|name| match name {
+            Some(name) => f(name),
+            None => with_thread_number(f),
+        }),
+    })
+}
+
+/// Calls `f` with the number in the id of the current thread, which is only computed
+/// once per thread
+pub(crate) fn with_thread_number<R>(f: impl FnOnce(&str) -> R) -> R {
+    THREAD_NUMBER.with(|number| f(number))
+}
+
+/// The number in the id of the current thread
+pub(crate) fn current_thread_number() -> u64 {
+    threadid_as_u64(std::thread::current().id())
+}
+
+// https://github.com/rust-lang/rust/issues/67939
+// TODO: use the stabilzed function when and if it is stabilized
+// TODO: error handling?
+fn threadid_as_u64(id: ThreadId) -> u64 {
+    let string = format!("{:?}", id);
+    let string = string.strip_prefix("ThreadId(").unwrap();
+    let string = string.strip_suffix(")").unwrap();
+    string.parse().unwrap()
+}
+
+/// The `i`th thread style, where `basic` leaves out italics and bright colors
+fn gen_color(i: u8, basic: bool) -> Style {
+    const BOLD: u8 = 2;
+    const COLOR: u8 = 7;
+    const ITALIC: u8 = 2;
+    let total = BOLD * COLOR * ITALIC;
+    const _: () = assert!((BOLD * COLOR * ITALIC) as usize == THREAD_STYLES);
+
+    let style = Style::new();
+
+    let i = i % total;
+    let total = total / ITALIC;
+    let style = match i / total {
+        0 => style,
+        1 if basic => style,
+        1 => style.italic(),
+        _ => unreachable!(),
+    };
+
+    let i = i % total;
+    let total = total / BOLD;
+    let style = match i / total {
+        0 => style,
+        1 => style.bold(),
+        _ => unreachable!(),
+    };
+
+    let i = i % total;
+    let total = total / COLOR;
+    if basic {
+        return match i / total {
+            0 => style.white(),
+            1 => style.blue(),
+            2 => style.yellow(),
+            3 => style.cyan(),
+            4 => style.purple(),
+            5 => style.green(),
+            6 => style.red(),
+            _ => unreachable!(),
+        };
+    }
+    match i / total {
+        0 => style.bright_white(),
+        1 => style.bright_blue(),
+        2 => style.bright_yellow(),
+        3 => style.bright_cyan(),
+        4 => style.bright_purple(),
+        5 => style.bright_green(),
+        6 => style.bright_red(),
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn basic_thread_styles_overflow_after_half() {
+        let style = |index| {
+            let thread = ThreadStyle {
+                index,
+                overflow: ThreadStyleOverflow::Neutral,
+            };
+            thread.get(None, true)
+        };
+        assert_ne!(style(BASIC_THREAD_STYLES - 1), Style::new());
+        assert_eq!(style(BASIC_THREAD_STYLES), Style::new());
+    }
+
+    #[test]
+    fn basic_thread_styles_are_distinct() {
+        let styles: Vec<_> = (0..BASIC_THREAD_STYLES)
+            .map(|i| gen_color(i as u8, true))
+            .collect();
+        for (i, style) in styles.iter().enumerate() {
+            assert!(!styles[..i].contains(style), "style {i} repeats");
+        }
+    }
+}
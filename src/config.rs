@@ -0,0 +1,633 @@
+//! Deserializing a [`Format`] from the configuration of an application
+
+use std::collections::BTreeMap;
+#[cfg(feature = "toml")]
+use std::{fmt::Display, path::PathBuf};
+
+use owo_colors::{AnsiColors, DynColors, Style};
+use serde::{Deserialize, Deserializer};
+
+#[cfg(feature = "kv")]
+use crate::KvFormat;
+use crate::{
+    Colorize, Format, JsonKey, KvFilter, Layout, LineEnding, ScopeStyle, Stream, Theme,
+    Timestamp,
+};
+
+/// The options of a [`Format`] as they are written in a configuration file, named like
+/// the options of [`Format::from_env`]. See [`Format::from_config_file`] for all of
+/// them.
+/// ```toml
+/// [logging.format]
+/// color = "auto"
+/// threads = true
+/// time = "rfc3339"
+/// target-width = 20
+/// kv-deny = ["request_body"]
+/// fields = { service = "api" }
+/// ```
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct Options {
+    color: Option<Color>,
+    basic_colors: bool,
+    color_warnings_only: bool,
+    ticks: bool,
+    threads: bool,
+    thread_colors: bool,
+    thread_width: Option<usize>,
+    time: Timestamp,
+    hide_time: bool,
+    weekday: bool,
+    also_utc: bool,
+    label_main_thread: bool,
+    target: bool,
+    target_width: Option<usize>,
+    auto_width: Option<usize>,
+    target_elapsed: bool,
+    target_counts: bool,
+    watch_targets: BTreeMap<String, StyleName>,
+    dim_target_prefixes: Vec<String>,
+    rainbow: bool,
+    message_width: Option<usize>,
+    strip_escapes: bool,
+    line_ending: LineEnding,
+    severity_prefixes: bool,
+    checksums: bool,
+    max_record_bytes: Option<usize>,
+    #[cfg(feature = "kv")]
+    kv: KvFormat,
+    #[cfg(feature = "kv")]
+    human_durations: bool,
+    #[cfg(feature = "kv")]
+    human_sizes: bool,
+    #[cfg(feature = "kv")]
+    human_size_keys: Option<Vec<String>>,
+    #[cfg(feature = "kv")]
+    kv_value_len: Option<usize>,
+    #[cfg(feature = "kv")]
+    kv_len: Option<usize>,
+    kv_allow: Option<Vec<String>>,
+    kv_deny: Option<Vec<String>>,
+    fields: BTreeMap<String, String>,
+    scopes: ScopeStyle,
+    correlation_id_key: Option<String>,
+    layout: Layout,
+    json_keys: JsonKeyOptions,
+    theme: Option<Theme>,
+}
+
+/// The names of the members of JSON objects, see [`Format::json_key`]
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct JsonKeyOptions {
+    timestamp: Option<String>,
+    level: Option<String>,
+    target: Option<String>,
+    thread: Option<String>,
+    message: Option<String>,
+    fields: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Color {
+    Auto,
+    Always,
+    Never,
+    /// Like [`Format::color_from_env`]
+    Env,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            color: None,
+            basic_colors: false,
+            color_warnings_only: false,
+            ticks: false,
+            threads: false,
+            thread_colors: false,
+            thread_width: None,
+            time: Timestamp::default(),
+            hide_time: false,
+            weekday: false,
+            also_utc: false,
+            label_main_thread: false,
+            target: true,
+            target_width: None,
+            auto_width: None,
+            target_elapsed: false,
+            target_counts: false,
+            watch_targets: BTreeMap::new(),
+            dim_target_prefixes: Vec::new(),
+            rainbow: false,
+            message_width: None,
+            strip_escapes: false,
+            line_ending: LineEnding::default(),
+            severity_prefixes: false,
+            checksums: false,
+            max_record_bytes: None,
+            #[cfg(feature = "kv")]
+            kv: KvFormat::default(),
+            #[cfg(feature = "kv")]
+            human_durations: false,
+            #[cfg(feature = "kv")]
+            human_sizes: false,
+            #[cfg(feature = "kv")]
+            human_size_keys: None,
+            #[cfg(feature = "kv")]
+            kv_value_len: None,
+            #[cfg(feature = "kv")]
+            kv_len: None,
+            kv_allow: None,
+            kv_deny: None,
+            fields: BTreeMap::new(),
+            scopes: ScopeStyle::default(),
+            correlation_id_key: None,
+            layout: Layout::default(),
+            json_keys: JsonKeyOptions::default(),
+            theme: None,
+        }
+    }
+}
+
+impl From<Options> for Format {
+    fn from(options: Options) -> Self {
+        let mut format = Format::new();
+        if let Some(color) = options.color {
+            format.colorize = match color {
+                Color::Auto => Colorize::ColorIf(Stream::Stdout),
+                Color::Always => Colorize::Color,
+                Color::Never => Colorize::BlackWhite,
+                Color::Env => Colorize::FromEnv(Stream::Stdout),
+            };
+        }
+        format.basic_colors = options.basic_colors;
+        format.color_warnings_only = options.color_warnings_only;
+        format.ticks = options.ticks;
+        format.thread_names = options.threads || options.thread_colors;
+        format.color_threads = options.thread_colors;
+        format.thread_name_width = options.thread_width;
+        format.timestamp = options.time;
+        format.show_time = !options.hide_time;
+        format.weekday = options.weekday;
+        format.also_utc = options.also_utc;
+        if options.label_main_thread {
+            format = format.label_main_thread();
+        }
+        format.targets = options.target;
+        format.target_width = options.target_width;
+        format.auto_width = options.auto_width;
+        format.target_elapsed = options.target_elapsed;
+        format.target_counts = options.target_counts;
+        for (target, StyleName(style)) in options.watch_targets {
+            format = format.watch_target(target, style);
+        }
+        format.dimmed_targets = options.dim_target_prefixes;
+        format.rainbow = options.rainbow;
+        format.message_width = options.message_width;
+        format.strip_escapes = options.strip_escapes;
+        format.line_ending = options.line_ending;
+        format.severity_prefixes = options.severity_prefixes;
+        format.checksums = options.checksums;
+        format.max_record_bytes = options.max_record_bytes;
+        #[cfg(feature = "kv")]
+        {
+            format.kv_format = options.kv;
+            format.human_durations = options.human_durations;
+            format.human_sizes = options.human_sizes;
+            if let Some(keys) = options.human_size_keys {
+                format = format.human_size_keys(keys);
+            }
+            format.kv_limits.value = options.kv_value_len;
+            format.kv_limits.total = options.kv_len;
+        }
+        format.kv_filter = match (options.kv_allow, options.kv_deny) {
+            (Some(allow), _) => KvFilter::Allow(allow),
+            (None, Some(deny)) => KvFilter::Deny(deny),
+            (None, None) => KvFilter::All,
+        };
+        format.static_fields = options.fields.into_iter().collect();
+        format.scope_style = options.scopes;
+        if let Some(key) = options.correlation_id_key {
+            format.correlation_key = key;
+        }
+        format.layout = options.layout;
+        let json_keys = [
+            (JsonKey::Timestamp, options.json_keys.timestamp),
+            (JsonKey::Level, options.json_keys.level),
+            (JsonKey::Target, options.json_keys.target),
+            (JsonKey::Thread, options.json_keys.thread),
+            (JsonKey::Message, options.json_keys.message),
+        ];
+        for (key, name) in json_keys {
+            if let Some(name) = name {
+                format.json_keys.set(key, name);
+            }
+        }
+        format.json_keys.fields = options.json_keys.fields;
+        if let Some(theme) = options.theme {
+            format.theme = theme;
+        }
+        format
+    }
+}
+
+/// The styles of a [`Theme`] as they are written in a configuration file, see
+/// [`Theme::from_file`]
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub(crate) struct ThemeOptions {
+    error: Option<StyleName>,
+    warn: Option<StyleName>,
+    info: Option<StyleName>,
+    debug: Option<StyleName>,
+    trace: Option<StyleName>,
+    threads: Option<Vec<StyleName>>,
+    main_thread: Option<StyleName>,
+    time: Option<StyleName>,
+    target: Option<StyleName>,
+    punctuation: Option<StyleName>,
+    key: Option<StyleName>,
+    value: Option<StyleName>,
+    secondary: Option<StyleName>,
+    correlation_id: Option<StyleName>,
+    dimmed: Option<StyleName>,
+}
+
+impl From<ThemeOptions> for Theme {
+    fn from(options: ThemeOptions) -> Self {
+        let mut theme = Theme::new();
+        let levels = [
+            options.error,
+            options.warn,
+            options.info,
+            options.debug,
+            options.trace,
+        ];
+        for (level, style) in log::Level::iter().zip(levels) {
+            if let Some(StyleName(style)) = style {
+                theme = theme.level(level, style);
+            }
+        }
+        if let Some(threads) = options.threads {
+            theme = theme.thread_palette(threads.into_iter().map(|StyleName(s)| s));
+        }
+        let set = |theme, style, set: fn(Theme, Style) -> Theme| match style {
+            Some(StyleName(style)) => set(theme, style),
+            None => theme,
+        };
+        theme = set(theme, options.main_thread, Theme::main_thread);
+        theme = set(theme, options.time, Theme::time);
+        theme = set(theme, options.target, Theme::target);
+        theme = set(theme, options.punctuation, Theme::punctuation);
+        theme = set(theme, options.secondary, Theme::secondary);
+        theme = set(theme, options.correlation_id, Theme::correlation_id);
+        theme = set(theme, options.dimmed, Theme::dimmed);
+        if let Some(StyleName(key)) = options.key {
+            theme.key_values.key = key;
+        }
+        if let Some(StyleName(value)) = options.value {
+            theme.key_values.value = value;
+        }
+        theme
+    }
+}
+
+/// A style written as words, e.g. `"bold bright-red on-blue"` or `"italic #ff8800"`
+struct StyleName(Style);
+
+impl<'de> Deserialize<'de> for StyleName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        parse_style(&name)
+            .map(StyleName)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+fn parse_style(name: &str) -> Result<Style, String> {
+    name.split_whitespace()
+        .try_fold(Style::new(), |style, word| {
+            Ok(match word {
+                "bold" => style.bold(),
+                "dimmed" => style.dimmed(),
+                "italic" => style.italic(),
+                "underline" => style.underline(),
+                "blink" => style.blink(),
+                "reversed" => style.reversed(),
+                "hidden" => style.hidden(),
+                "strikethrough" => style.strikethrough(),
+                _ => match word.strip_prefix("on-") {
+                    Some(color) => style.on_color(parse_color(color)?),
+                    None => style.color(parse_color(word)?),
+                },
+            })
+        })
+}
+
+/// A color by its name, e.g. `red` or `bright-red`, or as `#rrggbb`
+fn parse_color(name: &str) -> Result<DynColors, String> {
+    if let Some(hex) = name.strip_prefix('#') {
+        let channel = |i: usize| {
+            (hex.len() == 6)
+                .then(|| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+                .flatten()
+        };
+        return match (channel(0), channel(2), channel(4)) {
+            (Some(r), Some(g), Some(b)) => Ok(DynColors::Rgb(r, g, b)),
+            _ => Err(format!("invalid color {name:?}, expected #rrggbb")),
+        };
+    }
+
+    let color = match name {
+        "black" => AnsiColors::Black,
+        "red" => AnsiColors::Red,
+        "green" => AnsiColors::Green,
+        "yellow" => AnsiColors::Yellow,
+        "blue" => AnsiColors::Blue,
+        "magenta" => AnsiColors::Magenta,
+        "cyan" => AnsiColors::Cyan,
+        "white" => AnsiColors::White,
+        "bright-black" => AnsiColors::BrightBlack,
+        "bright-red" => AnsiColors::BrightRed,
+        "bright-green" => AnsiColors::BrightGreen,
+        "bright-yellow" => AnsiColors::BrightYellow,
+        "bright-blue" => AnsiColors::BrightBlue,
+        "bright-magenta" => AnsiColors::BrightMagenta,
+        "bright-cyan" => AnsiColors::BrightCyan,
+        "bright-white" => AnsiColors::BrightWhite,
+        _ => {
+            return Err(format!(
+                "unknown style {name:?}, expected a color such as red, bright-red or \
+                 #rrggbb, or bold, dimmed, italic, underline, blink, reversed, hidden or \
+                 strikethrough"
+            ))
+        }
+    };
+    Ok(DynColors::Ansi(color))
+}
+
+/// Why [`Format::from_config_file`] failed
+#[cfg(feature = "toml")]
+#[derive(Debug)]
+pub struct ConfigError {
+    path: PathBuf,
+    kind: ConfigErrorKind,
+}
+
+#[cfg(feature = "toml")]
+#[derive(Debug)]
+enum ConfigErrorKind {
+    Read(std::io::Error),
+    Parse(toml::de::Error),
+}
+
+#[cfg(feature = "toml")]
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ConfigErrorKind::Read(e) => {
+                write!(f, "failed to read {}: {}", self.path.display(), e)
+            }
+            ConfigErrorKind::Parse(e) => {
+                write!(f, "invalid log format in {}: {}", self.path.display(), e)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "toml")]
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ConfigErrorKind::Read(e) => Some(e),
+            ConfigErrorKind::Parse(e) => Some(e),
+        }
+    }
+}
+
+#[cfg(feature = "toml")]
+impl Format {
+    /// Reads a `Format` from a TOML file, so that the appearance of the logs can be tuned
+    /// without recompiling. All options are optional and keep their defaults from
+    /// [`Format::new`] when left out:
+    /// ```toml
+    /// color = "auto"               # or "always", "never", "env" for RUST_LOG_STYLE
+    /// basic-colors = true          # only the 8 basic colors, e.g. for serial consoles
+    /// color-warnings-only = true   # leave the messages of other levels uncolored
+    /// ticks = true                 # print the tick set with `set_tick`
+    /// threads = true               # print thread names
+    /// thread-colors = true         # color each thread uniquely
+    /// thread-width = 10            # pad or truncate thread names
+    /// time = "datetime"            # or "time", "rfc3339"
+    /// hide-time = true             # leave out the time
+    /// weekday = true               # start the time with the weekday
+    /// also-utc = true              # follow the time with the time in UTC
+    /// label-main-thread = true     # call the main thread `main` even without a name
+    /// target = true                # print targets
+    /// target-width = 20            # pad or truncate targets
+    /// auto-width = 30              # widen threads and targets as needed, up to 30
+    /// target-elapsed = true        # time since the last record of the target
+    /// target-counts = true         # number of records of the target so far
+    /// dim-target-prefixes = ["h2"] # dim the records of these targets
+    /// rainbow = true               # color messages along a rainbow
+    /// message-width = 200          # truncate messages
+    /// strip-escapes = true         # remove ANSI escapes from messages
+    /// line-ending = "crlf"         # or "lf", "nul"
+    /// severity-prefixes = true     # start lines with e.g. <6> for systemd
+    /// checksums = true             # end lines with their CRC-32
+    /// max-record-bytes = 1024      # cut longer records
+    /// kv = "json"                  # or "pairs", with the `kv` feature
+    /// human-durations = true       # elapsed_ms=1200 as elapsed_ms=1.2s, with `kv`
+    /// human-sizes = true           # size=3565158 as size=3.4MiB, with `kv`
+    /// human-size-keys = ["rss"]    # or humanize these keys instead
+    /// kv-value-len = 200           # cut off long values, with `kv`
+    /// kv-len = 1000                # leave out the key-values after 1000 bytes, with `kv`
+    /// kv-allow = ["user_id"]       # only render these key-values
+    /// kv-deny = ["request_body"]   # or render all but these
+    /// scopes = "indent"            # or "breadcrumbs"
+    /// correlation-id-key = "trace" # the key of the correlation id in JSON
+    /// layout = "json"              # or "line", JSON objects like `Format::cloudwatch`
+    ///
+    /// [fields]                     # added to every record
+    /// service = "api"
+    ///
+    /// [watch-targets]              # style the targets of these modules
+    /// payments = "on-blue"
+    ///
+    /// [json-keys]                  # rename the members of JSON objects
+    /// timestamp = "@timestamp"     # or level, target, thread, message
+    /// fields = "attributes"        # nest the key-values in this object
+    ///
+    /// [theme]                      # see `Theme::from_file`
+    /// error = "bold bright-red"
+    /// ```
+    ///
+    /// Unknown options and invalid values are errors that say what was expected.
+    pub fn from_config_file(path: impl Into<PathBuf>) -> Result<Self, ConfigError> {
+        read_toml(path.into())
+    }
+}
+
+#[cfg(feature = "toml")]
+impl Theme {
+    /// Reads a `Theme` from a TOML file. The styles are words separated by spaces: the
+    /// colors `black`, `red`, `green`, `yellow`, `blue`, `magenta`, `cyan` and `white`,
+    /// their `bright-` variants, `#rrggbb`, any of them after `on-` for the background,
+    /// and `bold`, `dimmed`, `italic`, `underline`, `blink`, `reversed`, `hidden` and
+    /// `strikethrough`. All styles are optional and keep their defaults when left out:
+    /// ```toml
+    /// error = "bold bright-red"
+    /// warn = "bold #ff8800"
+    /// info = "bold"
+    /// debug = ""
+    /// trace = "dimmed"
+    /// threads = ["blue", "magenta", "bold cyan"]
+    /// main-thread = "reversed"
+    /// time = "dimmed"
+    /// target = "green"
+    /// punctuation = "dimmed"
+    /// key = "dimmed"
+    /// value = "italic"
+    /// secondary = "dimmed"             # elapsed times, ticks, counts and scopes
+    /// correlation-id = "cyan"
+    /// dimmed = "bright-black"          # records of dimmed targets
+    /// ```
+    pub fn from_file(path: impl Into<PathBuf>) -> Result<Self, ConfigError> {
+        read_toml(path.into())
+    }
+}
+
+#[cfg(feature = "toml")]
+fn read_toml<T: serde::de::DeserializeOwned>(path: PathBuf) -> Result<T, ConfigError> {
+    let result = std::fs::read_to_string(&path)
+        .map_err(ConfigErrorKind::Read)
+        .and_then(|config| toml::from_str(&config).map_err(ConfigErrorKind::Parse));
+    result.map_err(|kind| ConfigError { path, kind })
+}
+
+#[cfg(all(test, feature = "toml"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_options() {
+        let format: Format = toml::from_str(
+            r#"
+            threads = true
+            time = "rfc3339"
+            target-width = 20
+            line-ending = "crlf"
+            "#,
+        )
+        .unwrap();
+        assert!(format.thread_names);
+        assert_eq!(format.timestamp, Timestamp::Rfc3339);
+        assert_eq!(format.target_width, Some(20));
+        assert_eq!(format.line_ending, LineEnding::CrLf);
+    }
+
+    #[test]
+    fn reports_the_file_and_the_invalid_option() {
+        let path =
+            std::env::temp_dir().join(format!("fern-format-{}.toml", std::process::id()));
+        std::fs::write(&path, "time = \"never\"\n").unwrap();
+        let error = Format::from_config_file(&path).unwrap_err().to_string();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.contains(&*path.to_string_lossy()), "{error}");
+        assert!(error.contains("never"), "{error}");
+    }
+
+    #[test]
+    fn reads_the_layout() {
+        let format: Format = toml::from_str(r#"layout = "json""#).unwrap();
+        assert_eq!(format.layout, Layout::Json);
+        assert!(toml::from_str::<Format>(r#"layout = "yaml""#).is_err());
+    }
+
+    #[test]
+    fn renames_json_members() {
+        let format: Format = toml::from_str(
+            r#"
+            layout = "json"
+            json-keys = { timestamp = "@timestamp", message = "msg" }
+            "#,
+        )
+        .unwrap();
+        let line = format.renderer().render_to_string(
+            &log::Record::builder()
+                .args(format_args!("hello"))
+                .target("app")
+                .build(),
+        );
+        assert!(line.contains(r#""@timestamp":"#), "{line}");
+        assert!(line.contains(r#""msg":"hello""#), "{line}");
+        assert!(line.contains(r#""target":"app""#), "{line}");
+    }
+
+    #[test]
+    fn reads_the_nested_fields_key() {
+        let format: Format = toml::from_str(
+            r#"
+            [json-keys]
+            fields = "attributes"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(format.json_keys.fields.as_deref(), Some("attributes"));
+    }
+
+    #[test]
+    fn reads_watched_targets() {
+        let format: Format = toml::from_str(
+            r##"watch-targets = { payments = "bold on-blue", db = "#ff8800" }"##,
+        )
+        .unwrap();
+        assert_eq!(
+            format.watched_targets,
+            [
+                (
+                    "db".to_string(),
+                    Style::new().color(DynColors::Rgb(0xff, 0x88, 0))
+                ),
+                ("payments".to_string(), Style::new().bold().on_blue()),
+            ]
+        );
+        assert!(toml::from_str::<Format>(r#"watch-targets = { db = "mauve" }"#).is_err());
+    }
+
+    #[test]
+    fn reads_dimmed_targets() {
+        let format: Format =
+            toml::from_str(r#"dim-target-prefixes = ["hyper", "h2"]"#).unwrap();
+        assert_eq!(format.dimmed_targets, ["hyper", "h2"]);
+    }
+
+    #[test]
+    fn reads_the_main_thread_label() {
+        let format: Format = toml::from_str("label-main-thread = true").unwrap();
+        assert!(format.label_main_thread && format.thread_names);
+    }
+
+    #[test]
+    fn reads_the_options_of_auto() {
+        let format: Format =
+            toml::from_str("hide-time = true\nseverity-prefixes = true").unwrap();
+        assert!(!format.show_time);
+        assert!(format.severity_prefixes);
+    }
+
+    #[test]
+    fn reads_checksums() {
+        let format: Format = toml::from_str("checksums = true").unwrap();
+        assert!(format.checksums);
+    }
+
+    #[test]
+    fn reads_the_record_budget() {
+        let format: Format = toml::from_str("max-record-bytes = 512").unwrap();
+        assert_eq!(format.max_record_bytes, Some(512));
+    }
+}
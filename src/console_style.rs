@@ -0,0 +1,178 @@
+use owo_colors::{AnsiColors, DynColors, Style, XtermColors};
+
+use crate::Theme;
+
+/// Converts `style` to a style of the console crate, so that the prompts of
+/// dialoguer and the like look like the logs:
+/// ```
+/// use fern_format::{to_console_style, Style};
+///
+/// let error = Style::new().bold().red();
+/// println!("{}", to_console_style(error).apply_to("failed"));
+/// ```
+///
+/// RGB colors become the closest of the 256 colors, since console has no others.
+pub fn to_console_style(style: Style) -> console::Style {
+    let color = |color| match color {
+        Color::Ansi(n) => (ANSI[n as usize % 8].1, n >= 8),
+        Color::Xterm(n) => (console::Color::Color256(n), false),
+        Color::Rgb(r, g, b) => (console::Color::Color256(xterm(r, g, b)), false),
+    };
+    sgr(&style.style("").to_string()).into_iter().fold(
+        console::Style::new(),
+        |console, sgr| match sgr {
+            Sgr::Effect(effect) => match effect {
+                1 => console.bold(),
+                2 => console.dim(),
+                3 => console.italic(),
+                4 => console.underlined(),
+                5 => console.blink(),
+                6 => console.blink_fast(),
+                7 => console.reverse(),
+                8 => console.hidden(),
+                9 => console.strikethrough(),
+                _ => console,
+            },
+            Sgr::Fg(fg) => match color(fg) {
+                (fg, true) => console.fg(fg).bright(),
+                (fg, false) => console.fg(fg),
+            },
+            Sgr::Bg(bg) => match color(bg) {
+                (bg, true) => console.bg(bg).on_bright(),
+                (bg, false) => console.bg(bg),
+            },
+        },
+    )
+}
+
+/// Converts a style of the console crate to a [`Style`], e.g. to give the records of a
+/// [`Theme`] the colors that a CLI already uses:
+/// ```
+/// use fern_format::{from_console_style, Theme};
+///
+/// let hint = console::Style::new().dim();
+/// let theme = Theme::new().secondary(from_console_style(&hint));
+/// ```
+pub fn from_console_style(style: &console::Style) -> Style {
+    let color = |color| match color {
+        Color::Ansi(n) if n < 8 => DynColors::Ansi(ANSI[n as usize].0),
+        Color::Ansi(n) => DynColors::Ansi(BRIGHT[n as usize % 8]),
+        Color::Xterm(n) => DynColors::Xterm(XtermColors::from(n)),
+        Color::Rgb(r, g, b) => DynColors::Rgb(r, g, b),
+    };
+    let sample = style.clone().force_styling(true).apply_to("").to_string();
+    sgr(&sample)
+        .into_iter()
+        .fold(Style::new(), |owo, sgr| match sgr {
+            Sgr::Effect(effect) => match effect {
+                1 => owo.bold(),
+                2 => owo.dimmed(),
+                3 => owo.italic(),
+                4 => owo.underline(),
+                5 => owo.blink(),
+                6 => owo.blink_fast(),
+                7 => owo.reversed(),
+                8 => owo.hidden(),
+                9 => owo.strikethrough(),
+                _ => owo,
+            },
+            Sgr::Fg(fg) => owo.color(color(fg)),
+            Sgr::Bg(bg) => owo.on_color(color(bg)),
+        })
+}
+
+impl Theme {
+    /// The style of the messages of `level` as a style of the console crate, see
+    /// [`to_console_style`]
+    pub fn console_level_style(&self, level: log::Level) -> console::Style {
+        to_console_style(self.level_style(level, false))
+    }
+}
+
+/// The basic colors of both crates, in the order of their SGR parameters
+const ANSI: [(AnsiColors, console::Color); 8] = [
+    (AnsiColors::Black, console::Color::Black),
+    (AnsiColors::Red, console::Color::Red),
+    (AnsiColors::Green, console::Color::Green),
+    (AnsiColors::Yellow, console::Color::Yellow),
+    (AnsiColors::Blue, console::Color::Blue),
+    (AnsiColors::Magenta, console::Color::Magenta),
+    (AnsiColors::Cyan, console::Color::Cyan),
+    (AnsiColors::White, console::Color::White),
+];
+
+const BRIGHT: [AnsiColors; 8] = [
+    AnsiColors::BrightBlack,
+    AnsiColors::BrightRed,
+    AnsiColors::BrightGreen,
+    AnsiColors::BrightYellow,
+    AnsiColors::BrightBlue,
+    AnsiColors::BrightMagenta,
+    AnsiColors::BrightCyan,
+    AnsiColors::BrightWhite,
+];
+
+/// What an SGR parameter sets
+#[derive(Clone, Copy)]
+enum Sgr {
+    /// Bold, dimmed and so on, by their parameters 1 to 9
+    Effect(u8),
+    Fg(Color),
+    Bg(Color),
+}
+
+#[derive(Clone, Copy)]
+enum Color {
+    /// One of the 8 basic colors, or 8 to 15 for their bright variants
+    Ansi(u8),
+    Xterm(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// What the SGR sequences in `escapes` set, e.g. `\x1b[1m\x1b[31m` or `\x1b[1;31m`
+fn sgr(escapes: &str) -> Vec<Sgr> {
+    let mut params = escapes
+        .split('\x1b')
+        .filter_map(|sequence| sequence.strip_prefix('[')?.strip_suffix('m'))
+        .flat_map(|sequence| sequence.split(';'))
+        .map(|param| param.parse::<u8>().unwrap_or(0));
+    let mut sgr = Vec::new();
+    while let Some(param) = params.next() {
+        sgr.push(match param {
+            1..=9 => Sgr::Effect(param),
+            30..=37 => Sgr::Fg(Color::Ansi(param - 30)),
+            90..=97 => Sgr::Fg(Color::Ansi(param - 90 + 8)),
+            40..=47 => Sgr::Bg(Color::Ansi(param - 40)),
+            100..=107 => Sgr::Bg(Color::Ansi(param - 100 + 8)),
+            38 => match extended_color(&mut params) {
+                Some(color) => Sgr::Fg(color),
+                None => continue,
+            },
+            48 => match extended_color(&mut params) {
+                Some(color) => Sgr::Bg(color),
+                None => continue,
+            },
+            _ => continue,
+        });
+    }
+    sgr
+}
+
+/// The color of `5;N` or `2;R;G;B` after 38 or 48
+fn extended_color(params: &mut impl Iterator<Item = u8>) -> Option<Color> {
+    match params.next()? {
+        5 => Some(match params.next()? {
+            n @ 0..=15 => Color::Ansi(n),
+            n => Color::Xterm(n),
+        }),
+        2 => Some(Color::Rgb(params.next()?, params.next()?, params.next()?)),
+        _ => None,
+    }
+}
+
+/// The closest color of the 6x6x6 cube of the 256 colors
+fn xterm(r: u8, g: u8, b: u8) -> u8 {
+    // the cube has the levels 0, 95, 135, 175, 215 and 255
+    let level = |v: u8| if v < 75 { 0 } else { (v - 35) / 40 };
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
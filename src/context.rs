@@ -0,0 +1,236 @@
+//! A mapped diagnostic context: key-values that are stored per thread and rendered on
+//! every record logged from that thread, together with the key-values of the record.
+//!
+//! ```
+//! use fern_format::context;
+//!
+//! let _guard = context::scoped("request_id", 1234);
+//! log::info!("handling request"); // ... request_id=1234
+//! ```
+//!
+//! Async tasks can move between threads at every `.await`, so with the `tokio` feature
+//! a future can instead be given its own context with
+//! [`with_log_context`](WithLogContext::with_log_context). All functions in this module
+//! then operate on the context of the task instead of the thread.
+//!
+//! With the `log-mdc` feature the entries of the [`log_mdc`] map of the thread are
+//! rendered as well, unless this context has the same key.
+
+use std::{cell::RefCell, fmt::Display, marker::PhantomData};
+
+type Context = Vec<(String, String)>;
+
+thread_local! {
+    static CONTEXT: RefCell<Context> = const { RefCell::new(Vec::new()) };
+}
+
+#[cfg(feature = "tokio")]
+tokio::task_local! {
+    static TASK_CONTEXT: TaskContext;
+}
+
+/// Sets `key` to `value` for all following records logged from this thread. Returns the
+/// previous value, if any.
+pub fn insert(key: impl Into<String>, value: impl Display) -> Option<String> {
+    let key = key.into();
+    let value = value.to_string();
+    with_context_mut(|context| set(context, key, value))
+}
+
+/// Removes `key` from the context of this thread, returning its value
+pub fn remove(key: &str) -> Option<String> {
+    with_context_mut(|context| {
+        let i = position(context, key).ok()?;
+        Some(context.remove(i).1)
+    })
+}
+
+/// The current value of `key` in the context of this thread
+pub fn get(key: &str) -> Option<String> {
+    with_context_mut(|context| {
+        let i = position(context, key).ok()?;
+        Some(context[i].1.clone())
+    })
+}
+
+/// Removes everything from the context of this thread
+pub fn clear() {
+    with_context_mut(|context| context.clear());
+}
+
+/// Like [`insert`], but the previous value is restored when the returned guard is
+/// dropped
+pub fn scoped(key: impl Into<String>, value: impl Display) -> Guard {
+    let key = key.into();
+    let previous = insert(key.clone(), value);
+    Guard {
+        key,
+        previous,
+        not_send: PhantomData,
+    }
+}
+
+/// Restores a key in the context when dropped, see [`scoped`]. It restores the context
+/// of the thread it was created on, so it can't be sent to another one:
+/// ```compile_fail
+/// let guard = fern_format::context::scoped("request_id", 1234);
+/// std::thread::spawn(move || drop(guard));
+/// ```
+#[must_use = "the key is removed from the context again when the guard is dropped"]
+pub struct Guard {
+    key: String,
+    previous: Option<String>,
+    not_send: PhantomData<*const ()>,
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        match self.previous.take() {
+            Some(previous) => insert(std::mem::take(&mut self.key), previous),
+            None => remove(&self.key),
+        };
+    }
+}
+
+/// The key the correlation id is stored under in the context
+pub const CORRELATION_ID: &str = "correlation_id";
+
+/// Sets the correlation id that ties together the records of one request, also across
+/// services. It is stored in the context like any other key, but is shown in a dedicated
+/// place of the line. Returns the previous id, if any.
+pub fn set_correlation_id(id: impl Display) -> Option<String> {
+    insert(CORRELATION_ID, id)
+}
+
+/// Like [`set_correlation_id`], but the previous id is restored when the returned guard
+/// is dropped
+pub fn scoped_correlation_id(id: impl Display) -> Guard {
+    scoped(CORRELATION_ID, id)
+}
+
+/// The current correlation id, see [`set_correlation_id`]
+pub fn correlation_id() -> Option<String> {
+    get(CORRELATION_ID)
+}
+
+/// Calls `f` with the context of this task or thread, sorted by key
+pub(crate) fn with<R>(f: impl FnOnce(&[(String, String)]) -> R) -> R {
+    crate::non_blocking::with_origin(|origin| match origin {
+        Some(origin) => f(&origin.context),
+        None => with_own(f),
+    })
+}
+
+/// Like [`with`], but ignores the origin of records formatted by a
+/// [`NonBlocking`](crate::NonBlocking) worker
+fn with_own<R>(f: impl FnOnce(&[(String, String)]) -> R) -> R {
+    #[cfg(feature = "log-mdc")]
+    let f = |context: &[(String, String)]| with_mdc(context, f);
+
+    #[cfg(feature = "tokio")]
+    if in_task_context() {
+        return TASK_CONTEXT.with(|context| match context.0.try_borrow() {
+            Ok(context) => f(&context),
+            Err(_) => f(&[]),
+        });
+    }
+
+    CONTEXT.with(|context| match context.try_borrow() {
+        Ok(context) => f(&context),
+        // something in the context is being logged while it is modified
+        Err(_) => f(&[]),
+    })
+}
+
+/// Calls `f` with `context` and the entries of the [`log_mdc`] map merged into it. The
+/// map is usually empty, so `context` is only copied if it isn't.
+#[cfg(feature = "log-mdc")]
+fn with_mdc<R>(
+    context: &[(String, String)],
+    f: impl FnOnce(&[(String, String)]) -> R,
+) -> R {
+    let mut merged: Option<Context> = None;
+    log_mdc::iter(|key, value| {
+        let merged = merged.get_or_insert_with(|| context.to_vec());
+        if let Err(i) = position(merged, key) {
+            merged.insert(i, (key.to_string(), value.to_string()));
+        }
+    });
+    match merged {
+        Some(merged) => f(&merged),
+        None => f(context),
+    }
+}
+
+/// Calls `f` with the context of the current task, or thread if not in a task with a
+/// context
+fn with_context_mut<R>(f: impl FnOnce(&mut Context) -> R) -> R {
+    #[cfg(feature = "tokio")]
+    if in_task_context() {
+        return TASK_CONTEXT.with(|context| f(&mut context.0.borrow_mut()));
+    }
+
+    CONTEXT.with_borrow_mut(f)
+}
+
+fn position(context: &Context, key: &str) -> Result<usize, usize> {
+    context.binary_search_by(|(k, _)| k.as_str().cmp(key))
+}
+
+fn set(context: &mut Context, key: String, value: String) -> Option<String> {
+    match position(context, &key) {
+        Ok(i) => Some(std::mem::replace(&mut context[i].1, value)),
+        Err(i) => {
+            context.insert(i, (key, value));
+            None
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+fn in_task_context() -> bool {
+    TASK_CONTEXT.try_with(|_| ()).is_ok()
+}
+
+/// The context of a task, see [`WithLogContext`]
+#[cfg(feature = "tokio")]
+pub struct TaskContext(RefCell<Context>);
+
+/// Gives futures their own context that follows them across `.await` points, instead of
+/// using the context of whatever thread they happen to be polled on
+///
+/// ```ignore
+/// use fern_format::context::{self, WithLogContext};
+///
+/// tokio::spawn(
+///     async move {
+///         log::info!("handling request"); // ... request_id=1234
+///         context::insert("user", "alice");
+///     }
+///     .with_log_context([("request_id", 1234)]),
+/// );
+/// ```
+#[cfg(feature = "tokio")]
+pub trait WithLogContext: std::future::Future + Sized {
+    /// Runs this future with a context that starts out with `fields` on top of what is
+    /// in the context of the current task, if any
+    fn with_log_context<K, V>(
+        self,
+        fields: impl IntoIterator<Item = (K, V)>,
+    ) -> tokio::task::futures::TaskLocalFuture<TaskContext, Self>
+    where
+        K: Into<String>,
+        V: Display,
+    {
+        let mut context = TASK_CONTEXT
+            .try_with(|context| context.0.borrow().clone())
+            .unwrap_or_default();
+        for (key, value) in fields {
+            set(&mut context, key.into(), value.to_string());
+        }
+        TASK_CONTEXT.scope(TaskContext(RefCell::new(context)), self)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<F: std::future::Future> WithLogContext for F {}
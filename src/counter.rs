@@ -0,0 +1,47 @@
+use std::{collections::HashMap, fmt::Display};
+
+use owo_colors::Style;
+
+use crate::{
+    lock::{lock, Mutex},
+    styled::Styled,
+};
+
+/// How many records each target has logged
+#[derive(Default)]
+pub(crate) struct TargetCounter {
+    counts: Mutex<HashMap<String, u64>>,
+}
+
+impl TargetCounter {
+    /// Counts a record of `target`, and returns how many there have been
+    pub(crate) fn count(&self, target: &str) -> u64 {
+        let mut counts = lock(&self.counts);
+        match counts.get_mut(target) {
+            Some(count) => {
+                *count += 1;
+                *count
+            }
+            None => {
+                counts.insert(target.to_string(), 1);
+                1
+            }
+        }
+    }
+}
+
+/// ` #12`, if records are counted
+pub(crate) struct Count {
+    pub(crate) count: Option<u64>,
+    pub(crate) style: Option<Style>,
+}
+
+impl Display for Count {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(count) = self.count else {
+            return Ok(());
+        };
+
+        write!(f, " {}", Styled(format_args!("#{}", count), self.style))
+    }
+}
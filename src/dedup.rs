@@ -0,0 +1,175 @@
+use std::time::{Duration, Instant};
+
+use crate::lock::{lock, Mutex};
+
+/// A [`log::Log`] that collapses consecutive identical records into the first occurrence
+/// and a `last message repeated N times` summary, like syslogd does.
+///
+/// It compares the unformatted messages, so it has to sit in front of the dispatch that
+/// does the formatting:
+/// ```no_run
+/// use fern_format::{Dedup, Format};
+///
+/// let (_, output) = fern::Dispatch::new()
+///     .format(Format::new().callback())
+///     .chain(std::io::stdout())
+///     .into_log();
+///
+/// fern::Dispatch::new()
+///     .level(log::LevelFilter::Info)
+///     .chain(Dedup::new(output))
+///     .apply()
+///     .unwrap();
+/// ```
+pub struct Dedup<L> {
+    inner: L,
+    max_repeats: Option<u64>,
+    max_delay: Option<Duration>,
+    last: Mutex<Option<Repeated>>,
+}
+
+/// The last record that was let through and how many times it has been suppressed since
+struct Repeated {
+    level: log::Level,
+    target: String,
+    message: String,
+    count: u64,
+    since: Instant,
+}
+
+impl Repeated {
+    fn new(record: &log::Record<'_>, message: String) -> Self {
+        Self {
+            level: record.level(),
+            target: record.target().to_string(),
+            message,
+            count: 0,
+            since: Instant::now(),
+        }
+    }
+
+    fn matches(&self, record: &log::Record<'_>, message: &str) -> bool {
+        self.level == record.level()
+            && self.target == record.target()
+            && self.message == message
+    }
+
+    /// The summary of the suppressed records, if there are any, which starts the count
+    /// over
+    fn take_summary(&mut self) -> Option<Summary> {
+        (self.count > 0).then(|| Summary {
+            level: self.level,
+            target: self.target.clone(),
+            count: std::mem::take(&mut self.count),
+        })
+    }
+}
+
+/// A `last message repeated N times` record, which is logged once the lock on the last
+/// record is released, so that the inner logger never runs while it is held
+struct Summary {
+    level: log::Level,
+    target: String,
+    count: u64,
+}
+
+impl<L: log::Log> Dedup<L> {
+    /// Wraps `inner`. A summary is printed at the latest 30 seconds after the first
+    /// suppressed record, and there is no limit on the number of repeats.
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            max_repeats: None,
+            max_delay: Some(Duration::from_secs(30)),
+            last: Mutex::new(None),
+        }
+    }
+
+    /// Print the summary after this many suppressed records, even if they keep coming
+    pub fn max_repeats(mut self, repeats: u64) -> Self {
+        self.max_repeats = Some(repeats.max(1));
+        self
+    }
+
+    /// Print the summary when this much time has passed since the first suppressed
+    /// record. Note that the summary is only printed when the next record arrives or on
+    /// [`log::Log::flush`].
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = Some(delay);
+        self
+    }
+
+    /// Never print the summary because of time, only because of
+    /// [`max_repeats`](Self::max_repeats) or a different record
+    pub fn no_max_delay(mut self) -> Self {
+        self.max_delay = None;
+        self
+    }
+
+    fn threshold_reached(&self, repeated: &Repeated) -> bool {
+        self.max_repeats.is_some_and(|max| repeated.count >= max)
+            || self
+                .max_delay
+                .is_some_and(|max| repeated.since.elapsed() >= max)
+    }
+
+    fn log_summary(&self, summary: Option<Summary>) {
+        let Some(summary) = summary else {
+            return;
+        };
+
+        self.inner.log(
+            &log::Record::builder()
+                .level(summary.level)
+                .target(&summary.target)
+                .args(format_args!(
+                    "last message repeated {} times",
+                    summary.count
+                ))
+                .build(),
+        );
+    }
+}
+
+impl<L: log::Log> log::Log for Dedup<L> {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let message = record.args().to_string();
+        let mut last = lock(&self.last);
+
+        if let Some(repeated) = last.as_mut().filter(|r| r.matches(record, &message)) {
+            if repeated.count == 0 {
+                repeated.since = Instant::now();
+            }
+            repeated.count += 1;
+            let summary = self
+                .threshold_reached(repeated)
+                .then(|| repeated.take_summary())
+                .flatten();
+            drop(last);
+            self.log_summary(summary);
+            return;
+        }
+
+        let summary = last.as_mut().and_then(Repeated::take_summary);
+        *last = Some(Repeated::new(record, message));
+        drop(last);
+        self.log_summary(summary);
+        self.inner.log(record);
+    }
+
+    fn flush(&self) {
+        let summary = lock(&self.last).as_mut().and_then(Repeated::take_summary);
+        self.log_summary(summary);
+        self.inner.flush();
+    }
+}
+
+impl<L: log::Log + 'static> From<Dedup<L>> for fern::Output {
+    fn from(dedup: Dedup<L>) -> Self {
+        (Box::new(dedup) as Box<dyn log::Log>).into()
+    }
+}
@@ -0,0 +1,17 @@
+/// The rule on each side of the title of a [`divider`]
+const RULE: &str = "────────";
+
+/// Logs a separator line with `title` in the middle, to delimit phases in long outputs:
+/// ```
+/// fern_format::divider("phase: migration");
+/// // ... [INFO] divider: ──────── phase: migration ────────
+/// ```
+///
+/// The record has the target `divider` and is logged at info, so it is formatted like any
+/// other record. An empty title gives an unbroken line.
+pub fn divider(title: &str) {
+    match title {
+        "" => log::info!(target: "divider", "{}{}", RULE, RULE),
+        title => log::info!(target: "divider", "{} {} {}", RULE, title, RULE),
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    time::{Duration, SystemTime},
+};
+
+use owo_colors::Style;
+
+use crate::{
+    lock::{lock, Mutex},
+    non_blocking::with_origin,
+    styled::Styled,
+};
+
+/// When each target logged last
+#[derive(Default)]
+pub(crate) struct TargetClock {
+    last: Mutex<HashMap<String, SystemTime>>,
+}
+
+impl TargetClock {
+    /// The time since the previous record of `target`, which is then updated to now
+    pub(crate) fn elapsed(&self, target: &str) -> Option<Duration> {
+        let now =
+            with_origin(|origin| origin.map(|o| o.time)).unwrap_or_else(SystemTime::now);
+        let mut last = lock(&self.last);
+        match last.get_mut(target) {
+            Some(previous) => {
+                let elapsed = now.duration_since(*previous).unwrap_or_default();
+                *previous = now;
+                Some(elapsed)
+            }
+            None => {
+                last.insert(target.to_string(), now);
+                None
+            }
+        }
+    }
+}
+
+/// ` +12ms`, nothing for the first record of a target
+pub(crate) struct Elapsed {
+    pub(crate) elapsed: Option<Duration>,
+    pub(crate) style: Option<Style>,
+}
+
+impl Display for Elapsed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(elapsed) = self.elapsed else {
+            return Ok(());
+        };
+
+        write!(
+            f,
+            " {}",
+            Styled(format_args!("+{}", Short(elapsed)), self.style)
+        )
+    }
+}
+
+/// A duration in its largest unit with at most one decimal, e.g. `850µs`, `12ms` or
+/// `1.5s`
+pub(crate) struct Short(pub(crate) Duration);
+
+impl Display for Short {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let micros = self.0.as_micros();
+        if micros < 1_000 {
+            write!(f, "{}µs", micros)
+        } else if micros < 10_000 {
+            write!(f, "{:.1}ms", micros as f64 / 1e3)
+        } else if micros < 1_000_000 {
+            write!(f, "{}ms", micros / 1_000)
+        } else if micros < 60_000_000 {
+            write!(f, "{:.1}s", micros as f64 / 1e6)
+        } else {
+            write!(f, "{}m{}s", micros / 60_000_000, micros / 1_000_000 % 60)
+        }
+    }
+}
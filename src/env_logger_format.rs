@@ -0,0 +1,34 @@
+//! Using the format with env_logger instead of fern
+
+use std::{io::Write, sync::Arc};
+
+use crate::Format;
+
+impl Format {
+    /// The format function to give to [`env_logger::Builder::format`], for projects that
+    /// configure their logging with env_logger. env_logger writes to stderr by default,
+    /// so detect color support there:
+    /// ```no_run
+    /// use fern_format::{Format, Stream};
+    ///
+    /// env_logger::Builder::from_default_env()
+    ///     .format(Format::new().color_if_supported(Stream::Stderr).env_logger())
+    ///     .init();
+    /// ```
+    pub fn env_logger(
+        self,
+    ) -> impl Fn(&mut env_logger::fmt::Formatter, &log::Record<'_>) -> std::io::Result<()>
+           + Send
+           + Sync
+           + 'static {
+        let line_ending = self.get_line_ending().as_str();
+        let renderer = Arc::new(self.renderer());
+        move |buf, record| {
+            renderer
+                .render(record, record.args(), |line| {
+                    write!(buf, "{}{}", line, line_ending)
+                })
+                .unwrap_or(Ok(()))
+        }
+    }
+}
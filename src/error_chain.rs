@@ -0,0 +1,36 @@
+use std::{error::Error, fmt::Display};
+
+/// Displays an error followed by its sources, one per indented `caused by:` line, so
+/// that logging an error shows why it happened and not only the outermost message:
+/// ```
+/// use fern_format::ErrorChain;
+///
+/// # fn save() -> Result<(), Box<dyn std::error::Error>> { Ok(()) }
+/// if let Err(error) = save() {
+///     log::error!("failed to save: {}", ErrorChain::new(&*error));
+///     // failed to save: could not write config
+///     //   caused by: disk full
+/// }
+/// ```
+#[derive(Clone, Copy)]
+pub struct ErrorChain<'a> {
+    error: &'a (dyn Error + 'a),
+}
+
+impl<'a> ErrorChain<'a> {
+    pub fn new(error: &'a (dyn Error + 'a)) -> Self {
+        Self { error }
+    }
+}
+
+impl Display for ErrorChain<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)?;
+        let mut source = self.error.source();
+        while let Some(error) = source {
+            write!(f, "\n  caused by: {}", error)?;
+            source = error.source();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,262 @@
+use std::fmt::{Display, Write};
+
+use owo_colors::Style;
+
+use crate::{context::CORRELATION_ID, styled::Styled};
+
+/// Styles of key-values when they are rendered as pairs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct KvStyles {
+    pub(crate) key: Style,
+    pub(crate) value: Style,
+}
+
+impl Default for KvStyles {
+    fn default() -> Self {
+        Self {
+            key: Style::new().dimmed(),
+            value: Style::new(),
+        }
+    }
+}
+
+/// Which keys of key-values are rendered
+#[derive(Clone, Debug, Default)]
+pub(crate) enum KvFilter {
+    #[default]
+    All,
+    Allow(Vec<String>),
+    Deny(Vec<String>),
+}
+
+impl KvFilter {
+    pub(crate) fn allows(&self, key: &str) -> bool {
+        match self {
+            KvFilter::All => true,
+            KvFilter::Allow(keys) => keys.iter().any(|k| k == key),
+            KvFilter::Deny(keys) => !keys.iter().any(|k| k == key),
+        }
+    }
+}
+
+/// Constant key-values that are added to every record
+pub(crate) type StaticFields = Vec<(String, String)>;
+
+/// Key-values that come from somewhere else than the record itself
+#[derive(Clone, Copy)]
+pub(crate) struct Fields<'a> {
+    statics: &'a [(String, String)],
+    /// Without the correlation id
+    context: &'a [(String, String)],
+    pub(crate) correlation_id: Option<&'a str>,
+    /// What to call the correlation id in structured output
+    pub(crate) correlation_key: &'a str,
+}
+
+impl<'a> Fields<'a> {
+    pub(crate) fn new(
+        statics: &'a [(String, String)],
+        context: &'a [(String, String)],
+        correlation_key: &'a str,
+    ) -> Self {
+        Self {
+            statics,
+            context,
+            correlation_id: context
+                .iter()
+                .find(|(k, _)| k == CORRELATION_ID)
+                .map(|(_, v)| v.as_str()),
+            correlation_key,
+        }
+    }
+
+    #[cfg(feature = "kv")]
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        self.statics
+            .iter()
+            .chain(self.context.iter().filter(|(k, _)| k != CORRELATION_ID))
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Like [`iter`](Self::iter), but sorted by key. Both kinds of fields are kept
+    /// sorted, so they are merged instead of collected and sorted.
+    #[cfg(not(feature = "kv"))]
+    pub(crate) fn iter_sorted(&self) -> impl Iterator<Item = (&'a str, &'a str)> {
+        let as_strs = |(k, v): &'a (String, String)| (k.as_str(), v.as_str());
+        let mut statics = self.statics.iter().map(as_strs).peekable();
+        let mut context = self
+            .context
+            .iter()
+            .filter(|(k, _)| k != CORRELATION_ID)
+            .map(as_strs)
+            .peekable();
+        std::iter::from_fn(move || match (statics.peek(), context.peek()) {
+            (Some(s), Some(c)) if c.0 < s.0 => context.next(),
+            (Some(_), _) => statics.next(),
+            (None, _) => context.next(),
+        })
+    }
+}
+
+/// Renders the correlation id of [`Fields`] as ` #id`
+pub(crate) struct CorrelationId<'a> {
+    id: Option<&'a str>,
+    style: Option<Style>,
+}
+
+impl<'a> CorrelationId<'a> {
+    pub(crate) fn new(id: Option<&'a str>, style: Option<Style>) -> Self {
+        Self { id, style }
+    }
+}
+
+impl<'a> Display for CorrelationId<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(id) = self.id else {
+            return Ok(());
+        };
+
+        write!(f, " {}", Styled(format_args!("#{}", id), self.style))
+    }
+}
+
+/// Renders the [`Fields`] as ` key=value` pairs, sorted by key. This is only used when
+/// the key-values of the records themselves aren't rendered.
+#[cfg(not(feature = "kv"))]
+pub(crate) struct FieldPairs<'a> {
+    fields: Fields<'a>,
+    filter: &'a KvFilter,
+    styles: Option<KvStyles>,
+}
+
+#[cfg(not(feature = "kv"))]
+impl<'a> FieldPairs<'a> {
+    pub(crate) fn new(
+        fields: Fields<'a>,
+        filter: &'a KvFilter,
+        styles: Option<KvStyles>,
+    ) -> Self {
+        Self {
+            fields,
+            filter,
+            styles,
+        }
+    }
+}
+
+#[cfg(not(feature = "kv"))]
+impl<'a> Display for FieldPairs<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pairs = self
+            .fields
+            .iter_sorted()
+            .filter(|(key, _)| self.filter.allows(key));
+        for (key, value) in pairs {
+            write_pair(f, key, Quoted(value), self.styles)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the correlation id and the [`Fields`] that pass `filter` as `"key":"value"`
+/// members of a JSON object, sorted by key. `first` is if nothing has been written into
+/// the object yet, otherwise a comma is written first.
+#[cfg(not(feature = "kv"))]
+pub(crate) fn write_json_members(
+    f: &mut std::fmt::Formatter<'_>,
+    fields: Fields<'_>,
+    filter: &KvFilter,
+    first: bool,
+) -> std::fmt::Result {
+    let correlation_id = fields.correlation_id.map(|id| (fields.correlation_key, id));
+    let pairs = fields.iter_sorted().filter(|(key, _)| filter.allows(key));
+    for (i, (key, value)) in correlation_id.into_iter().chain(pairs).enumerate() {
+        if i > 0 || !first {
+            f.write_char(',')?;
+        }
+        crate::json::write_str(f, key)?;
+        f.write_char(':')?;
+        crate::json::write_str(f, value)?;
+    }
+    Ok(())
+}
+
+/// Writes ` key=value`
+pub(crate) fn write_pair(
+    f: &mut impl Write,
+    key: impl Display,
+    value: impl Display,
+    styles: Option<KvStyles>,
+) -> std::fmt::Result {
+    write!(
+        f,
+        " {}={}",
+        Styled(key, styles.map(|s| s.key)),
+        Styled(value, styles.map(|s| s.value))
+    )
+}
+
+/// A value that is quoted if needed, see [`write_quoted`]
+pub(crate) struct Quoted<T>(pub(crate) T);
+
+impl<T: Display> Display for Quoted<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_quoted(f, &self.0)
+    }
+}
+
+/// Writes `value` as is if it can't be confused with the surrounding pairs, and as a
+/// quoted and escaped string otherwise. It is formatted twice to find out which,
+/// instead of into a `String`.
+fn write_quoted(
+    f: &mut std::fmt::Formatter<'_>,
+    value: impl Display,
+) -> std::fmt::Result {
+    let mut check = NeedsQuotes {
+        empty: true,
+        needs_quotes: false,
+    };
+    write!(check, "{}", value)?;
+    if !check.empty && !check.needs_quotes {
+        return write!(f, "{}", value);
+    }
+
+    f.write_char('"')?;
+    write!(Escape(&mut *f), "{}", value)?;
+    f.write_char('"')
+}
+
+struct NeedsQuotes {
+    empty: bool,
+    needs_quotes: bool,
+}
+
+impl Write for NeedsQuotes {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.empty &= s.is_empty();
+        self.needs_quotes |= s
+            .chars()
+            .any(|c| c == ' ' || c == '=' || c == '"' || c.is_control());
+        Ok(())
+    }
+}
+
+/// Escapes everything written to it for use inside of a quoted value
+struct Escape<W>(W);
+
+impl<W: Write> Write for Escape<W> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        for c in s.chars() {
+            match c {
+                '"' => self.0.write_str("\\\"")?,
+                '\\' => self.0.write_str("\\\\")?,
+                '\n' => self.0.write_str("\\n")?,
+                '\r' => self.0.write_str("\\r")?,
+                '\t' => self.0.write_str("\\t")?,
+                c if c.is_control() => write!(self.0, "\\u{{{:x}}}", c as u32)?,
+                c => self.0.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+//! Using the format with flexi_logger instead of fern
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    RwLock,
+};
+
+use flexi_logger::{DeferredNow, FormatFunction};
+
+use crate::{report::report, Format, Renderer};
+
+/// How many formats can be in use at once, see [`Format::flexi_logger`]
+const SLOTS: usize = 16;
+
+/// flexi_logger only takes plain functions, so the formats live here, one for each of
+/// the [`FUNCTIONS`]
+static RENDERERS: [RwLock<Option<Renderer>>; SLOTS] =
+    [const { RwLock::new(None) }; SLOTS];
+
+/// The slot of the next format
+static NEXT_SLOT: AtomicUsize = AtomicUsize::new(0);
+
+/// A function for each slot of [`RENDERERS`]
+const FUNCTIONS: [FormatFunction; SLOTS] = [
+    format::<0>,
+    format::<1>,
+    format::<2>,
+    format::<3>,
+    format::<4>,
+    format::<5>,
+    format::<6>,
+    format::<7>,
+    format::<8>,
+    format::<9>,
+    format::<10>,
+    format::<11>,
+    format::<12>,
+    format::<13>,
+    format::<14>,
+    format::<15>,
+];
+
+impl Format {
+    /// The format function to give to [`flexi_logger::Logger::format`], e.g. to combine
+    /// this format with the file rotation of flexi_logger:
+    /// ```no_run
+    /// use fern_format::Format;
+    /// use flexi_logger::{FileSpec, Logger};
+    ///
+    /// let _logger = Logger::try_with_str("info")
+    ///     .unwrap()
+    ///     .log_to_file(FileSpec::default())
+    ///     .format_for_files(Format::new().flexi_logger())
+    ///     .format_for_stderr(Format::new().force_colors().flexi_logger())
+    ///     .start()
+    ///     .unwrap();
+    /// ```
+    ///
+    /// flexi_logger only takes plain functions, so each call returns one of 16 functions
+    /// with a format of its own. After that they are reused from the first one on, which
+    /// replaces the format of the function that was returned 16 calls earlier.
+    pub fn flexi_logger(self) -> FormatFunction {
+        let slot = NEXT_SLOT.fetch_add(1, Ordering::Relaxed) % SLOTS;
+        let mut renderer = RENDERERS[slot].write().unwrap_or_else(|e| e.into_inner());
+        if renderer.is_some() {
+            report(format_args!(
+                "More than {SLOTS} flexi_logger formats, replacing the oldest"
+            ));
+        }
+        *renderer = Some(self.renderer());
+        FUNCTIONS[slot]
+    }
+}
+
+fn format<const SLOT: usize>(
+    w: &mut dyn std::io::Write,
+    _now: &mut DeferredNow,
+    record: &log::Record<'_>,
+) -> std::io::Result<()> {
+    let renderer = RENDERERS[SLOT].read().unwrap_or_else(|e| e.into_inner());
+    match &*renderer {
+        // flexi_logger ends the lines itself
+        Some(renderer) => renderer
+            .render(record, record.args(), |line| write!(w, "{}", line))
+            .unwrap_or(Ok(())),
+        None => write!(w, "{}", record.args()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatted(function: FormatFunction) -> String {
+        let mut line = Vec::new();
+        let record = log::Record::builder()
+            .args(format_args!("hello"))
+            .level(log::Level::Info)
+            .target("app")
+            .build();
+        function(&mut line, &mut DeferredNow::new(), &record).unwrap();
+        String::from_utf8(line).unwrap()
+    }
+
+    #[test]
+    fn functions_keep_their_formats() {
+        let plain = Format::new().hide_time().flexi_logger();
+        let without_target = Format::new().hide_time().hide_targets().flexi_logger();
+        assert_eq!(formatted(plain), "[INFO] app: hello");
+        assert_eq!(formatted(without_target), "[INFO] hello");
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    fmt::{Display, Write as _},
+    io::{self, Write},
+    sync::Mutex,
+};
+
+use log::LevelFilter;
+
+use crate::{report::report, sanitize::StripEscapes, scope};
+
+/// Writes records to stdout as workflow commands of GitHub Actions, so that errors and
+/// warnings show up as annotations on the run and its pull request. Records below the
+/// [`annotate`](Self::annotate) level are written as plain lines. Chain it instead of
+/// stdout when running in a workflow:
+/// ```no_run
+/// use fern_format::{Format, GithubActions};
+///
+/// let output: fern::Output = if GithubActions::detected() {
+///     GithubActions::new().groups(true).into()
+/// } else {
+///     std::io::stdout().into()
+/// };
+/// fern::Dispatch::new()
+///     .format(Format::new().callback())
+///     .chain(output)
+///     .apply()
+///     .unwrap();
+/// ```
+pub struct GithubActions {
+    state: Mutex<State>,
+    annotate: LevelFilter,
+    groups: bool,
+}
+
+struct State {
+    stdout: io::Stdout,
+    /// The scope whose group is open
+    group: Option<String>,
+}
+
+impl GithubActions {
+    /// If the program runs in a workflow of GitHub Actions
+    pub fn detected() -> bool {
+        std::env::var_os("GITHUB_ACTIONS").is_some_and(|v| v == "true")
+    }
+
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(State {
+                stdout: io::stdout(),
+                group: None,
+            }),
+            annotate: LevelFilter::Warn,
+            groups: false,
+        }
+    }
+
+    /// Annotate records at `level` and above, errors with `::error`, warnings with
+    /// `::warning` and everything else with `::notice`. Defaults to warnings.
+    pub fn annotate(mut self, level: LevelFilter) -> Self {
+        self.annotate = level;
+        self
+    }
+
+    /// Put the records of each outermost [`scope`](crate::scope) into a collapsible
+    /// group of the log. Groups can't be nested, so inner scopes get no groups of their
+    /// own.
+    pub fn groups(mut self, enable: bool) -> Self {
+        self.groups = enable;
+        self
+    }
+
+    fn write(&self, state: &mut State, record: &log::Record<'_>) -> io::Result<()> {
+        let mut out = String::with_capacity(256);
+        if self.groups {
+            scope::with_outermost(|scope| {
+                if scope != state.group.as_deref() {
+                    if state.group.is_some() {
+                        out.push_str("::endgroup::\n");
+                    }
+                    state.group = scope.map(str::to_string);
+                    if let Some(scope) = scope {
+                        let _ = writeln!(out, "::group::{}", Data(scope));
+                    }
+                }
+            });
+        }
+
+        if record.level() <= self.annotate {
+            let command = match record.level() {
+                log::Level::Error => "error",
+                log::Level::Warn => "warning",
+                _ => "notice",
+            };
+            out.push_str("::");
+            out.push_str(command);
+            if let Some(file) = record.file() {
+                let _ = write!(out, " file={}", Property(file));
+                if let Some(line) = record.line() {
+                    let _ = write!(out, ",line={}", line);
+                }
+            }
+            // annotations don't show colors
+            let _ = writeln!(out, "::{}", Data(StripEscapes(record.args())));
+        } else {
+            let _ = writeln!(out, "{}", record.args());
+        }
+
+        state.stdout.write_all(out.as_bytes())?;
+        state.stdout.flush()
+    }
+}
+
+impl Default for GithubActions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl log::Log for GithubActions {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = self.write(&mut state, record) {
+            report(format_args!("Failed to write a workflow command: {e}"));
+        }
+    }
+
+    fn flush(&self) {
+        let _ = self
+            .state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .stdout
+            .flush();
+    }
+}
+
+impl From<GithubActions> for fern::Output {
+    fn from(github: GithubActions) -> Self {
+        fern::Output::from(Box::new(github) as Box<dyn log::Log>)
+    }
+}
+
+/// The message of a workflow command, where line breaks and `%` are escaped
+struct Data<T>(T);
+
+impl<T: Display> Display for Data<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            Escape {
+                out: f,
+                property: false
+            },
+            "{}",
+            self.0
+        )
+    }
+}
+
+/// A property of a workflow command, which also has `:` and `,` escaped
+struct Property<T>(T);
+
+impl<T: Display> Display for Property<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            Escape {
+                out: f,
+                property: true
+            },
+            "{}",
+            self.0
+        )
+    }
+}
+
+struct Escape<W> {
+    out: W,
+    property: bool,
+}
+
+impl<W: std::fmt::Write> std::fmt::Write for Escape<W> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        for c in s.chars() {
+            match c {
+                '%' => self.out.write_str("%25")?,
+                '\r' => self.out.write_str("%0D")?,
+                '\n' => self.out.write_str("%0A")?,
+                ':' if self.property => self.out.write_str("%3A")?,
+                ',' if self.property => self.out.write_str("%2C")?,
+                c => self.out.write_char(c)?,
+            }
+        }
+        Ok(())
+    }
+}
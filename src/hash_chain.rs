@@ -0,0 +1,252 @@
+use std::{collections::VecDeque, fmt::Display, io};
+
+use sha2::{Digest, Sha256};
+
+#[cfg(feature = "kv")]
+use crate::kv::OwnedValue;
+use crate::lock::{lock, Mutex};
+
+/// The hash that the first record of a chain follows
+const GENESIS: &str = "0000000000000000";
+
+/// A [`log::Log`] that makes an audit log tamper-evident: it ends each formatted record
+/// with ` #` and the first 16 hex digits of the SHA-256 of the previous hash and the
+/// record, so that changing, inserting or removing a record breaks every hash after it.
+/// [`verify_hash_chain`] checks a file that was written this way.
+///
+/// It hashes the formatted records, so it has to sit behind the dispatch that does the
+/// formatting:
+/// ```no_run
+/// use fern_format::{Format, HashChain, RotatingFile};
+///
+/// let file = RotatingFile::new("audit.log").unwrap();
+/// fern::Dispatch::new()
+///     .format(Format::new().force_colors_if(false).callback())
+///     .chain(Box::new(HashChain::new(file)) as Box<dyn log::Log>)
+///     .apply()
+///     .unwrap();
+/// ```
+///
+/// Colors are hashed like everything else, so they are best left out. Records that span
+/// several lines only have the hash on the last one.
+pub struct HashChain<L> {
+    inner: L,
+    chain: Mutex<Chain>,
+}
+
+/// The end of the chain, and the chained records that are yet to be passed on
+struct Chain {
+    previous: String,
+    pending: VecDeque<Chained>,
+    /// If a call of [`log`](log::Log::log) is passing the pending records on
+    forwarding: bool,
+}
+
+/// A copy of a record that ends with its hash
+struct Chained {
+    level: log::Level,
+    target: String,
+    module_path: Option<String>,
+    file: Option<String>,
+    line: Option<u32>,
+    message: String,
+    #[cfg(feature = "kv")]
+    key_values: Vec<(String, OwnedValue)>,
+}
+
+impl Chained {
+    fn new(record: &log::Record<'_>, message: String) -> Self {
+        Self {
+            level: record.level(),
+            target: record.target().to_string(),
+            module_path: record.module_path().map(str::to_string),
+            file: record.file().map(str::to_string),
+            line: record.line(),
+            message,
+            #[cfg(feature = "kv")]
+            key_values: OwnedValue::collect(record.key_values()),
+        }
+    }
+
+    fn log(&self, logger: &impl log::Log) {
+        let mut builder = log::Record::builder();
+        builder
+            .level(self.level)
+            .target(&self.target)
+            .module_path(self.module_path.as_deref())
+            .file(self.file.as_deref())
+            .line(self.line);
+        #[cfg(feature = "kv")]
+        builder.key_values(&self.key_values);
+        logger.log(&builder.args(format_args!("{}", self.message)).build());
+    }
+}
+
+/// Stops forwarding if the inner logger panics, so that the next record takes over
+struct Forwarding<'a>(&'a Mutex<Chain>);
+
+impl Drop for Forwarding<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            lock(self.0).forwarding = false;
+        }
+    }
+}
+
+impl<L: log::Log> HashChain<L> {
+    /// Wraps `inner`, starting a new chain
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            chain: Mutex::new(Chain {
+                previous: GENESIS.to_string(),
+                pending: VecDeque::new(),
+                forwarding: false,
+            }),
+        }
+    }
+
+    /// Continue the chain of a file that is appended to, which ends with `last_line`.
+    /// A line without a hash starts a new chain.
+    pub fn after(self, last_line: &str) -> Self {
+        let previous = split(last_line.trim_end_matches(['\r', '\n']))
+            .map_or(GENESIS, |(_, hash)| hash)
+            .to_string();
+        lock(&self.chain).previous = previous;
+        self
+    }
+}
+
+impl<L: log::Log> log::Log for HashChain<L> {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    /// Records are passed on in the order of the chain by the one call that is
+    /// forwarding, also those of other threads and those that the inner logger logs
+    /// itself, but never while the chain is locked
+    fn log(&self, record: &log::Record<'_>) {
+        let line = record.args().to_string();
+        let mut chain = lock(&self.chain);
+        chain.previous = hash(&chain.previous, &line);
+        let message = format!("{line} #{}", chain.previous);
+        chain.pending.push_back(Chained::new(record, message));
+        if chain.forwarding {
+            return;
+        }
+        chain.forwarding = true;
+        let _forwarding = Forwarding(&self.chain);
+        while let Some(next) = chain.pending.pop_front() {
+            drop(chain);
+            next.log(&self.inner);
+            chain = lock(&self.chain);
+        }
+        chain.forwarding = false;
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+impl<L: log::Log + 'static> From<HashChain<L>> for fern::Output {
+    fn from(chain: HashChain<L>) -> Self {
+        (Box::new(chain) as Box<dyn log::Log>).into()
+    }
+}
+
+/// Why [`verify_hash_chain`] rejected a log
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum HashChainError {
+    /// The log couldn't be read
+    Io(io::Error),
+    /// The record ending on this line, counting from 1, has been changed, or a record
+    /// before it has been inserted or removed
+    Mismatch(usize),
+    /// The log ends with lines that don't end with a hash
+    Unfinished(usize),
+}
+
+impl Display for HashChainError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HashChainError::Io(e) => write!(f, "failed to read the log: {e}"),
+            HashChainError::Mismatch(line) => {
+                write!(f, "the hash on line {line} doesn't match")
+            }
+            HashChainError::Unfinished(line) => {
+                write!(f, "the record starting on line {line} has no hash")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HashChainError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HashChainError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Checks that the lines of `log` form an unbroken chain of [`HashChain`], starting
+/// with a new chain, and returns the last hash. Removing records from the end of the
+/// log can only be noticed by comparing it to a hash that was kept elsewhere.
+///
+/// ```
+/// use fern_format::{verify_hash_chain, HashChainError};
+///
+/// let log = "\
+///     [INFO] audit: login #851dd1a26188e0e5\n\
+///     [INFO] audit: logout #0ab14a5fb7264d12\n";
+/// assert!(verify_hash_chain(log.as_bytes()).is_ok());
+///
+/// let tampered = log.replace("login", "lgoin");
+/// assert!(matches!(
+///     verify_hash_chain(tampered.as_bytes()),
+///     Err(HashChainError::Mismatch(1))
+/// ));
+/// ```
+pub fn verify_hash_chain(log: impl io::BufRead) -> Result<String, HashChainError> {
+    let mut previous = GENESIS.to_string();
+    let mut record = String::new();
+    let mut start = 1;
+    for (n, line) in log.lines().enumerate() {
+        let line = line.map_err(HashChainError::Io)?;
+        let Some((text, expected)) = split(&line) else {
+            record.push_str(&line);
+            record.push('\n');
+            continue;
+        };
+        record.push_str(text);
+        previous = hash(&previous, &record);
+        if previous != expected {
+            return Err(HashChainError::Mismatch(n + 1));
+        }
+        record.clear();
+        start = n + 2;
+    }
+    if !record.is_empty() {
+        return Err(HashChainError::Unfinished(start));
+    }
+    Ok(previous)
+}
+
+/// The record and the hash of a line that ends with one
+fn split(line: &str) -> Option<(&str, &str)> {
+    let (text, hash) = line.rsplit_once(" #")?;
+    let is_hash = hash.len() == GENESIS.len()
+        && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    is_hash.then_some((text, hash))
+}
+
+/// The first 16 hex digits of the SHA-256 of `previous` and `record`
+fn hash(previous: &str, record: &str) -> String {
+    let digest = Sha256::new()
+        .chain_update(previous)
+        .chain_update(record)
+        .finalize();
+    digest[..8].iter().map(|b| format!("{b:02x}")).collect()
+}
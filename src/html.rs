@@ -0,0 +1,261 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+    sync::Mutex,
+};
+
+use crate::report::report;
+
+const HEADER: &str = "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+    <style>body { background: #1e1e1e; color: #d4d4d4; }</style>\n\
+    </head>\n<body>\n<pre>\n";
+const FOOTER: &str = "</pre>\n</body>\n</html>\n";
+
+/// Writes an HTML page with the records, where the colors of the lines are turned into
+/// inline CSS. Colored logs can then be attached to bug reports or kept as artifacts of
+/// CI runs and viewed in a browser. Force colors on the format, since the output isn't a
+/// terminal:
+/// ```no_run
+/// use fern_format::{Format, Html};
+///
+/// fern::Dispatch::new()
+///     .format(Format::new().force_colors().uniquely_color_threads().callback())
+///     .chain(Html::new(std::fs::File::create("log.html").unwrap()).unwrap())
+///     .apply()
+///     .unwrap();
+/// ```
+///
+/// The page is finished when the output is dropped, but browsers show unfinished pages
+/// just as well.
+pub struct Html {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Html {
+    /// Writes the start of the page to `writer`
+    pub fn new(writer: impl Write + Send + 'static) -> io::Result<Self> {
+        let mut writer: Box<dyn Write + Send> = Box::new(writer);
+        writer.write_all(HEADER.as_bytes())?;
+        Ok(Self {
+            writer: Mutex::new(writer),
+        })
+    }
+}
+
+impl log::Log for Html {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let mut html = String::with_capacity(256);
+        to_html(&record.args().to_string(), &mut html);
+        html.push('\n');
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let result: io::Result<()> = writer
+            .write_all(html.as_bytes())
+            .and_then(|()| writer.flush());
+        if let Err(e) = result {
+            report(format_args!("Failed to write a record as HTML: {e}"));
+        }
+    }
+
+    fn flush(&self) {
+        let _ = self
+            .writer
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .flush();
+    }
+}
+
+impl Drop for Html {
+    fn drop(&mut self) {
+        let writer = self.writer.get_mut().unwrap_or_else(|e| e.into_inner());
+        let _ = writer
+            .write_all(FOOTER.as_bytes())
+            .and_then(|()| writer.flush());
+    }
+}
+
+impl From<Html> for fern::Output {
+    fn from(html: Html) -> Self {
+        fern::Output::from(Box::new(html) as Box<dyn log::Log>)
+    }
+}
+
+/// The SGR attributes that are in effect
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+struct Attributes {
+    bold: bool,
+    dimmed: bool,
+    italic: bool,
+    underline: bool,
+    reversed: bool,
+    strikethrough: bool,
+    fg: Option<Rgb>,
+    bg: Option<Rgb>,
+}
+
+type Rgb = (u8, u8, u8);
+
+/// The colors of VS Code's terminal, which are readable on its dark background
+const PALETTE: [Rgb; 16] = [
+    (0x00, 0x00, 0x00),
+    (0xcd, 0x31, 0x31),
+    (0x0d, 0xbc, 0x79),
+    (0xe5, 0xe5, 0x10),
+    (0x24, 0x72, 0xc8),
+    (0xbc, 0x3f, 0xbc),
+    (0x11, 0xa8, 0xcd),
+    (0xe5, 0xe5, 0xe5),
+    (0x66, 0x66, 0x66),
+    (0xf1, 0x4c, 0x4c),
+    (0x23, 0xd1, 0x8b),
+    (0xf5, 0xf5, 0x43),
+    (0x3b, 0x8e, 0xea),
+    (0xd6, 0x70, 0xd6),
+    (0x29, 0xb8, 0xdb),
+    (0xff, 0xff, 0xff),
+];
+
+impl Attributes {
+    /// Applies the parameters of an SGR sequence
+    fn apply(&mut self, params: &str) {
+        let mut params = params.split(';').map(|p| p.parse::<u8>().unwrap_or(0));
+        while let Some(param) = params.next() {
+            match param {
+                0 => *self = Self::default(),
+                1 => self.bold = true,
+                2 => self.dimmed = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                7 => self.reversed = true,
+                9 => self.strikethrough = true,
+                22 => (self.bold, self.dimmed) = (false, false),
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.reversed = false,
+                29 => self.strikethrough = false,
+                30..=37 => self.fg = Some(PALETTE[param as usize - 30]),
+                38 => self.fg = extended_color(&mut params),
+                39 => self.fg = None,
+                40..=47 => self.bg = Some(PALETTE[param as usize - 40]),
+                48 => self.bg = extended_color(&mut params),
+                49 => self.bg = None,
+                90..=97 => self.fg = Some(PALETTE[param as usize - 90 + 8]),
+                100..=107 => self.bg = Some(PALETTE[param as usize - 100 + 8]),
+                _ => {}
+            }
+        }
+    }
+
+    fn css(&self, out: &mut String) {
+        let (mut fg, mut bg) = (self.fg, self.bg);
+        if self.reversed {
+            // the defaults of the page
+            (fg, bg) = (
+                bg.or(Some((0x1e, 0x1e, 0x1e))),
+                fg.or(Some((0xd4, 0xd4, 0xd4))),
+            );
+        }
+        if let Some((r, g, b)) = fg {
+            let _ = write!(out, "color:#{r:02x}{g:02x}{b:02x};");
+        }
+        if let Some((r, g, b)) = bg {
+            let _ = write!(out, "background:#{r:02x}{g:02x}{b:02x};");
+        }
+        if self.bold {
+            out.push_str("font-weight:bold;");
+        }
+        if self.dimmed {
+            out.push_str("opacity:0.6;");
+        }
+        if self.italic {
+            out.push_str("font-style:italic;");
+        }
+        match (self.underline, self.strikethrough) {
+            (true, true) => out.push_str("text-decoration:underline line-through;"),
+            (true, false) => out.push_str("text-decoration:underline;"),
+            (false, true) => out.push_str("text-decoration:line-through;"),
+            (false, false) => {}
+        }
+    }
+}
+
+/// The color of `5;N` or `2;R;G;B` after 38 or 48
+fn extended_color(params: &mut impl Iterator<Item = u8>) -> Option<Rgb> {
+    match params.next()? {
+        5 => {
+            let n = params.next()?;
+            Some(match n {
+                0..=15 => PALETTE[n as usize],
+                16..=231 => {
+                    let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                    let n = n - 16;
+                    (level(n / 36), level(n / 6 % 6), level(n % 6))
+                }
+                232..=255 => {
+                    let gray = 8 + (n - 232) * 10;
+                    (gray, gray, gray)
+                }
+            })
+        }
+        2 => Some((params.next()?, params.next()?, params.next()?)),
+        _ => None,
+    }
+}
+
+/// Escapes `line` for HTML and turns its SGR sequences into spans. Other escape sequences
+/// are dropped.
+fn to_html(line: &str, out: &mut String) {
+    let mut attributes = Attributes::default();
+    let mut open = false;
+    let mut rest = line;
+    while let Some(start) = rest.find('\x1b') {
+        escape(&rest[..start], out);
+        rest = &rest[start + 1..];
+        let Some(csi) = rest.strip_prefix('[') else {
+            continue;
+        };
+        let Some(end) = csi.find(|c: char| ('\x40'..='\x7e').contains(&c)) else {
+            rest = "";
+            break;
+        };
+        rest = &csi[end + 1..];
+        if !csi[end..].starts_with('m') {
+            continue;
+        }
+
+        let before = attributes;
+        attributes.apply(&csi[..end]);
+        if attributes == before {
+            continue;
+        }
+        if open {
+            out.push_str("</span>");
+            open = false;
+        }
+        if attributes != Attributes::default() {
+            out.push_str("<span style=\"");
+            attributes.css(out);
+            out.push_str("\">");
+            open = true;
+        }
+    }
+    escape(rest, out);
+    if open {
+        out.push_str("</span>");
+    }
+}
+
+fn escape(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            c => out.push(c),
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use std::{io, os::unix::net::UnixDatagram};
+
+use crate::{
+    components::with_thread_number,
+    context,
+    report::report,
+    syslog::{executable_name, severity},
+};
+
+const SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Sends records to journald over its native protocol, with the level, thread, target
+/// and key-values as structured fields instead of text that journald has to scrape from
+/// stdout. Put it in its own chain, since journald shows the time and the program by
+/// itself:
+/// ```no_run
+/// use fern_format::{Format, Journald};
+///
+/// fern::Dispatch::new()
+///     .chain(Journald::new().unwrap())
+///     .chain(
+///         fern::Dispatch::new()
+///             .format(Format::new().callback())
+///             .chain(std::io::stderr()),
+///     )
+///     .apply()
+///     .unwrap();
+/// ```
+///
+/// The fields are `MESSAGE`, `PRIORITY`, `TID`, `TARGET`, `CODE_MODULE`, `CODE_FILE`,
+/// `CODE_LINE`, `SYSLOG_IDENTIFIER` and the [`context`](crate::context) and key-values
+/// of the record, with their keys in upper case. Records that are too big for a
+/// datagram are passed in a memory file on Linux, like systemd does itself.
+pub struct Journald {
+    socket: UnixDatagram,
+    identifier: Option<String>,
+}
+
+impl Journald {
+    /// Connects to the journald socket
+    pub fn new() -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(SOCKET)?;
+        Ok(Self {
+            socket,
+            identifier: executable_name(),
+        })
+    }
+
+    /// Set `SYSLOG_IDENTIFIER` to `identifier` instead of the name of the executable
+    pub fn identifier(mut self, identifier: impl Into<String>) -> Self {
+        self.identifier = Some(identifier.into());
+        self
+    }
+
+    fn entry(&self, record: &log::Record<'_>) -> Vec<u8> {
+        let mut entry = Vec::with_capacity(256);
+        add_field(&mut entry, "MESSAGE", &record.args().to_string());
+        add_field(
+            &mut entry,
+            "PRIORITY",
+            &severity(record.level()).to_string(),
+        );
+        THREAD_ID.with(|tid| match tid {
+            Some(tid) => add_field(&mut entry, "TID", tid),
+            None => with_thread_number(|number| add_field(&mut entry, "TID", number)),
+        });
+        add_field(&mut entry, "TARGET", record.target());
+        if let Some(module) = record.module_path() {
+            add_field(&mut entry, "CODE_MODULE", module);
+        }
+        if let Some(file) = record.file() {
+            add_field(&mut entry, "CODE_FILE", file);
+        }
+        if let Some(line) = record.line() {
+            add_field(&mut entry, "CODE_LINE", &line.to_string());
+        }
+        if let Some(identifier) = &self.identifier {
+            add_field(&mut entry, "SYSLOG_IDENTIFIER", identifier);
+        }
+        context::with(|context| {
+            for (key, value) in context {
+                add_user_field(&mut entry, key, value);
+            }
+        });
+        #[cfg(feature = "kv")]
+        {
+            struct Add<'a>(&'a mut Vec<u8>);
+
+            impl<'kvs> log::kv::VisitSource<'kvs> for Add<'_> {
+                fn visit_pair(
+                    &mut self,
+                    key: log::kv::Key<'kvs>,
+                    value: log::kv::Value<'kvs>,
+                ) -> Result<(), log::kv::Error> {
+                    add_user_field(self.0, key.as_str(), &value.to_string());
+                    Ok(())
+                }
+            }
+
+            let _ = record.key_values().visit(&mut Add(&mut entry));
+        }
+        entry
+    }
+}
+
+impl log::Log for Journald {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let entry = self.entry(record);
+        let sent = match self.socket.send(&entry) {
+            #[cfg(target_os = "linux")]
+            Err(e)
+                if matches!(e.raw_os_error(), Some(libc::EMSGSIZE | libc::ENOBUFS)) =>
+            {
+                memfd::send(&self.socket, &entry)
+            }
+            sent => sent.map(drop),
+        };
+        if let Err(e) = sent {
+            report(format_args!("Failed to send a record to journald: {e}"));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+impl From<Journald> for fern::Output {
+    fn from(journald: Journald) -> Self {
+        fern::Output::from(Box::new(journald) as Box<dyn log::Log>)
+    }
+}
+
+thread_local! {
+    /// The id of this thread in the kernel, which is what journald expects as `TID`.
+    /// It is only available on Linux, other systems get the number of the `ThreadId`.
+    static THREAD_ID: Option<String> = std::fs::read_link("/proc/thread-self")
+        .ok()
+        .and_then(|link| Some(link.file_name()?.to_str()?.to_string()));
+}
+
+/// The longest key that journald accepts
+const MAX_KEY: usize = 64;
+
+/// Adds a field with a key chosen by the user. Journald only accepts keys of up to 64
+/// upper case letters, digits and underscores that don't start with a digit, and
+/// reserves keys that start with an underscore. Keys that start with a digit get an
+/// `F_` in front.
+fn add_user_field(entry: &mut Vec<u8>, key: &str, value: &str) {
+    let key = key.trim_start_matches('_');
+    let prefix = match key.starts_with(|c: char| c.is_ascii_digit()) {
+        true => "F_",
+        false => "",
+    };
+    let key: String = prefix
+        .chars()
+        .chain(key.chars().map(|c| match c {
+            'a'..='z' => c.to_ascii_uppercase(),
+            'A'..='Z' | '0'..='9' => c,
+            _ => '_',
+        }))
+        .take(MAX_KEY)
+        .collect();
+    if !key.is_empty() {
+        add_field(entry, &key, value);
+    }
+}
+
+/// Adds `KEY=value\n`, or the length prefixed form if `value` spans several lines
+fn add_field(entry: &mut Vec<u8>, key: &str, value: &str) {
+    entry.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        entry.push(b'\n');
+        entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        entry.push(b'=');
+    }
+    entry.extend_from_slice(value.as_bytes());
+    entry.push(b'\n');
+}
+
+/// Passing entries in memory files, which journald reads when they don't fit in a
+/// datagram
+#[cfg(target_os = "linux")]
+mod memfd {
+    use std::{
+        fs::File,
+        io::{self, Write},
+        os::{
+            fd::{AsRawFd, FromRawFd, RawFd},
+            unix::net::UnixDatagram,
+        },
+    };
+
+    /// Sends `entry` over `socket` in a sealed memory file
+    pub(super) fn send(socket: &UnixDatagram, entry: &[u8]) -> io::Result<()> {
+        // SAFETY: the name is a valid C string
+        let fd = unsafe {
+            libc::memfd_create(
+                c"fern-format journal entry".as_ptr(),
+                libc::MFD_ALLOW_SEALING | libc::MFD_CLOEXEC,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the file descriptor was just created and isn't owned by anything else
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        file.write_all(entry)?;
+        // journald only reads memory files that can't change anymore
+        let seals = libc::F_SEAL_SHRINK
+            | libc::F_SEAL_GROW
+            | libc::F_SEAL_WRITE
+            | libc::F_SEAL_SEAL;
+        // SAFETY: the file descriptor is open
+        if unsafe { libc::fcntl(file.as_raw_fd(), libc::F_ADD_SEALS, seals) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        send_fd(socket, file.as_raw_fd())
+    }
+
+    /// Sends `fd` over `socket` without any data
+    fn send_fd(socket: &UnixDatagram, fd: RawFd) -> io::Result<()> {
+        const FD_LEN: u32 = std::mem::size_of::<RawFd>() as u32;
+        // aligned for the header of the control message
+        let mut control = [0u64; 4];
+        // SAFETY: an all zero msghdr is valid, and the control message is written into
+        // `control`, which is big enough for one file descriptor and outlives `sendmsg`
+        unsafe {
+            let mut message: libc::msghdr = std::mem::zeroed();
+            message.msg_control = control.as_mut_ptr().cast();
+            message.msg_controllen = libc::CMSG_SPACE(FD_LEN) as _;
+            let header = libc::CMSG_FIRSTHDR(&message);
+            (*header).cmsg_level = libc::SOL_SOCKET;
+            (*header).cmsg_type = libc::SCM_RIGHTS;
+            (*header).cmsg_len = libc::CMSG_LEN(FD_LEN) as _;
+            std::ptr::write_unaligned(libc::CMSG_DATA(header).cast::<RawFd>(), fd);
+            if libc::sendmsg(socket.as_raw_fd(), &message, libc::MSG_NOSIGNAL) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_field(key: &str) -> String {
+        let mut entry = Vec::new();
+        add_user_field(&mut entry, key, "value");
+        String::from_utf8(entry).unwrap()
+    }
+
+    #[test]
+    fn user_keys_are_valid() {
+        assert_eq!(user_field("request.id"), "REQUEST_ID=value\n");
+        assert_eq!(user_field("_private"), "PRIVATE=value\n");
+        assert_eq!(user_field("2fa"), "F_2FA=value\n");
+        assert_eq!(user_field("__"), "");
+        assert_eq!(
+            user_field(&"k".repeat(70)),
+            format!("{}=value\n", "K".repeat(64))
+        );
+    }
+
+    #[test]
+    fn multi_line_values_are_length_prefixed() {
+        let mut entry = Vec::new();
+        add_field(&mut entry, "MESSAGE", "two\nlines");
+        assert_eq!(entry, b"MESSAGE\n\x09\0\0\0\0\0\0\0two\nlines\n");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn large_records_are_sent_in_a_memory_file() {
+        use std::{
+            io::{Read, Seek},
+            os::fd::{AsRawFd, FromRawFd, RawFd},
+        };
+
+        let (socket, receiver) = UnixDatagram::pair().unwrap();
+        let journald = Journald {
+            socket,
+            identifier: None,
+        };
+        let message = "x".repeat(1 << 20);
+        log::Log::log(
+            &journald,
+            &log::Record::builder()
+                .args(format_args!("{message}"))
+                .level(log::Level::Info)
+                .target("app")
+                .build(),
+        );
+
+        let mut control = [0u64; 4];
+        // SAFETY: an all zero msghdr is valid, and `control` outlives `recvmsg`
+        let fd = unsafe {
+            let mut message: libc::msghdr = std::mem::zeroed();
+            message.msg_control = control.as_mut_ptr().cast();
+            message.msg_controllen = std::mem::size_of_val(&control) as _;
+            assert!(libc::recvmsg(receiver.as_raw_fd(), &mut message, 0) >= 0);
+            let header = libc::CMSG_FIRSTHDR(&message);
+            assert!(!header.is_null());
+            assert_eq!((*header).cmsg_type, libc::SCM_RIGHTS);
+            std::ptr::read_unaligned(libc::CMSG_DATA(header).cast::<RawFd>())
+        };
+        // SAFETY: the file descriptor was just received and isn't owned by anything else
+        let mut file = unsafe { std::fs::File::from_raw_fd(fd) };
+        // the file shares its position with the one that was written
+        file.rewind().unwrap();
+        let mut entry = String::new();
+        file.read_to_string(&mut entry).unwrap();
+        assert!(entry.starts_with(&format!("MESSAGE={message}\nPRIORITY=6\n")));
+    }
+}
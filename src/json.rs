@@ -0,0 +1,880 @@
+//! Just enough JSON writing for the structured outputs, without pulling in serde_json
+
+use std::fmt::Write;
+
+/// Writes `s` as a quoted and escaped JSON string
+pub(crate) fn write_str(out: &mut impl Write, s: &str) -> std::fmt::Result {
+    out.write_char('"')?;
+    Escape(&mut *out).write_str(s)?;
+    out.write_char('"')
+}
+
+/// Writes `value` as a quoted and escaped JSON string, without formatting it into a
+/// `String` first
+pub(crate) fn write_display(
+    out: &mut impl Write,
+    value: impl std::fmt::Display,
+) -> std::fmt::Result {
+    out.write_char('"')?;
+    write!(Escape(&mut *out), "{}", value)?;
+    out.write_char('"')
+}
+
+/// Escapes everything written to it for use inside of a JSON string
+struct Escape<W>(W);
+
+impl<W: Write> Write for Escape<W> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let mut plain_start = 0;
+        for (i, c) in s.char_indices() {
+            let escaped = match c {
+                '"' => "\\\"",
+                '\\' => "\\\\",
+                '\n' => "\\n",
+                '\r' => "\\r",
+                '\t' => "\\t",
+                c if (c as u32) < 0x20 => "",
+                _ => continue,
+            };
+
+            self.0.write_str(&s[plain_start..i])?;
+            if escaped.is_empty() {
+                write!(self.0, "\\u{:04x}", c as u32)?;
+            } else {
+                self.0.write_str(escaped)?;
+            }
+            plain_start = i + c.len_utf8();
+        }
+        self.0.write_str(&s[plain_start..])
+    }
+}
+
+/// Writes `f` as a JSON number, or `null` if it can't be represented
+#[cfg(feature = "kv")]
+pub(crate) fn write_f64(out: &mut impl Write, f: f64) -> std::fmt::Result {
+    if f.is_finite() {
+        write!(out, "{}", f)
+    } else {
+        out.write_str("null")
+    }
+}
+
+/// A parsed JSON value, with just enough detail to check the shape of records
+#[derive(Debug, PartialEq)]
+pub(crate) enum Value {
+    Null,
+    Bool,
+    Number { integer: bool },
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+/// Parses `s` as a single JSON value, or returns the byte offset where it stops being
+/// valid JSON
+pub(crate) fn parse(s: &str) -> Result<Value, usize> {
+    let mut parser = Parser {
+        s: s.as_bytes(),
+        i: 0,
+    };
+    let value = parser.value()?;
+    parser.whitespace();
+    match parser.i == s.len() {
+        true => Ok(value),
+        false => Err(parser.i),
+    }
+}
+
+struct Parser<'a> {
+    s: &'a [u8],
+    i: usize,
+}
+
+impl Parser<'_> {
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.s.get(self.i) {
+            self.i += 1;
+        }
+    }
+
+    /// Skips whitespace and `byte`, which must come next
+    fn expect(&mut self, byte: u8) -> Result<(), usize> {
+        match self.eat(byte) {
+            true => Ok(()),
+            false => Err(self.i),
+        }
+    }
+
+    /// Skips whitespace, and `byte` if it comes next
+    fn eat(&mut self, byte: u8) -> bool {
+        self.whitespace();
+        let next = self.s.get(self.i) == Some(&byte);
+        self.i += next as usize;
+        next
+    }
+
+    fn value(&mut self) -> Result<Value, usize> {
+        self.whitespace();
+        match self.s.get(self.i) {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(b't') => self.literal("true", Value::Bool),
+            Some(b'f') => self.literal("false", Value::Bool),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.i),
+        }
+    }
+
+    fn literal(&mut self, literal: &str, value: Value) -> Result<Value, usize> {
+        if !self.s[self.i..].starts_with(literal.as_bytes()) {
+            return Err(self.i);
+        }
+        self.i += literal.len();
+        Ok(value)
+    }
+
+    fn object(&mut self) -> Result<Value, usize> {
+        self.expect(b'{')?;
+        let mut members = Vec::new();
+        if self.eat(b'}') {
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.whitespace();
+            let key = self.string()?;
+            self.expect(b':')?;
+            members.push((key, self.value()?));
+            if self.eat(b'}') {
+                return Ok(Value::Object(members));
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, usize> {
+        self.expect(b'[')?;
+        let mut elements = Vec::new();
+        if self.eat(b']') {
+            return Ok(Value::Array(elements));
+        }
+        loop {
+            elements.push(self.value()?);
+            if self.eat(b']') {
+                return Ok(Value::Array(elements));
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, usize> {
+        let digits = |parser: &mut Self| {
+            let start = parser.i;
+            while parser.s.get(parser.i).is_some_and(u8::is_ascii_digit) {
+                parser.i += 1;
+            }
+            match parser.i > start {
+                true => Ok(()),
+                false => Err(parser.i),
+            }
+        };
+        self.i += (self.s[self.i] == b'-') as usize;
+        match self.s.get(self.i) {
+            Some(b'0') => self.i += 1,
+            _ => digits(self)?,
+        }
+        let mut integer = true;
+        if self.s.get(self.i) == Some(&b'.') {
+            self.i += 1;
+            digits(self)?;
+            integer = false;
+        }
+        if let Some(b'e' | b'E') = self.s.get(self.i) {
+            self.i += 1;
+            if let Some(b'+' | b'-') = self.s.get(self.i) {
+                self.i += 1;
+            }
+            digits(self)?;
+            integer = false;
+        }
+        Ok(Value::Number { integer })
+    }
+
+    fn string(&mut self) -> Result<String, usize> {
+        if self.s.get(self.i) != Some(&b'"') {
+            return Err(self.i);
+        }
+        self.i += 1;
+        let mut out = String::new();
+        loop {
+            let rest = &self.s[self.i..];
+            let plain = rest
+                .iter()
+                .position(|&b| b == b'"' || b == b'\\' || b < 0x20)
+                .ok_or(self.s.len())?;
+            // the input is a `str` and the plain part ends at an ASCII character
+            out.push_str(std::str::from_utf8(&rest[..plain]).map_err(|_| self.i)?);
+            self.i += plain;
+            match self.s[self.i] {
+                b'"' => {
+                    self.i += 1;
+                    return Ok(out);
+                }
+                b'\\' => {
+                    self.i += 1;
+                    out.push(self.escape()?);
+                }
+                _ => return Err(self.i),
+            }
+        }
+    }
+
+    /// The character of the escape after a backslash
+    fn escape(&mut self) -> Result<char, usize> {
+        let escaped = match self.s.get(self.i) {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.i += 1;
+                let high = self.hex()?;
+                if !(0xd800..0xdc00).contains(&high) {
+                    return char::from_u32(high.into()).ok_or(self.i);
+                }
+                // a surrogate pair
+                if !self.s[self.i..].starts_with(b"\\u") {
+                    return Err(self.i);
+                }
+                self.i += 2;
+                let low = self.hex()?;
+                if !(0xdc00..0xe000).contains(&low) {
+                    return Err(self.i);
+                }
+                let c = 0x10000
+                    + ((u32::from(high) - 0xd800) << 10)
+                    + (u32::from(low) - 0xdc00);
+                return char::from_u32(c).ok_or(self.i);
+            }
+            _ => return Err(self.i),
+        };
+        self.i += 1;
+        Ok(escaped)
+    }
+
+    /// The 4 hex digits of a `\u` escape
+    fn hex(&mut self) -> Result<u16, usize> {
+        let digits = self.s.get(self.i..self.i + 4).ok_or(self.i)?;
+        let digits = std::str::from_utf8(digits).map_err(|_| self.i)?;
+        let value = u16::from_str_radix(digits, 16).map_err(|_| self.i)?;
+        self.i += 4;
+        Ok(value)
+    }
+}
+
+/// Serializes `value` into a compact JSON string
+#[cfg(feature = "kv-serde")]
+pub(crate) fn to_string<T: serde::Serialize + ?Sized>(value: &T) -> Option<String> {
+    let mut out = String::new();
+    value.serialize(&mut Serializer { out: &mut out }).ok()?;
+    Some(out)
+}
+
+#[cfg(feature = "kv-serde")]
+pub(crate) use ser::Serializer;
+
+#[cfg(feature = "kv-serde")]
+mod ser {
+    use std::fmt::Write;
+
+    use serde::ser::{self, Serialize};
+
+    /// A serde serializer producing compact JSON. Map keys that aren't strings are
+    /// turned into strings, since JSON can't represent them otherwise.
+    pub(crate) struct Serializer<'a> {
+        pub(crate) out: &'a mut String,
+    }
+
+    #[derive(Debug)]
+    pub(crate) struct Error;
+
+    impl std::fmt::Display for Error {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.write_str("failed to serialize to JSON")
+        }
+    }
+
+    impl std::error::Error for Error {}
+
+    impl ser::Error for Error {
+        fn custom<T: std::fmt::Display>(_msg: T) -> Self {
+            Error
+        }
+    }
+
+    impl From<std::fmt::Error> for Error {
+        fn from(_: std::fmt::Error) -> Self {
+            Error
+        }
+    }
+
+    impl<'a> Serializer<'a> {
+        fn write_display(&mut self, value: impl std::fmt::Display) -> Result<(), Error> {
+            Ok(write!(self.out, "{}", value)?)
+        }
+
+        fn open(&mut self, open: char, variant: Option<&str>) -> Result<(), Error> {
+            if let Some(variant) = variant {
+                self.out.push('{');
+                super::write_str(self.out, variant)?;
+                self.out.push(':');
+            }
+            self.out.push(open);
+            Ok(())
+        }
+    }
+
+    /// Seqs, tuples, maps and structs, with the text to write when they end
+    pub(crate) struct Compound<'a, 'b> {
+        ser: &'b mut Serializer<'a>,
+        first: bool,
+        close: &'static str,
+    }
+
+    impl<'a, 'b> Compound<'a, 'b> {
+        fn new(ser: &'b mut Serializer<'a>, close: &'static str) -> Self {
+            Self {
+                ser,
+                first: true,
+                close,
+            }
+        }
+
+        fn comma(&mut self) {
+            if !self.first {
+                self.ser.out.push(',');
+            }
+            self.first = false;
+        }
+
+        fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+            self.comma();
+            value.serialize(&mut *self.ser)
+        }
+
+        fn field<T: Serialize + ?Sized>(
+            &mut self,
+            key: &str,
+            value: &T,
+        ) -> Result<(), Error> {
+            self.comma();
+            super::write_str(self.ser.out, key)?;
+            self.ser.out.push(':');
+            value.serialize(&mut *self.ser)
+        }
+
+        fn close(self) -> Result<(), Error> {
+            self.ser.out.push_str(self.close);
+            Ok(())
+        }
+    }
+
+    impl<'a, 'b> ser::Serializer for &'b mut Serializer<'a> {
+        type Ok = ();
+        type Error = Error;
+        type SerializeSeq = Compound<'a, 'b>;
+        type SerializeTuple = Compound<'a, 'b>;
+        type SerializeTupleStruct = Compound<'a, 'b>;
+        type SerializeTupleVariant = Compound<'a, 'b>;
+        type SerializeMap = Compound<'a, 'b>;
+        type SerializeStruct = Compound<'a, 'b>;
+        type SerializeStructVariant = Compound<'a, 'b>;
+
+        fn serialize_bool(self, v: bool) -> Result<(), Error> {
+            self.write_display(v)
+        }
+
+        fn serialize_i8(self, v: i8) -> Result<(), Error> {
+            self.write_display(v)
+        }
+
+        fn serialize_i16(self, v: i16) -> Result<(), Error> {
+            self.write_display(v)
+        }
+
+        fn serialize_i32(self, v: i32) -> Result<(), Error> {
+            self.write_display(v)
+        }
+
+        fn serialize_i64(self, v: i64) -> Result<(), Error> {
+            self.write_display(v)
+        }
+
+        fn serialize_i128(self, v: i128) -> Result<(), Error> {
+            self.write_display(v)
+        }
+
+        fn serialize_u8(self, v: u8) -> Result<(), Error> {
+            self.write_display(v)
+        }
+
+        fn serialize_u16(self, v: u16) -> Result<(), Error> {
+            self.write_display(v)
+        }
+
+        fn serialize_u32(self, v: u32) -> Result<(), Error> {
+            self.write_display(v)
+        }
+
+        fn serialize_u64(self, v: u64) -> Result<(), Error> {
+            self.write_display(v)
+        }
+
+        fn serialize_u128(self, v: u128) -> Result<(), Error> {
+            self.write_display(v)
+        }
+
+        fn serialize_f32(self, v: f32) -> Result<(), Error> {
+            Ok(super::write_f64(self.out, v.into())?)
+        }
+
+        fn serialize_f64(self, v: f64) -> Result<(), Error> {
+            Ok(super::write_f64(self.out, v)?)
+        }
+
+        fn serialize_char(self, v: char) -> Result<(), Error> {
+            Ok(super::write_str(self.out, v.encode_utf8(&mut [0; 4]))?)
+        }
+
+        fn serialize_str(self, v: &str) -> Result<(), Error> {
+            Ok(super::write_str(self.out, v)?)
+        }
+
+        fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+            let mut seq = Compound::new(self, "]");
+            seq.ser.out.push('[');
+            for b in v {
+                seq.element(b)?;
+            }
+            seq.close()
+        }
+
+        fn serialize_none(self) -> Result<(), Error> {
+            self.out.push_str("null");
+            Ok(())
+        }
+
+        fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), Error> {
+            value.serialize(self)
+        }
+
+        fn serialize_unit(self) -> Result<(), Error> {
+            self.serialize_none()
+        }
+
+        fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+            self.serialize_none()
+        }
+
+        fn serialize_unit_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            variant: &'static str,
+        ) -> Result<(), Error> {
+            self.serialize_str(variant)
+        }
+
+        fn serialize_newtype_struct<T: Serialize + ?Sized>(
+            self,
+            _name: &'static str,
+            value: &T,
+        ) -> Result<(), Error> {
+            value.serialize(self)
+        }
+
+        fn serialize_newtype_variant<T: Serialize + ?Sized>(
+            self,
+            _name: &'static str,
+            _index: u32,
+            variant: &'static str,
+            value: &T,
+        ) -> Result<(), Error> {
+            let mut map = Compound::new(self, "}");
+            map.ser.out.push('{');
+            map.field(variant, value)?;
+            map.close()
+        }
+
+        fn serialize_seq(self, _len: Option<usize>) -> Result<Compound<'a, 'b>, Error> {
+            self.open('[', None)?;
+            Ok(Compound::new(self, "]"))
+        }
+
+        fn serialize_tuple(self, _len: usize) -> Result<Compound<'a, 'b>, Error> {
+            self.serialize_seq(None)
+        }
+
+        fn serialize_tuple_struct(
+            self,
+            _name: &'static str,
+            _len: usize,
+        ) -> Result<Compound<'a, 'b>, Error> {
+            self.serialize_seq(None)
+        }
+
+        fn serialize_tuple_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            variant: &'static str,
+            _len: usize,
+        ) -> Result<Compound<'a, 'b>, Error> {
+            self.open('[', Some(variant))?;
+            Ok(Compound::new(self, "]}"))
+        }
+
+        fn serialize_map(self, _len: Option<usize>) -> Result<Compound<'a, 'b>, Error> {
+            self.open('{', None)?;
+            Ok(Compound::new(self, "}"))
+        }
+
+        fn serialize_struct(
+            self,
+            _name: &'static str,
+            _len: usize,
+        ) -> Result<Compound<'a, 'b>, Error> {
+            self.serialize_map(None)
+        }
+
+        fn serialize_struct_variant(
+            self,
+            _name: &'static str,
+            _index: u32,
+            variant: &'static str,
+            _len: usize,
+        ) -> Result<Compound<'a, 'b>, Error> {
+            self.open('{', Some(variant))?;
+            Ok(Compound::new(self, "}}"))
+        }
+    }
+
+    impl<'a, 'b> ser::SerializeSeq for Compound<'a, 'b> {
+        type Ok = ();
+        type Error = Error;
+
+        fn serialize_element<T: Serialize + ?Sized>(
+            &mut self,
+            value: &T,
+        ) -> Result<(), Error> {
+            self.element(value)
+        }
+
+        fn end(self) -> Result<(), Error> {
+            self.close()
+        }
+    }
+
+    impl<'a, 'b> ser::SerializeTuple for Compound<'a, 'b> {
+        type Ok = ();
+        type Error = Error;
+
+        fn serialize_element<T: Serialize + ?Sized>(
+            &mut self,
+            value: &T,
+        ) -> Result<(), Error> {
+            self.element(value)
+        }
+
+        fn end(self) -> Result<(), Error> {
+            self.close()
+        }
+    }
+
+    impl<'a, 'b> ser::SerializeTupleStruct for Compound<'a, 'b> {
+        type Ok = ();
+        type Error = Error;
+
+        fn serialize_field<T: Serialize + ?Sized>(
+            &mut self,
+            value: &T,
+        ) -> Result<(), Error> {
+            self.element(value)
+        }
+
+        fn end(self) -> Result<(), Error> {
+            self.close()
+        }
+    }
+
+    impl<'a, 'b> ser::SerializeTupleVariant for Compound<'a, 'b> {
+        type Ok = ();
+        type Error = Error;
+
+        fn serialize_field<T: Serialize + ?Sized>(
+            &mut self,
+            value: &T,
+        ) -> Result<(), Error> {
+            self.element(value)
+        }
+
+        fn end(self) -> Result<(), Error> {
+            self.close()
+        }
+    }
+
+    impl<'a, 'b> ser::SerializeMap for Compound<'a, 'b> {
+        type Ok = ();
+        type Error = Error;
+
+        fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+            self.comma();
+            let mut rendered = String::new();
+            key.serialize(&mut Serializer { out: &mut rendered })?;
+            if rendered.starts_with('"') {
+                self.ser.out.push_str(&rendered);
+            } else {
+                super::write_str(self.ser.out, &rendered)?;
+            }
+            Ok(())
+        }
+
+        fn serialize_value<T: Serialize + ?Sized>(
+            &mut self,
+            value: &T,
+        ) -> Result<(), Error> {
+            self.ser.out.push(':');
+            value.serialize(&mut *self.ser)
+        }
+
+        fn end(self) -> Result<(), Error> {
+            self.close()
+        }
+    }
+
+    impl<'a, 'b> ser::SerializeStruct for Compound<'a, 'b> {
+        type Ok = ();
+        type Error = Error;
+
+        fn serialize_field<T: Serialize + ?Sized>(
+            &mut self,
+            key: &'static str,
+            value: &T,
+        ) -> Result<(), Error> {
+            self.field(key, value)
+        }
+
+        fn end(self) -> Result<(), Error> {
+            self.close()
+        }
+    }
+
+    impl<'a, 'b> ser::SerializeStructVariant for Compound<'a, 'b> {
+        type Ok = ();
+        type Error = Error;
+
+        fn serialize_field<T: Serialize + ?Sized>(
+            &mut self,
+            key: &'static str,
+            value: &T,
+        ) -> Result<(), Error> {
+            self.field(key, value)
+        }
+
+        fn end(self) -> Result<(), Error> {
+            self.close()
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::collections::BTreeMap;
+
+        use serde::ser::{Serialize, Serializer as _};
+
+        use super::{super::to_string, *};
+
+        /// Serializes with the serializer methods that `f` calls
+        fn with(f: impl FnOnce(&mut Serializer<'_>) -> Result<(), Error>) -> String {
+            let mut out = String::new();
+            f(&mut Serializer { out: &mut out }).unwrap();
+            out
+        }
+
+        #[test]
+        fn strings_are_escaped() {
+            assert_eq!(
+                to_string("a\"b\\c\nd\r\te\u{1}\u{1f}é").unwrap(),
+                r#""a\"b\\c\nd\r\te\u0001\u001fé""#
+            );
+            assert_eq!(to_string(&'"').unwrap(), r#""\"""#);
+        }
+
+        #[test]
+        fn numbers_that_json_lacks_are_null() {
+            assert_eq!(to_string(&f64::NAN).unwrap(), "null");
+            assert_eq!(to_string(&f64::INFINITY).unwrap(), "null");
+            assert_eq!(to_string(&f32::NEG_INFINITY).unwrap(), "null");
+            assert_eq!(to_string(&1.5f64).unwrap(), "1.5");
+            assert_eq!(to_string(&u128::MAX).unwrap(), u128::MAX.to_string());
+            assert_eq!(to_string(&-3i8).unwrap(), "-3");
+        }
+
+        #[test]
+        fn nested_maps_and_seqs() {
+            let mut inner = BTreeMap::new();
+            inner.insert("list", vec![Some(1), None]);
+            let mut outer = BTreeMap::new();
+            outer.insert("inner", inner);
+            outer.insert("empty", BTreeMap::new());
+            assert_eq!(
+                to_string(&outer).unwrap(),
+                r#"{"empty":{},"inner":{"list":[1,null]}}"#
+            );
+            assert_eq!(
+                to_string(&(1, "two", [true])).unwrap(),
+                r#"[1,"two",[true]]"#
+            );
+            assert_eq!(to_string(&()).unwrap(), "null");
+        }
+
+        #[test]
+        fn keys_that_are_not_strings_become_strings() {
+            let map: BTreeMap<i32, bool> = [(-1, true), (2, false)].into();
+            assert_eq!(to_string(&map).unwrap(), r#"{"-1":true,"2":false}"#);
+            let map: BTreeMap<char, ()> = [('k', ())].into();
+            assert_eq!(to_string(&map).unwrap(), r#"{"k":null}"#);
+        }
+
+        #[test]
+        fn variants_are_tagged_with_their_name() {
+            use serde::ser::{SerializeStructVariant, SerializeTupleVariant};
+
+            assert_eq!(
+                with(|s| s.serialize_unit_variant("E", 0, "Unit")),
+                r#""Unit""#
+            );
+            assert_eq!(
+                with(|s| s.serialize_newtype_variant("E", 1, "New", &5)),
+                r#"{"New":5}"#
+            );
+            assert_eq!(
+                with(|s| {
+                    let mut tuple = s.serialize_tuple_variant("E", 2, "Tuple", 2)?;
+                    SerializeTupleVariant::serialize_field(&mut tuple, &1)?;
+                    SerializeTupleVariant::serialize_field(&mut tuple, "a")?;
+                    SerializeTupleVariant::end(tuple)
+                }),
+                r#"{"Tuple":[1,"a"]}"#
+            );
+            assert_eq!(
+                with(|s| {
+                    let mut fields = s.serialize_struct_variant("E", 3, "Struct", 1)?;
+                    SerializeStructVariant::serialize_field(&mut fields, "x", &[0u8; 0])?;
+                    SerializeStructVariant::end(fields)
+                }),
+                r#"{"Struct":{"x":[]}}"#
+            );
+            assert_eq!(with(|s| s.serialize_newtype_struct("N", "in")), r#""in""#);
+            assert_eq!(with(|s| s.serialize_bytes(b"ab")), "[97,98]");
+            assert_eq!(with(|s| Some(()).serialize(s)), "null");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> Result<String, usize> {
+        match parse(s)? {
+            Value::String(string) => Ok(string),
+            value => panic!("{value:?} isn't a string"),
+        }
+    }
+
+    #[test]
+    fn escapes_and_surrogate_pairs() {
+        assert_eq!(
+            string(r#""\"\\\/\b\f\n\r\t""#).unwrap(),
+            "\"\\/\u{8}\u{c}\n\r\t"
+        );
+        assert_eq!(string(r#""\u00e9\u20AC""#).unwrap(), "é€");
+        assert_eq!(string(r#""\ud83d\ude00""#).unwrap(), "😀");
+        assert_eq!(string(r#""plain é😀""#).unwrap(), "plain é😀");
+        // a lone or reversed surrogate
+        assert!(parse(r#""\ud83d""#).is_err());
+        assert!(parse(r#""\ud83dx""#).is_err());
+        assert!(parse(r#""\ude00""#).is_err());
+        assert!(parse(r#""\ud83d\u0041""#).is_err());
+        assert!(parse(r#""\u12g4""#).is_err());
+        assert!(parse(r#""\x""#).is_err());
+    }
+
+    #[test]
+    fn numbers() {
+        for (number, integer) in [
+            ("0", true),
+            ("-12", true),
+            ("1.5", false),
+            ("-0.25e-3", false),
+            ("2E+10", false),
+        ] {
+            assert_eq!(parse(number), Ok(Value::Number { integer }), "{number}");
+        }
+        for number in ["01", "-", "1.", ".5", "1e", "+1", "0x10"] {
+            assert!(parse(number).is_err(), "{number}");
+        }
+    }
+
+    #[test]
+    fn malformed_input_reports_where_it_stops() {
+        assert_eq!(parse(""), Err(0));
+        assert_eq!(parse("{\"a\":1,}"), Err(7));
+        assert_eq!(parse("[1 2]"), Err(3));
+        assert_eq!(parse("{\"a\" 1}"), Err(5));
+        assert_eq!(parse("{a:1}"), Err(1));
+        assert_eq!(parse("\"open"), Err(5));
+        assert_eq!(parse("\"tab\there\""), Err(4));
+        assert_eq!(parse("nul"), Err(0));
+        assert_eq!(parse("true false"), Err(5));
+        assert_eq!(
+            parse(" {\"a\" : [true, null, {}] } "),
+            Ok(Value::Object(vec![(
+                "a".to_string(),
+                Value::Array(vec![Value::Bool, Value::Null, Value::Object(vec![])])
+            )]))
+        );
+    }
+
+    #[test]
+    fn parses_json_records() {
+        let message = "quote \" backslash \\ newline \n control \u{1} emoji 😀";
+        let line = crate::Format::cloudwatch()
+            .hide_time()
+            .renderer()
+            .render_to_string(
+                &log::Record::builder()
+                    .args(format_args!("{message}"))
+                    .level(log::Level::Warn)
+                    .target("app")
+                    .build(),
+            );
+        let Ok(Value::Object(members)) = parse(&line) else {
+            panic!("{line} isn't an object");
+        };
+        assert!(
+            members.contains(&("level".to_string(), Value::String("WARN".to_string())))
+        );
+        assert!(
+            members.contains(&("target".to_string(), Value::String("app".to_string())))
+        );
+        assert!(members
+            .contains(&("message".to_string(), Value::String(message.to_string()))));
+    }
+}
@@ -0,0 +1,151 @@
+//! Records rendered as whole JSON objects instead of lines
+
+use std::fmt::{Display, Write};
+
+use crate::{
+    components::{with_thread_name, Time},
+    fields::{Fields, KvFilter},
+    json,
+    sanitize::StripEscapes,
+};
+
+/// How records are laid out
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub(crate) enum Layout {
+    /// The time, level, message and so on after each other, optionally colored
+    #[default]
+    Line,
+    /// One JSON object per record, see [`Format::cloudwatch`](crate::Format::cloudwatch)
+    Json,
+}
+
+/// The members of [`Layout::Json`] objects that aren't key-values, see
+/// [`Format::json_key`](crate::Format::json_key)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsonKey {
+    /// `timestamp`
+    Timestamp,
+    /// `level`
+    Level,
+    /// `target`
+    Target,
+    /// `thread`
+    Thread,
+    /// `message`
+    Message,
+}
+
+/// What the members of [`Layout::Json`] objects are called
+#[derive(Clone, Debug)]
+pub(crate) struct JsonKeys {
+    pub(crate) timestamp: String,
+    pub(crate) level: String,
+    pub(crate) target: String,
+    pub(crate) thread: String,
+    pub(crate) message: String,
+    /// The object to put the key-values in, instead of next to the message
+    pub(crate) fields: Option<String>,
+}
+
+impl Default for JsonKeys {
+    fn default() -> Self {
+        Self {
+            timestamp: "timestamp".into(),
+            level: "level".into(),
+            target: "target".into(),
+            thread: "thread".into(),
+            message: "message".into(),
+            fields: None,
+        }
+    }
+}
+
+impl JsonKeys {
+    pub(crate) fn set(&mut self, key: JsonKey, name: String) {
+        let slot = match key {
+            JsonKey::Timestamp => &mut self.timestamp,
+            JsonKey::Level => &mut self.level,
+            JsonKey::Target => &mut self.target,
+            JsonKey::Thread => &mut self.thread,
+            JsonKey::Message => &mut self.message,
+        };
+        *slot = name;
+    }
+}
+
+/// `{"timestamp":1700000000123,"level":"INFO","target":"app","message":"hello",...}`,
+/// with the key-values of the record and the other [`Fields`] as members of the same
+/// object, or of a nested one
+pub(crate) struct JsonRecord<'a> {
+    pub(crate) keys: &'a JsonKeys,
+    pub(crate) time: &'a Time,
+    pub(crate) thread_name: bool,
+    /// Shown instead of the name of the thread
+    pub(crate) thread_placeholder: Option<&'static str>,
+    pub(crate) target: bool,
+    pub(crate) record: &'a log::Record<'a>,
+    pub(crate) message: &'a std::fmt::Arguments<'a>,
+    pub(crate) strip_escapes: bool,
+    pub(crate) fields: Fields<'a>,
+    pub(crate) filter: &'a KvFilter,
+    #[cfg(feature = "kv")]
+    pub(crate) limits: crate::kv::KvLimits,
+}
+
+impl Display for JsonRecord<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let keys = self.keys;
+        f.write_char('{')?;
+        json::write_str(f, &keys.timestamp)?;
+        write!(f, ":{},", self.time.unix_millis())?;
+        json::write_str(f, &keys.level)?;
+        f.write_char(':')?;
+        json::write_str(f, self.record.level().as_str())?;
+        if self.target {
+            f.write_char(',')?;
+            json::write_str(f, &keys.target)?;
+            f.write_char(':')?;
+            json::write_str(f, self.record.target())?;
+        }
+        if self.thread_name {
+            f.write_char(',')?;
+            json::write_str(f, &keys.thread)?;
+            f.write_char(':')?;
+            match self.thread_placeholder {
+                Some(placeholder) => json::write_str(f, placeholder)?,
+                None => with_thread_name(|name| json::write_str(f, name))?,
+            }
+        }
+        f.write_char(',')?;
+        json::write_str(f, &keys.message)?;
+        f.write_char(':')?;
+        if self.strip_escapes {
+            json::write_display(f, StripEscapes(self.message))?;
+        } else {
+            json::write_display(f, self.message)?;
+        }
+        let nested = keys.fields.as_ref();
+        if let Some(key) = nested {
+            f.write_char(',')?;
+            json::write_str(f, key)?;
+            f.write_str(":{")?;
+        }
+        #[cfg(feature = "kv")]
+        crate::kv::write_json_members(
+            f,
+            self.record.key_values(),
+            self.fields,
+            self.filter,
+            self.limits,
+            nested.is_some(),
+        )?;
+        #[cfg(not(feature = "kv"))]
+        crate::fields::write_json_members(f, self.fields, self.filter, nested.is_some())?;
+        if nested.is_some() {
+            f.write_char('}')?;
+        }
+        f.write_char('}')
+    }
+}
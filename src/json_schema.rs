@@ -0,0 +1,190 @@
+use std::fmt::{Display, Write};
+
+use crate::{
+    json::{self, Value},
+    Format,
+};
+
+/// Why a line isn't a JSON object as the [JSON formats](Format::cloudwatch) write them,
+/// see [`Format::validate_json_line`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum JsonLineError {
+    /// The line stops being JSON at this byte offset
+    Syntax(usize),
+    /// The line is JSON, but not an object
+    NotAnObject,
+    /// A member that every record has is missing
+    Missing(String),
+    /// A member has a value of the wrong type
+    WrongType { key: String, expected: &'static str },
+    /// A member next to the [nested fields](Format::nest_json_fields) that records
+    /// don't have
+    Unexpected(String),
+}
+
+impl Display for JsonLineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonLineError::Syntax(offset) => write!(f, "invalid JSON at byte {offset}"),
+            JsonLineError::NotAnObject => f.write_str("the line isn't a JSON object"),
+            JsonLineError::Missing(key) => write!(f, "the member {key:?} is missing"),
+            JsonLineError::WrongType { key, expected } => {
+                write!(f, "the member {key:?} isn't {expected}")
+            }
+            JsonLineError::Unexpected(key) => {
+                write!(f, "the member {key:?} isn't written by the format")
+            }
+        }
+    }
+}
+
+impl std::error::Error for JsonLineError {}
+
+/// What the value of a member of every record is
+#[derive(Clone, Copy)]
+enum Kind {
+    /// The time in milliseconds since the Unix epoch
+    Millis,
+    Level,
+    String,
+    Object,
+}
+
+impl Kind {
+    fn matches(self, value: &Value) -> bool {
+        match (self, value) {
+            (Kind::Millis, Value::Number { integer }) => *integer,
+            (Kind::Level, Value::String(level)) => {
+                log::Level::iter().any(|l| l.as_str() == level)
+            }
+            (Kind::String, Value::String(_)) | (Kind::Object, Value::Object(_)) => true,
+            _ => false,
+        }
+    }
+
+    fn expected(self) -> &'static str {
+        match self {
+            Kind::Millis => "an integer",
+            Kind::Level => "one of ERROR, WARN, INFO, DEBUG and TRACE",
+            Kind::String => "a string",
+            Kind::Object => "an object",
+        }
+    }
+
+    fn schema(self) -> &'static str {
+        match self {
+            Kind::Millis => {
+                r#"{"type": "integer", "description": "milliseconds since the Unix epoch"}"#
+            }
+            Kind::Level => r#"{"enum": ["ERROR", "WARN", "INFO", "DEBUG", "TRACE"]}"#,
+            Kind::String => r#"{"type": "string"}"#,
+            Kind::Object => r#"{"type": "object"}"#,
+        }
+    }
+}
+
+impl Format {
+    /// The members that every JSON object of this format has
+    fn json_members(&self) -> Vec<(&str, Kind)> {
+        let keys = &self.json_keys;
+        let mut members = vec![
+            (keys.timestamp.as_str(), Kind::Millis),
+            (keys.level.as_str(), Kind::Level),
+        ];
+        if self.targets {
+            members.push((&keys.target, Kind::String));
+        }
+        if self.thread_names {
+            members.push((&keys.thread, Kind::String));
+        }
+        members.push((&keys.message, Kind::String));
+        if let Some(fields) = &keys.fields {
+            members.push((fields, Kind::Object));
+        }
+        members
+    }
+
+    /// A JSON Schema of the objects that this format writes with a [JSON
+    /// layout](Self::cloudwatch), with the configured [keys](Self::json_key), so that
+    /// the consumers of the logs can check their parsers against it. The key-values are
+    /// any other members, or the members of the [nested](Self::nest_json_fields) object.
+    /// ```
+    /// use fern_format::Format;
+    ///
+    /// let schema = Format::cloudwatch().json_schema();
+    /// assert!(schema.contains(r#""required": ["timestamp", "level", "target", "message"]"#));
+    /// ```
+    pub fn json_schema(&self) -> String {
+        let members = self.json_members();
+        let mut schema = String::new();
+        let _ = self.write_json_schema(&mut schema, &members);
+        schema
+    }
+
+    fn write_json_schema(
+        &self,
+        out: &mut String,
+        members: &[(&str, Kind)],
+    ) -> std::fmt::Result {
+        out.push_str("{\n");
+        out.push_str(
+            "  \"$schema\": \"https://json-schema.org/draft/2020-12/schema\",\n",
+        );
+        out.push_str("  \"title\": \"fern-format record\",\n");
+        out.push_str("  \"type\": \"object\",\n");
+        out.push_str("  \"properties\": {");
+        for (i, (key, kind)) in members.iter().enumerate() {
+            out.push_str(if i == 0 { "\n    " } else { ",\n    " });
+            json::write_str(out, key)?;
+            write!(out, ": {}", kind.schema())?;
+        }
+        out.push_str("\n  },\n  \"required\": [");
+        for (i, (key, _)) in members.iter().enumerate() {
+            if i > 0 {
+                out.push_str(", ");
+            }
+            json::write_str(out, key)?;
+        }
+        let nested = self.json_keys.fields.is_some();
+        writeln!(out, "],\n  \"additionalProperties\": {}\n}}", !nested)
+    }
+
+    /// Checks that `line` is a JSON object as this format writes them with a [JSON
+    /// layout](Self::cloudwatch), i.e. that it matches the [schema](Self::json_schema)
+    /// ```
+    /// use fern_format::Format;
+    ///
+    /// let format = Format::cloudwatch();
+    /// let line = r#"{"timestamp":1700000000123,"level":"INFO","target":"app","message":"hi"}"#;
+    /// assert_eq!(format.validate_json_line(line), Ok(()));
+    /// assert!(format.validate_json_line(r#"{"level":"INFO"}"#).is_err());
+    /// ```
+    pub fn validate_json_line(&self, line: &str) -> Result<(), JsonLineError> {
+        let Value::Object(object) = json::parse(line).map_err(JsonLineError::Syntax)?
+        else {
+            return Err(JsonLineError::NotAnObject);
+        };
+        let members = self.json_members();
+        for (key, kind) in &members {
+            let Some((_, value)) = object.iter().find(|(k, _)| k == key) else {
+                return Err(JsonLineError::Missing(key.to_string()));
+            };
+            if !kind.matches(value) {
+                return Err(JsonLineError::WrongType {
+                    key: key.to_string(),
+                    expected: kind.expected(),
+                });
+            }
+        }
+        if self.json_keys.fields.is_some() {
+            let unexpected = object
+                .iter()
+                .find(|(k, _)| !members.iter().any(|(m, _)| m == k));
+            if let Some((key, _)) = unexpected {
+                return Err(JsonLineError::Unexpected(key.clone()));
+            }
+        }
+        Ok(())
+    }
+}
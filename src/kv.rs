@@ -0,0 +1,523 @@
+use std::{
+    fmt::{Display, Write},
+    time::Duration,
+};
+
+use log::kv::{Key, Source, ToValue, Value, VisitSource, VisitValue};
+
+use crate::{
+    elapsed::Short,
+    fields::{self, Fields, KvFilter, KvStyles, Quoted},
+    json,
+};
+
+/// How the key-values of a record are appended to the line
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub(crate) enum KvFormat {
+    /// ` key=value key2="quoted value"`
+    #[default]
+    Pairs,
+    /// ` {"key":"value","key2":42}`
+    Json,
+}
+
+/// Limits on how much of the key-values of a record is rendered
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct KvLimits {
+    /// Cut off values after this many characters
+    pub(crate) value: Option<usize>,
+    /// Leave out the pairs after this many bytes of them
+    pub(crate) total: Option<usize>,
+}
+
+/// Renders the key-values of a record together with the other [`Fields`] in the given
+/// [`KvFormat`], sorted by key
+pub(crate) struct KeyValues<'a> {
+    source: &'a dyn Source,
+    fields: Fields<'a>,
+    filter: &'a KvFilter,
+    format: KvFormat,
+    styles: Option<KvStyles>,
+    human_durations: bool,
+    human_sizes: Option<&'a [String]>,
+    limits: KvLimits,
+}
+
+impl<'a> KeyValues<'a> {
+    pub(crate) fn new(
+        source: &'a dyn Source,
+        fields: Fields<'a>,
+        filter: &'a KvFilter,
+        format: KvFormat,
+        styles: Option<KvStyles>,
+    ) -> Self {
+        Self {
+            source,
+            fields,
+            filter,
+            format,
+            styles,
+            human_durations: false,
+            human_sizes: None,
+            limits: KvLimits::default(),
+        }
+    }
+
+    /// Show pairs with the unit of a duration in their keys as durations
+    pub(crate) fn human_durations(mut self, enable: bool) -> Self {
+        self.human_durations = enable;
+        self
+    }
+
+    /// Show pairs with these keys, or ending with them, as byte sizes
+    pub(crate) fn human_sizes(mut self, keys: Option<&'a [String]>) -> Self {
+        self.human_sizes = keys;
+        self
+    }
+
+    /// Render no more than `limits` allow
+    pub(crate) fn limits(mut self, limits: KvLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Writes ` key=value`, with the value humanized or cut off if it should be
+    fn write_pair(
+        &self,
+        f: &mut impl Write,
+        key: &Key<'_>,
+        value: &Value<'_>,
+        styles: Option<KvStyles>,
+    ) -> std::fmt::Result {
+        if self.human_durations {
+            if let Some(duration) = duration(key.as_str(), value) {
+                return fields::write_pair(f, key, Short(duration), styles);
+            }
+        }
+        if let Some(keys) = self.human_sizes {
+            if let Some(size) = value.to_u64().filter(|_| is_size(key.as_str(), keys)) {
+                return fields::write_pair(f, key, Bytes(size), styles);
+            }
+        }
+        // numbers are short anyway, and cutting them off would change them
+        let max = self.limits.value.filter(|_| value.to_f64().is_none());
+        match nested_json(value) {
+            Some(json) => fields::write_pair(f, key, Truncated(json, max), styles),
+            None => fields::write_pair(f, key, Quoted(Truncated(value, max)), styles),
+        }
+    }
+}
+
+impl<'a> Display for KeyValues<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut pairs = SortedPairs::new(self.source, self.fields, self.filter);
+        let mut next = || pairs.next().map_err(|_| std::fmt::Error);
+
+        match self.format {
+            KvFormat::Pairs => {
+                let mut written = 0;
+                while let Some((key, value)) = next()? {
+                    if let Some(max) = self.limits.total {
+                        let mut counter = Counter(0);
+                        self.write_pair(&mut counter, &key, &value, None)?;
+                        written += counter.0;
+                        if written > max {
+                            return f.write_str(" …");
+                        }
+                    }
+                    self.write_pair(f, &key, &value, self.styles)?;
+                }
+                Ok(())
+            }
+            KvFormat::Json => {
+                let pair = next()?;
+                if pair.is_none() && self.fields.correlation_id.is_none() {
+                    return Ok(());
+                }
+
+                f.write_str(" {")?;
+                write_members(f, self.fields, &mut pairs, pair, self.limits, true)?;
+                f.write_char('}')
+            }
+        }
+    }
+}
+
+/// The duration that `value` is if `key` ends with its unit, e.g. `elapsed_ms`
+fn duration(key: &str, value: &Value<'_>) -> Option<Duration> {
+    const UNITS: [(&str, f64); 4] =
+        [("_ns", 1e-9), ("_us", 1e-6), ("_ms", 1e-3), ("_secs", 1.0)];
+    let (_, unit) = UNITS.iter().find(|(suffix, _)| key.ends_with(suffix))?;
+    Duration::try_from_secs_f64(value.to_f64()? * unit).ok()
+}
+
+/// If `key` is one of `keys`, or ends with one of them after an underscore
+fn is_size(key: &str, keys: &[String]) -> bool {
+    keys.iter().any(|size| {
+        key.strip_suffix(size.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.ends_with('_'))
+    })
+}
+
+/// A number of bytes in its largest binary unit with one decimal, e.g. `512B` or
+/// `3.4MiB`
+struct Bytes(u64);
+
+impl Display for Bytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const UNITS: [&str; 6] = ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+        if self.0 < 1024 {
+            return write!(f, "{}B", self.0);
+        }
+        let mut size = self.0 as f64 / 1024.0;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        write!(f, "{:.1}{}", size, UNITS[unit])
+    }
+}
+
+/// Writes the correlation id and the key-values of `source` and `fields` that pass
+/// `filter` as `"key":value` members of a JSON object, sorted by key and within
+/// `limits`. `first` is if nothing has been written into the object yet, otherwise a
+/// comma is written first.
+pub(crate) fn write_json_members(
+    f: &mut std::fmt::Formatter<'_>,
+    source: &dyn Source,
+    fields: Fields<'_>,
+    filter: &KvFilter,
+    limits: KvLimits,
+    first: bool,
+) -> std::fmt::Result {
+    let mut pairs = SortedPairs::new(source, fields, filter);
+    let pair = pairs.next().map_err(|_| std::fmt::Error)?;
+    write_members(f, fields, &mut pairs, pair, limits, first)
+}
+
+/// [`write_json_members`], with the first pair already taken from `pairs`
+fn write_members<'a>(
+    f: &mut std::fmt::Formatter<'_>,
+    fields: Fields<'_>,
+    pairs: &mut SortedPairs<'a>,
+    mut pair: Option<(Key<'a>, Value<'a>)>,
+    limits: KvLimits,
+    mut first: bool,
+) -> std::fmt::Result {
+    let mut written = 0;
+    if let Some(id) = fields.correlation_id {
+        if !first {
+            f.write_char(',')?;
+        }
+        first = false;
+        json::write_str(f, fields.correlation_key)?;
+        f.write_char(':')?;
+        json::write_str(f, id)?;
+    }
+    while let Some((key, value)) = pair {
+        if !first {
+            f.write_char(',')?;
+        }
+        first = false;
+        if let Some(max) = limits.total {
+            let mut counter = Counter(0);
+            json::write_str(&mut counter, key.as_str())?;
+            write_json_value(&mut counter, &value, limits.value)?;
+            written += counter.0 + 2;
+            if written > max {
+                return f.write_str("\"_truncated\":true");
+            }
+        }
+        json::write_str(f, key.as_str())?;
+        f.write_char(':')?;
+        write_json_value(f, &value, limits.value)?;
+        pair = pairs.next().map_err(|_| std::fmt::Error)?;
+    }
+    Ok(())
+}
+
+/// Writes `value` as JSON, with strings cut off after `max` characters
+fn write_json_value(
+    out: &mut impl Write,
+    value: &Value<'_>,
+    max: Option<usize>,
+) -> std::fmt::Result {
+    match nested_json(value) {
+        // nested JSON that is too long can only be cut off as a string
+        Some(json) if max.is_some_and(|max| json.chars().count() > max) => {
+            json::write_display(out, Truncated(json, max))
+        }
+        Some(json) => out.write_str(&json),
+        None => value
+            .visit(JsonValue { out, max })
+            .map_err(|_| std::fmt::Error),
+    }
+}
+
+/// `.0` cut off with an ellipsis after `.1` characters, or all of it without a limit
+struct Truncated<T>(T, Option<usize>);
+
+impl<T: Display> Display for Truncated<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(max) = self.1 else {
+            return self.0.fmt(f);
+        };
+
+        let mut limit = Limit {
+            inner: &mut *f,
+            left: max,
+            cut: false,
+        };
+        match write!(limit, "{}", self.0) {
+            // the error stops the formatting of the rest
+            Err(_) if limit.cut => f.write_char('…'),
+            result => result,
+        }
+    }
+}
+
+/// Writes the first `left` characters, and fails after that
+struct Limit<W> {
+    inner: W,
+    left: usize,
+    cut: bool,
+}
+
+impl<W: Write> Write for Limit<W> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        match s.char_indices().nth(self.left) {
+            Some((end, _)) => {
+                self.inner.write_str(&s[..end])?;
+                self.left = 0;
+                self.cut = true;
+                Err(std::fmt::Error)
+            }
+            None => {
+                self.left -= s.chars().count();
+                self.inner.write_str(s)
+            }
+        }
+    }
+}
+
+/// Counts the bytes written to it
+struct Counter(usize);
+
+impl Write for Counter {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// The pairs of `source` and `fields` that pass `filter`, sorted by key so that the
+/// output is stable between runs. Records only have a handful of pairs, so instead of
+/// collecting and sorting them, all pairs are visited again to find each next one.
+struct SortedPairs<'a> {
+    source: &'a dyn Source,
+    fields: Fields<'a>,
+    filter: &'a KvFilter,
+    /// The key and position of the last returned pair, which breaks ties between
+    /// duplicate keys
+    last: Option<(Key<'a>, usize)>,
+}
+
+impl<'a> SortedPairs<'a> {
+    fn new(source: &'a dyn Source, fields: Fields<'a>, filter: &'a KvFilter) -> Self {
+        Self {
+            source,
+            fields,
+            filter,
+            last: None,
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<(Key<'a>, Value<'a>)>, log::kv::Error> {
+        let mut find = FindNext {
+            filter: self.filter,
+            last: self.last.as_ref().map(|(key, i)| (key.as_str(), *i)),
+            position: 0,
+            next: None,
+        };
+        self.source.visit(&mut find)?;
+        for (key, value) in self.fields.iter() {
+            find.offer(Key::from_str(key), Value::from(value));
+        }
+
+        let next = find.next;
+        Ok(next.map(|(key, value, i)| {
+            self.last = Some((key.clone(), i));
+            (key, value)
+        }))
+    }
+}
+
+/// Finds the smallest pair after `last`
+struct FindNext<'kvs, 'f> {
+    filter: &'f KvFilter,
+    last: Option<(&'f str, usize)>,
+    position: usize,
+    next: Option<(Key<'kvs>, Value<'kvs>, usize)>,
+}
+
+impl<'kvs, 'f> FindNext<'kvs, 'f> {
+    fn offer(&mut self, key: Key<'kvs>, value: Value<'kvs>) {
+        let position = self.position;
+        self.position += 1;
+        if !self.filter.allows(key.as_str()) {
+            return;
+        }
+
+        let this = (key.as_str(), position);
+        let after_last = self.last.is_none_or(|last| this > last);
+        let before_next = match &self.next {
+            Some((k, _, i)) => this < (k.as_str(), *i),
+            None => true,
+        };
+        if after_last && before_next {
+            self.next = Some((key, value, position));
+        }
+    }
+}
+
+impl<'kvs, 'f> VisitSource<'kvs> for FindNext<'kvs, 'f> {
+    fn visit_pair(
+        &mut self,
+        key: Key<'kvs>,
+        value: Value<'kvs>,
+    ) -> Result<(), log::kv::Error> {
+        self.offer(key, value);
+        Ok(())
+    }
+}
+
+/// Renders maps and sequences captured with `:serde` as inline JSON. Other values are
+/// left to the normal rendering.
+#[cfg(feature = "kv-serde")]
+fn nested_json(value: &Value<'_>) -> Option<String> {
+    let primitive = value.to_borrowed_str().is_some()
+        || value.to_u64().is_some()
+        || value.to_i64().is_some()
+        || value.to_f64().is_some()
+        || value.to_bool().is_some()
+        || value.to_char().is_some();
+    if primitive {
+        return None;
+    }
+
+    let json = json::to_string(value)?;
+    (json.starts_with('{') || json.starts_with('[')).then_some(json)
+}
+
+#[cfg(not(feature = "kv-serde"))]
+fn nested_json(_value: &Value<'_>) -> Option<String> {
+    None
+}
+
+/// Writes numbers, booleans and nulls as their JSON counterparts, and everything else
+/// as strings that are cut off after `max` characters
+struct JsonValue<'a, W> {
+    out: &'a mut W,
+    max: Option<usize>,
+}
+
+impl<'a, 'v, W: Write> VisitValue<'v> for JsonValue<'a, W> {
+    fn visit_any(&mut self, value: Value<'_>) -> Result<(), log::kv::Error> {
+        Ok(json::write_display(self.out, Truncated(value, self.max))?)
+    }
+
+    fn visit_null(&mut self) -> Result<(), log::kv::Error> {
+        Ok(self.out.write_str("null")?)
+    }
+
+    fn visit_u64(&mut self, value: u64) -> Result<(), log::kv::Error> {
+        Ok(write!(self.out, "{}", value)?)
+    }
+
+    fn visit_i64(&mut self, value: i64) -> Result<(), log::kv::Error> {
+        Ok(write!(self.out, "{}", value)?)
+    }
+
+    fn visit_u128(&mut self, value: u128) -> Result<(), log::kv::Error> {
+        Ok(write!(self.out, "{}", value)?)
+    }
+
+    fn visit_i128(&mut self, value: i128) -> Result<(), log::kv::Error> {
+        Ok(write!(self.out, "{}", value)?)
+    }
+
+    fn visit_f64(&mut self, value: f64) -> Result<(), log::kv::Error> {
+        Ok(json::write_f64(self.out, value)?)
+    }
+
+    fn visit_bool(&mut self, value: bool) -> Result<(), log::kv::Error> {
+        Ok(write!(self.out, "{}", value)?)
+    }
+
+    fn visit_str(&mut self, value: &str) -> Result<(), log::kv::Error> {
+        match self.max {
+            Some(_) => Ok(json::write_display(self.out, Truncated(value, self.max))?),
+            None => Ok(json::write_str(self.out, value)?),
+        }
+    }
+}
+
+/// A key-value of a record without its lifetime. Numbers and booleans stay numbers and
+/// booleans, everything else is formatted into a string.
+pub(crate) enum OwnedValue {
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    Bool(bool),
+    Str(String),
+}
+
+impl OwnedValue {
+    pub(crate) fn collect(source: &dyn Source) -> Vec<(String, OwnedValue)> {
+        struct Collect(Vec<(String, OwnedValue)>);
+
+        impl<'kvs> VisitSource<'kvs> for Collect {
+            fn visit_pair(
+                &mut self,
+                key: Key<'kvs>,
+                value: Value<'kvs>,
+            ) -> Result<(), log::kv::Error> {
+                self.0.push((key.to_string(), OwnedValue::new(&value)));
+                Ok(())
+            }
+        }
+
+        let mut collect = Collect(Vec::new());
+        let _ = source.visit(&mut collect);
+        collect.0
+    }
+
+    pub(crate) fn new(value: &Value<'_>) -> Self {
+        if let Some(v) = value.to_bool() {
+            OwnedValue::Bool(v)
+        } else if let Some(v) = value.to_u64() {
+            OwnedValue::U64(v)
+        } else if let Some(v) = value.to_i64() {
+            OwnedValue::I64(v)
+        } else if let Some(v) = value.to_f64() {
+            OwnedValue::F64(v)
+        } else {
+            OwnedValue::Str(value.to_string())
+        }
+    }
+}
+
+impl ToValue for OwnedValue {
+    fn to_value(&self) -> Value<'_> {
+        match self {
+            OwnedValue::U64(v) => Value::from(*v),
+            OwnedValue::I64(v) => Value::from(*v),
+            OwnedValue::F64(v) => Value::from(*v),
+            OwnedValue::Bool(v) => Value::from(*v),
+            OwnedValue::Str(v) => Value::from(v.as_str()),
+        }
+    }
+}
@@ -1,18 +1,160 @@
 use std::{
-    collections::HashMap,
-    fmt::Display,
-    sync::{
-        atomic::{AtomicU8, Ordering},
-        RwLock,
-    },
-    thread::ThreadId,
+    fmt::{Display, Write},
+    sync::Arc,
 };
 
-use owo_colors::{OwoColorize, Style};
-use time::{OffsetDateTime, UtcOffset};
-
+pub use owo_colors::Style;
+#[cfg(feature = "supports-color")]
 pub use supports_color::Stream;
 
+mod banner;
+mod budget;
+mod buffered;
+mod checksum;
+pub mod components;
+#[cfg(feature = "serde")]
+mod config;
+#[cfg(feature = "console")]
+mod console_style;
+pub mod context;
+mod counter;
+mod dedup;
+mod divider;
+mod elapsed;
+#[cfg(feature = "env_logger")]
+mod env_logger_format;
+mod error_chain;
+mod fields;
+#[cfg(feature = "flexi_logger")]
+mod flexi_logger_format;
+mod github_actions;
+#[cfg(feature = "hash-chain")]
+mod hash_chain;
+mod html;
+#[cfg(unix)]
+mod journald;
+mod json;
+mod json_record;
+mod json_schema;
+#[cfg(feature = "kv")]
+mod kv;
+mod line_buffer;
+mod line_ending;
+mod lock;
+#[cfg(feature = "log4rs")]
+mod log4rs_encoder;
+mod logger;
+mod loki;
+mod non_blocking;
+mod options;
+mod panic;
+mod presets;
+mod rainbow;
+mod rate_limit;
+mod reentrancy;
+mod reload;
+mod report;
+mod rotating_file;
+mod sample;
+mod sanitize;
+mod scope;
+mod service_messages;
+#[cfg(feature = "slog")]
+mod slog_drain;
+mod splunk;
+mod stopwatch;
+mod styled;
+mod suspend;
+mod syslog;
+pub mod template;
+mod test_sink;
+mod theme;
+#[cfg(feature = "toml")]
+mod theme_watcher;
+mod thread_label;
+mod tick;
+mod timestamp;
+#[cfg(feature = "tracing")]
+mod tracing_layer;
+mod validation;
+mod width;
+pub use banner::banner;
+pub use buffered::Buffered;
+pub use checksum::checksum_matches;
+#[cfg(feature = "toml")]
+pub use config::ConfigError;
+#[cfg(feature = "console")]
+pub use console_style::{from_console_style, to_console_style};
+pub use dedup::Dedup;
+pub use divider::divider;
+pub use error_chain::ErrorChain;
+pub use github_actions::GithubActions;
+#[cfg(feature = "hash-chain")]
+pub use hash_chain::{verify_hash_chain, HashChain, HashChainError};
+pub use html::Html;
+#[cfg(unix)]
+pub use journald::Journald;
+pub use json_record::JsonKey;
+pub use json_schema::JsonLineError;
+pub use line_ending::LineEnding;
+#[cfg(feature = "log4rs")]
+pub use log4rs_encoder::Log4rsEncoder;
+pub use logger::Logger;
+pub use loki::Loki;
+pub use non_blocking::{NonBlocking, NonBlockingLog, Overflow, WorkerGuard};
+pub use panic::install_panic_hook;
+pub use presets::init;
+pub use rate_limit::RateLimit;
+pub use reload::Handle;
+pub use report::set_error_handler;
+pub use rotating_file::RotatingFile;
+pub use sample::Sample;
+pub use scope::{scope, ScopeGuard};
+pub use service_messages::ServiceMessages;
+#[cfg(feature = "slog")]
+pub use slog_drain::SlogDrain;
+pub use splunk::SplunkHec;
+pub use stopwatch::{checkpoint, Stopwatch};
+pub use suspend::SuspendOutput;
+pub use syslog::{Facility, Syslog};
+pub use test_sink::TestSink;
+pub use theme::Theme;
+#[cfg(feature = "toml")]
+pub use theme_watcher::ThemeWatcher;
+pub use thread_label::{clear_thread_label, set_thread_label};
+pub use tick::{set_tick, tick};
+#[cfg(feature = "time")]
+pub use timestamp::Clock;
+pub use timestamp::Timestamp;
+#[cfg(feature = "tracing")]
+pub use tracing_layer::FormatLayer;
+pub use validation::FormatError;
+
+use checksum::{crc32, Checksum};
+use components::{Level, LevelEscapes, Message, ThreadName, Time};
+use counter::{Count, TargetCounter};
+use elapsed::{Elapsed, TargetClock};
+use fields::{CorrelationId, Fields, KvFilter, StaticFields};
+use json_record::{JsonKeys, JsonRecord, Layout};
+#[cfg(feature = "kv")]
+use kv::{KvFormat, KvLimits};
+use line_buffer::LineBuffer;
+use rainbow::Rainbow;
+use reentrancy::Formatting;
+use report::report;
+use scope::{ScopeStyle, Scopes};
+use styled::Styled;
+use tick::Tick;
+use width::AutoWidth;
+
+/// The configuration of the log format, see [`callback`](Self::callback).
+///
+/// With the `serde` feature it can be deserialized, e.g. from a section of the
+/// configuration file of an application. The options are named like the ones of
+/// [`from_env`](Self::from_env) and the missing ones keep their defaults.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "config::Options"))]
 pub struct Format {
     /// How to decide if colors should be used at all
     colorize: Colorize,
@@ -20,14 +162,140 @@ pub struct Format {
     /// If thread names should be colored uniquely
     color_threads: bool,
 
+    /// If the [`tick`] should be logged
+    ticks: bool,
+
     /// If thread names should be logged
     thread_names: bool,
+
+    /// If every thread should be shown as `thread`, whatever its name
+    thread_placeholders: bool,
+
+    /// If the time of records should be shown
+    show_time: bool,
+
+    /// How to show the time of records
+    timestamp: Timestamp,
+
+    /// If the time should start with the weekday
+    weekday: bool,
+
+    /// If the time should be followed by the time in UTC
+    also_utc: bool,
+
+    /// Show the time in this time zone instead of the local one
+    #[cfg(feature = "time")]
+    utc_offset: Option<time::UtcOffset>,
+
+    /// Where to get the current time from
+    #[cfg(feature = "time")]
+    clock: Clock,
+
+    /// If targets should be logged
+    targets: bool,
+
+    /// If the main thread should be called `main` even if it has no name
+    label_main_thread: bool,
+
+    /// Pad or truncate thread names to this many columns
+    thread_name_width: Option<usize>,
+
+    /// Pad or truncate targets to this many columns
+    target_width: Option<usize>,
+
+    /// Widen the thread and target columns to the widest value so far, up to this many
+    /// columns
+    auto_width: Option<usize>,
+
+    /// Targets to highlight, and how
+    watched_targets: Vec<(String, Style)>,
+
+    /// Dim records whose targets start with these
+    dimmed_targets: Vec<String>,
+
+    /// If the time since the previous record of the same target should be shown
+    target_elapsed: bool,
+
+    /// If the number of records of each target should be shown
+    target_counts: bool,
+
+    /// If messages should be colored along a gradient instead of by level
+    rainbow: bool,
+
+    /// If only the 8 basic colors should be used
+    basic_colors: bool,
+
+    /// If only warnings and errors should be colored by level
+    color_warnings_only: bool,
+
+    /// Truncate messages longer than this many columns
+    message_width: Option<usize>,
+
+    /// If ANSI escape sequences should be removed from messages
+    strip_escapes: bool,
+
+    /// How to terminate lines inside of messages
+    line_ending: LineEnding,
+
+    /// If lines should start with the syslog severity of their level, e.g. `<6>`
+    severity_prefixes: bool,
+
+    /// If lines should end with the CRC-32 of the rest of the line
+    checksums: bool,
+
+    /// Cut lines longer than this many bytes
+    max_record_bytes: Option<usize>,
+
+    /// How to render the key-values of records
+    #[cfg(feature = "kv")]
+    kv_format: KvFormat,
+
+    /// If key-values with the unit of a duration in their key should be humanized
+    #[cfg(feature = "kv")]
+    human_durations: bool,
+
+    /// If key-values with the [`human_size_keys`](Self::human_size_keys) should be
+    /// humanized as byte sizes
+    #[cfg(feature = "kv")]
+    human_sizes: bool,
+
+    /// The keys of key-values that are byte sizes
+    #[cfg(feature = "kv")]
+    human_size_keys: Vec<String>,
+
+    /// How much of the key-values to render
+    #[cfg(feature = "kv")]
+    kv_limits: KvLimits,
+
+    /// The styles used when colors are enabled
+    theme: Theme,
+
+    /// Which key-values to render
+    kv_filter: KvFilter,
+
+    /// Key-values added to every record
+    static_fields: StaticFields,
+
+    /// How to show the active [`scope`]s
+    scope_style: ScopeStyle,
+
+    /// What to call the correlation id in structured output
+    correlation_key: String,
+
+    /// Lines or JSON objects
+    layout: Layout,
+
+    /// What to call the members of JSON objects
+    json_keys: JsonKeys,
 }
 
+#[derive(Clone, Copy, Debug)]
 enum Colorize {
     BlackWhite,
     Color,
     ColorIf(Stream),
+    /// Whatever `RUST_LOG_STYLE` says, like env_logger, and no colors without it
+    FromEnv(Stream),
 }
 
 impl Colorize {
@@ -36,17 +304,116 @@ impl Colorize {
             Colorize::BlackWhite => false,
             Colorize::Color => true,
             Colorize::ColorIf(stream) => supports_color(*stream),
+            Colorize::FromEnv(stream) => match std::env::var("RUST_LOG_STYLE").as_deref()
+            {
+                Ok("always") => true,
+                Ok("auto") => supports_color(*stream),
+                Ok("never") | Err(_) => false,
+                Ok(other) => {
+                    report(format_args!(
+                        "Ignoring RUST_LOG_STYLE={other:?}: expected always, auto or never"
+                    ));
+                    false
+                }
+            },
         }
     }
 }
 
+impl Default for Format {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Format {
     /// Creates a blank `Format` that prints without colors and no thread names
     pub fn new() -> Self {
         Self {
             colorize: Colorize::BlackWhite,
             color_threads: false,
+            ticks: false,
             thread_names: false,
+            thread_placeholders: false,
+            show_time: true,
+            timestamp: Timestamp::Time,
+            weekday: false,
+            also_utc: false,
+            #[cfg(feature = "time")]
+            utc_offset: None,
+            #[cfg(feature = "time")]
+            clock: time::OffsetDateTime::now_utc,
+            targets: true,
+            label_main_thread: false,
+            thread_name_width: None,
+            target_width: None,
+            auto_width: None,
+            watched_targets: Vec::new(),
+            dimmed_targets: Vec::new(),
+            target_elapsed: false,
+            target_counts: false,
+            rainbow: false,
+            basic_colors: false,
+            color_warnings_only: false,
+            message_width: None,
+            strip_escapes: false,
+            line_ending: LineEnding::Lf,
+            severity_prefixes: false,
+            checksums: false,
+            max_record_bytes: None,
+            #[cfg(feature = "kv")]
+            kv_format: KvFormat::Pairs,
+            #[cfg(feature = "kv")]
+            human_durations: false,
+            #[cfg(feature = "kv")]
+            human_sizes: false,
+            #[cfg(feature = "kv")]
+            human_size_keys: ["bytes", "size", "len"].map(String::from).to_vec(),
+            #[cfg(feature = "kv")]
+            kv_limits: KvLimits::default(),
+            theme: Theme::new(),
+            kv_filter: KvFilter::All,
+            static_fields: StaticFields::new(),
+            scope_style: ScopeStyle::Breadcrumbs,
+            correlation_key: context::CORRELATION_ID.to_string(),
+            layout: Layout::Line,
+            json_keys: JsonKeys::default(),
+        }
+    }
+
+    /// Creates a `Format` configured by the option string in the environment variable
+    /// `var`, so that users can change the appearance of the logs without new flags.
+    /// Options that are left out keep their defaults from [`new`](Self::new).
+    ///
+    /// The options are separated by commas, e.g. `color=auto,threads,time=rfc3339`:
+    /// - `color=auto|always|never|env`, where `env` follows `RUST_LOG_STYLE` like
+    ///   [`color_from_env`](Self::color_from_env), `basic-colors[=on|off]` and
+    ///   `color-warnings-only[=on|off]`
+    /// - `ticks[=on|off]`
+    /// - `threads[=on|off]`, `thread-colors[=on|off]` and `label-main-thread[=on|off]`
+    /// - `time=time|datetime|rfc3339`, `hide-time[=on|off]`, `weekday[=on|off]` and
+    ///   `also-utc[=on|off]`
+    /// - `target=on|off`, and `dim=PREFIX` for each target prefix to dim
+    /// - `thread-width=N|off`, `target-width=N|off` and `message-width=N|off`
+    /// - `auto-width=N|off`
+    /// - `target-elapsed[=on|off]` and `target-counts[=on|off]`
+    /// - `rainbow[=on|off]`
+    /// - `strip-escapes[=on|off]`
+    /// - `severity-prefixes[=on|off]`, `checksums[=on|off]` and `max-record-bytes=N|off`
+    /// - `layout=line|json`
+    /// - `kv=pairs|json`, `human-durations[=on|off]`, `human-sizes[=on|off]`,
+    ///   `kv-value-len=N|off` and `kv-len=N|off`, with the `kv` feature
+    /// - `scopes=breadcrumbs|indent`
+    ///
+    /// Watched targets, static fields, key-value filters and the names of the members of
+    /// JSON objects need styles, lists or tables and can only be set with the builders or
+    /// in a [configuration file](Self::from_config_file).
+    ///
+    /// Invalid options are reported, see [`set_error_handler`], and otherwise ignored.
+    pub fn from_env(var: &str) -> Self {
+        match std::env::var(var) {
+            Ok(options) => options::apply(Self::new(), &options),
+            Err(_) => Self::new(),
         }
     }
 
@@ -56,262 +423,1048 @@ impl Format {
         self
     }
 
+    /// Enable colors like env_logger does: with `RUST_LOG_STYLE=always`, or with
+    /// `RUST_LOG_STYLE=auto` if the given stream supports them. There are no colors
+    /// without the variable.
+    pub fn color_from_env(mut self, stream: Stream) -> Self {
+        self.colorize = Colorize::FromEnv(stream);
+        self
+    }
+
     /// Force enable colors
-    pub fn force_colors(mut self) -> Self {
-        self.colorize = Colorize::Color;
+    pub fn force_colors(self) -> Self {
+        self.force_colors_if(true)
+    }
+
+    /// Force enable colors if `enable`, and force disable them otherwise
+    pub fn force_colors_if(mut self, enable: bool) -> Self {
+        self.colorize = if enable {
+            Colorize::Color
+        } else {
+            Colorize::BlackWhite
+        };
+        self
+    }
+
+    /// Print the current [`tick`] after the time, e.g. ` @1234`, see [`set_tick`]
+    pub fn ticks(self) -> Self {
+        self.ticks_if(true)
+    }
+
+    /// [`ticks`](Self::ticks) if `enable`
+    pub fn ticks_if(mut self, enable: bool) -> Self {
+        self.ticks = enable;
         self
     }
 
     /// Print thread names/id
-    pub fn thread_names(mut self) -> Self {
+    pub fn thread_names(self) -> Self {
+        self.thread_names_if(true)
+    }
+
+    /// Print thread names/id if `enable`
+    pub fn thread_names_if(mut self, enable: bool) -> Self {
+        self.thread_names = enable;
+        self
+    }
+
+    /// Only use the 8 basic colors, without their bright variants and without italics,
+    /// for old terminals and serial consoles that show them wrong. This doesn't change
+    /// the styles that are given to the format, such as in
+    /// [`watch_target`](Self::watch_target).
+    pub fn basic_colors(self) -> Self {
+        self.basic_colors_if(true)
+    }
+
+    /// [`basic_colors`](Self::basic_colors) if `enable`
+    pub fn basic_colors_if(mut self, enable: bool) -> Self {
+        self.basic_colors = enable;
+        self
+    }
+
+    /// Only color the messages of warnings and errors by their level, and show the level
+    /// of the other records as ` [INFO]` and so on instead, since coloring every message
+    /// can be too noisy
+    pub fn color_warnings_only(self) -> Self {
+        self.color_warnings_only_if(true)
+    }
+
+    /// [`color_warnings_only`](Self::color_warnings_only) if `enable`
+    pub fn color_warnings_only_if(mut self, enable: bool) -> Self {
+        self.color_warnings_only = enable;
+        self
+    }
+
+    /// Give each thread its own color on their printed names. There are
+    /// [`THREAD_STYLES`](components::THREAD_STYLES) colors, see
+    /// [`set_thread_style_overflow`](components::set_thread_style_overflow) for the threads
+    /// after that.
+    pub fn uniquely_color_threads(self) -> Self {
+        self.uniquely_color_threads_if(true)
+    }
+
+    /// Give each thread its own color on their printed names if `enable`. Disabling it
+    /// still prints the names.
+    pub fn uniquely_color_threads_if(mut self, enable: bool) -> Self {
+        self.color_threads = enable;
+        self.thread_names |= enable;
+        self
+    }
+
+    /// Call the main thread `main` even if it has no name, which is the case when the
+    /// program isn't started by Rust, e.g. in libraries loaded by other languages. The
+    /// thread that creates the [`callback`](Self::callback) counts as the main thread.
+    pub fn label_main_thread(mut self) -> Self {
+        self.label_main_thread = true;
         self.thread_names = true;
         self
     }
 
-    /// Give each thread its own color on their printed names
-    pub fn uniquely_color_threads(mut self) -> Self {
-        self.color_threads = true;
-        self.thread_names()
+    /// Give the main thread `style` instead of a unique color, so that it is easy to spot
+    /// among the workers. Only used when threads are [uniquely
+    /// colored](Self::uniquely_color_threads).
+    pub fn main_thread_style(mut self, style: Style) -> Self {
+        self.theme = self.theme.main_thread(style);
+        self
     }
 
-    pub fn callback(
-        self,
-    ) -> impl Fn(fern::FormatCallback<'_>, &std::fmt::Arguments<'_>, &log::Record<'_>)
-    {
-        let use_color = self.colorize.use_color();
-        let now = Time::new();
-        let thread_name =
-            ThreadName::new(use_color && self.color_threads, self.thread_names);
+    /// Show the time of records like this
+    pub fn timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
 
-        move |out, message, record| {
-            let msg = Message::new(use_color, record.level(), message);
-            let level = Level::new(record.level(), use_color);
-
-            out.finish(format_args!(
-                "{}{}{} {}:{}",
-                now,
-                thread_name,
-                level,
-                record.target(),
-                msg,
-            ))
-        }
+    /// Don't print the time of records, e.g. when whatever collects the output adds its
+    /// own. JSON objects keep their time.
+    pub fn hide_time(self) -> Self {
+        self.hide_time_if(true)
     }
-}
 
-// TODO: organize into modules
+    /// Don't print the time of records if `hide`
+    pub fn hide_time_if(mut self, hide: bool) -> Self {
+        self.show_time = !hide;
+        self
+    }
 
-struct Message<'a> {
-    colorize: bool,
-    level: log::Level,
-    message: &'a std::fmt::Arguments<'a>,
-}
+    /// Start the time with the abbreviated weekday, e.g. `Fri 15:04:05.123456`, for
+    /// systems that run for days. Breaks the RFC 3339 format.
+    pub fn weekday(self) -> Self {
+        self.weekday_if(true)
+    }
 
-impl<'a> Message<'a> {
-    fn new(
-        colorize: bool,
-        level: log::Level,
-        message: &'a std::fmt::Arguments<'a>,
-    ) -> Self {
-        Self {
-            colorize,
-            level,
-            message,
+    /// [`weekday`](Self::weekday) if `enable`
+    pub fn weekday_if(mut self, enable: bool) -> Self {
+        self.weekday = enable;
+        self
+    }
+
+    /// Follow the local time with the time in UTC, dimmed if colored, e.g.
+    /// `15:04:05.123456 14:04:05Z`, for comparing logs across time zones
+    pub fn also_utc(self) -> Self {
+        self.also_utc_if(true)
+    }
+
+    /// [`also_utc`](Self::also_utc) if `enable`
+    pub fn also_utc_if(mut self, enable: bool) -> Self {
+        self.also_utc = enable;
+        self
+    }
+
+    /// Show the time in the time zone `offset` instead of the local one. Looking up the
+    /// local offset fails on some targets, such as WebAssembly, and on some platforms
+    /// when there are several threads.
+    #[cfg(feature = "time")]
+    pub fn utc_offset(mut self, offset: time::UtcOffset) -> Self {
+        self.utc_offset = Some(offset);
+        self
+    }
+
+    /// Get the current time from `clock` instead of the system, e.g. from JavaScript on
+    /// WebAssembly targets without a system clock:
+    /// ```
+    /// use fern_format::Format;
+    /// use time::{OffsetDateTime, UtcOffset};
+    ///
+    /// # mod js_sys { pub struct Date; impl Date { pub fn now() -> f64 { 0.0 } } }
+    /// fn now() -> OffsetDateTime {
+    ///     let millis = js_sys::Date::now() as i128;
+    ///     OffsetDateTime::from_unix_timestamp_nanos(millis * 1_000_000).unwrap()
+    /// }
+    ///
+    /// let format = Format::new().utc_offset(UtcOffset::UTC).clock(now);
+    /// ```
+    #[cfg(feature = "time")]
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Makes the output the same on every machine and in every run, so that tests can
+    /// compare it byte for byte, e.g. in snapshots: colors are off, every thread is
+    /// shown as `thread`, the times since the previous records of targets are left out
+    /// and, with the `time` feature, it is always midnight of 1970-01-01 in UTC.
+    /// ```
+    /// use fern_format::{Format, TestSink};
+    ///
+    /// let sink = TestSink::new();
+    /// let (_, logger) = fern::Dispatch::new()
+    ///     .format(Format::new().thread_names().deterministic_for_tests().callback())
+    ///     .chain(sink.clone())
+    ///     .into_log();
+    ///
+    /// logger.log(
+    ///     &log::Record::builder()
+    ///         .args(format_args!("hello"))
+    ///         .level(log::Level::Info)
+    ///         .target("app")
+    ///         .build(),
+    /// );
+    /// # #[cfg(feature = "time")]
+    /// assert_eq!(sink.lines(), ["00:00:00.000000 (thread) [INFO] app: hello"]);
+    /// ```
+    pub fn deterministic_for_tests(mut self) -> Self {
+        self.colorize = Colorize::BlackWhite;
+        self.thread_placeholders = true;
+        self.target_elapsed = false;
+        #[cfg(feature = "time")]
+        {
+            self.utc_offset = Some(time::UtcOffset::UTC);
+            self.clock = || time::OffsetDateTime::UNIX_EPOCH;
         }
+        self
     }
-}
 
-impl<'a> Display for Message<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let style = if self.colorize {
-            level_style(self.level)
+    /// Don't print the targets of records
+    pub fn hide_targets(self) -> Self {
+        self.hide_targets_if(true)
+    }
+
+    /// Don't print the targets of records if `hide`
+    pub fn hide_targets_if(mut self, hide: bool) -> Self {
+        self.targets = !hide;
+        self
+    }
+
+    /// Pad or truncate thread names so that they are exactly `width` columns wide
+    pub fn thread_name_width(mut self, width: usize) -> Self {
+        self.thread_name_width = Some(width);
+        self
+    }
+
+    /// Pad or truncate targets so that they are exactly `width` columns wide
+    pub fn target_width(mut self, width: usize) -> Self {
+        self.target_width = Some(width);
+        self
+    }
+
+    /// Widen the thread name and target columns to the widest value that they have shown
+    /// so far, up to `max` columns, so that the lines line up without fixed widths. Wider
+    /// values are truncated. The fixed widths of
+    /// [`thread_name_width`](Self::thread_name_width) and
+    /// [`target_width`](Self::target_width) take precedence.
+    pub fn auto_width(mut self, max: usize) -> Self {
+        self.auto_width = Some(max);
+        self
+    }
+
+    /// Style the targets of records from `target` and its submodules with `style`, e.g.
+    /// to make a module stand out while debugging it. Only used when colors are enabled.
+    /// ```
+    /// use fern_format::{Format, Style};
+    ///
+    /// let format = Format::new().watch_target("payments", Style::new().on_blue());
+    /// ```
+    pub fn watch_target(mut self, target: impl Into<String>, style: Style) -> Self {
+        self.watched_targets.push((target.into(), style));
+        self
+    }
+
+    /// Dim the records of targets that start with `prefix`, e.g. of chatty dependencies
+    /// such as `hyper` and `h2`, so that they recede behind the records of the
+    /// application. Only used when colors are enabled.
+    pub fn dim_target_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.dimmed_targets.push(prefix.into());
+        self
+    }
+
+    /// Show the time since the previous record of the same target before the target,
+    /// e.g. ` +12ms`, to see which part of a program stalls
+    pub fn target_elapsed(self) -> Self {
+        self.target_elapsed_if(true)
+    }
+
+    /// [`target_elapsed`](Self::target_elapsed) if `enable`
+    pub fn target_elapsed_if(mut self, enable: bool) -> Self {
+        self.target_elapsed = enable;
+        self
+    }
+
+    /// Show how many records the target of each record has logged so far before the
+    /// target, e.g. ` #1234`, to see which part of a program floods the log
+    pub fn target_counts(self) -> Self {
+        self.target_counts_if(true)
+    }
+
+    /// [`target_counts`](Self::target_counts) if `enable`
+    pub fn target_counts_if(mut self, enable: bool) -> Self {
+        self.target_counts = enable;
+        self
+    }
+
+    /// Color each message a bit further along a rainbow than the previous one, instead of
+    /// by its level. It is silly, but it is a party. Only used when colors are enabled.
+    pub fn rainbow(self) -> Self {
+        self.rainbow_if(true)
+    }
+
+    /// [`rainbow`](Self::rainbow) if `enable`
+    pub fn rainbow_if(mut self, enable: bool) -> Self {
+        self.rainbow = enable;
+        self
+    }
+
+    /// Truncate messages that are wider than `width` columns. The message is formatted
+    /// into a temporary string first, so this makes formatting allocate, like nested
+    /// key-values with the `kv-serde` feature do. Besides that, [`NonBlocking`] copies
+    /// each record to send it to its worker, and [`Dedup`] and, with the `hash-chain`
+    /// feature, `HashChain` copy the messages of records.
+    pub fn max_message_width(mut self, width: usize) -> Self {
+        self.message_width = Some(width);
+        self
+    }
+
+    /// Remove ANSI escape sequences from messages, so that logged user input can't move
+    /// the cursor, clear the terminal or pass itself off as another record
+    pub fn strip_escapes(self) -> Self {
+        self.strip_escapes_if(true)
+    }
+
+    /// [`strip_escapes`](Self::strip_escapes) if `enable`
+    pub fn strip_escapes_if(mut self, enable: bool) -> Self {
+        self.strip_escapes = enable;
+        self
+    }
+
+    /// Terminate the lines of multi-line messages with `ending`. Use
+    /// [`get_line_ending`](Self::get_line_ending) to configure the fern output the same
+    /// way.
+    pub fn line_ending(mut self, ending: LineEnding) -> Self {
+        self.line_ending = ending;
+        self
+    }
+
+    /// Start each record with the syslog severity of its level in angle brackets, e.g.
+    /// `<3>` for errors and `<6>` for infos, which systemd reads from the output of
+    /// services to set the priority of their lines in the journal
+    pub fn severity_prefixes(self) -> Self {
+        self.severity_prefixes_if(true)
+    }
+
+    /// [`severity_prefixes`](Self::severity_prefixes) if `enable`
+    pub fn severity_prefixes_if(mut self, enable: bool) -> Self {
+        self.severity_prefixes = enable;
+        self
+    }
+
+    /// End each record with ` crc32=` and the CRC-32 of everything before it in 8 hex
+    /// digits, so that lines that were cut short or garbled, e.g. by a crash while they
+    /// were written, can be found with [`checksum_matches`]. The checksum covers the
+    /// colors and the earlier lines of multi-line messages too. JSON objects have no
+    /// checksum.
+    pub fn checksums(self) -> Self {
+        self.checksums_if(true)
+    }
+
+    /// [`checksums`](Self::checksums) if `enable`
+    pub fn checksums_if(mut self, enable: bool) -> Self {
+        self.checksums = enable;
+        self
+    }
+
+    /// Cut records that would be longer than `bytes` and end them with ` [truncated]`,
+    /// for outputs that can't take longer lines, like UDP syslog or the systemd journal.
+    /// The time, level, target and other columns in front of the message are kept
+    /// whole, even if the record doesn't fit that way. The budget includes the
+    /// [severity prefix](Self::severity_prefixes) and the [checksum](Self::checksums),
+    /// but not the line ending. JSON objects aren't cut.
+    pub fn max_record_bytes(mut self, bytes: usize) -> Self {
+        self.max_record_bytes = Some(bytes);
+        self
+    }
+
+    /// The configured line ending
+    pub fn get_line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// Append key-values as a compact JSON object instead of as `key=value` pairs
+    #[cfg(feature = "kv")]
+    pub fn key_values_as_json(self) -> Self {
+        self.key_values_as_json_if(true)
+    }
+
+    /// Append key-values as JSON if `enable`, and as pairs otherwise
+    #[cfg(feature = "kv")]
+    pub fn key_values_as_json_if(mut self, enable: bool) -> Self {
+        self.kv_format = if enable {
+            KvFormat::Json
         } else {
-            Style::new()
+            KvFormat::Pairs
         };
+        self
+    }
 
-        write!(f, " {}", self.message.style(style))
+    /// Show key-values whose keys end with `_ns`, `_us`, `_ms` or `_secs` as durations
+    /// such as `1.2s` and `340µs` instead of as numbers, e.g. `elapsed_ms=1200` as
+    /// `elapsed_ms=1.2s`. Only used when key-values are rendered as pairs, so JSON keeps
+    /// the numbers.
+    #[cfg(feature = "kv")]
+    pub fn human_durations(self) -> Self {
+        self.human_durations_if(true)
     }
-}
 
-struct Time {
-    offset: UtcOffset,
-}
+    /// [`human_durations`](Self::human_durations) if `enable`
+    #[cfg(feature = "kv")]
+    pub fn human_durations_if(mut self, enable: bool) -> Self {
+        self.human_durations = enable;
+        self
+    }
 
-impl Time {
-    fn new() -> Self {
-        let offset = match UtcOffset::current_local_offset() {
-            Ok(offset) => offset,
-            Err(e) => {
-                eprintln!("Failed to get the current UTC offset: {e:?}");
-                UtcOffset::UTC
-            }
-        };
-        Self { offset }
+    /// Show key-values with the keys `bytes`, `size` or `len`, or that end with them
+    /// after an underscore such as `body_bytes`, as byte sizes, e.g. `size=3565158` as
+    /// `size=3.4MiB`. Only used when key-values are rendered as pairs, so JSON keeps the
+    /// numbers.
+    #[cfg(feature = "kv")]
+    pub fn human_sizes(self) -> Self {
+        self.human_sizes_if(true)
     }
-}
 
-impl Display for Time {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        const DATE_FORMAT: &[time::format_description::FormatItem<'_>] = time::macros::format_description!(
-            "[hour repr:24]:[minute]:[second].[subsecond digits:6]"
-        );
+    /// [`human_sizes`](Self::human_sizes) if `enable`
+    #[cfg(feature = "kv")]
+    pub fn human_sizes_if(mut self, enable: bool) -> Self {
+        self.human_sizes = enable;
+        self
+    }
 
-        let now = OffsetDateTime::now_utc()
-            .to_offset(self.offset)
-            .time()
-            // TODO: figure out how to format this directly into the formatter using
-            // format_into
-            .format(DATE_FORMAT)
-            .unwrap_or_else(|_| "??:??:??.??????".into());
-        write!(f, "{}", now)
+    /// [`human_sizes`](Self::human_sizes) for these keys instead
+    #[cfg(feature = "kv")]
+    pub fn human_size_keys(
+        mut self,
+        keys: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.human_size_keys = keys.into_iter().map(Into::into).collect();
+        self.human_sizes = true;
+        self
     }
-}
 
-struct ThreadName {
-    colorize: bool,
-    print: bool,
-    thread_colors: RwLock<HashMap<ThreadId, Style>>,
-    index: AtomicU8,
-}
+    /// Cut off the values of key-values after `max` characters with an ellipsis, so that
+    /// a huge payload can't blow up the line
+    #[cfg(feature = "kv")]
+    pub fn max_key_value_len(mut self, max: usize) -> Self {
+        self.kv_limits.value = Some(max);
+        self
+    }
 
-impl ThreadName {
-    fn new(colorize: bool, print: bool) -> Self {
-        let thread_colors = RwLock::new(HashMap::<ThreadId, Style>::new());
-        let index = 0.into();
-        Self {
-            colorize,
-            print,
-            thread_colors,
-            index,
-        }
+    /// Leave out the key-values of a record that come after the first `max` bytes of
+    /// them. The cut is marked with ` …` at the end of the line, or with
+    /// `"_truncated":true` in JSON.
+    #[cfg(feature = "kv")]
+    pub fn max_key_values_len(mut self, max: usize) -> Self {
+        self.kv_limits.total = Some(max);
+        self
     }
-}
 
-impl Display for ThreadName {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if !self.print {
-            return Ok(());
-        }
+    /// Color the keys and values of key-values with these styles. The default is to dim
+    /// the keys and leave the values as is. Only used when key-values are rendered as
+    /// pairs and colors are enabled.
+    pub fn key_value_styles(mut self, key: Style, value: Style) -> Self {
+        self.theme = self.theme.key_values(key, value);
+        self
+    }
 
-        let cur = std::thread::current();
-        let thread_style = if self.colorize {
-            let id = cur.id();
-            match {
-                let thread_colors = self.thread_colors.read().unwrap();
-                thread_colors.get(&id).copied()
-            } {
-                Some(style) => style,
-                None => {
-                    let mut thread_colors = self.thread_colors.write().unwrap();
-                    if let Some(style) = thread_colors.get(&id).copied() {
-                        style
-                    } else {
-                        let i = self.index.fetch_add(1, Ordering::SeqCst);
-                        let style = gen_color(i);
-                        thread_colors.insert(id, style);
-                        style
-                    }
-                }
-            }
+    /// Show the time in `style` when colors are enabled, e.g. dimmed so that the messages
+    /// stand out more
+    pub fn style_time(mut self, style: Style) -> Self {
+        self.theme = self.theme.time(style);
+        self
+    }
+
+    /// Show targets in `style` when colors are enabled. [Watched](Self::watch_target) and
+    /// [dimmed](Self::dim_target_prefix) targets keep their own styles.
+    pub fn style_target(mut self, style: Style) -> Self {
+        self.theme = self.theme.target(style);
+        self
+    }
+
+    /// Show the parentheses around thread names and the colon after targets in `style`
+    /// when colors are enabled. They otherwise get the style of what they surround.
+    pub fn style_punctuation(mut self, style: Style) -> Self {
+        self.theme = self.theme.punctuation(style);
+        self
+    }
+
+    /// Use the styles of `theme` when colors are enabled. This replaces the styles set
+    /// before, e.g. with [`style_time`](Self::style_time).
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Only render the key-values with these keys. Replaces any previous
+    /// [`key_value_denylist`](Self::key_value_denylist).
+    ///
+    /// Combine with a second `Format` without the list to e.g. keep bulky key-values out
+    /// of the terminal but still have them in a JSON file.
+    pub fn key_value_allowlist<K: Into<String>>(
+        mut self,
+        keys: impl IntoIterator<Item = K>,
+    ) -> Self {
+        self.kv_filter = KvFilter::Allow(keys.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Render all key-values except the ones with these keys. Replaces any previous
+    /// [`key_value_allowlist`](Self::key_value_allowlist).
+    pub fn key_value_denylist<K: Into<String>>(
+        mut self,
+        keys: impl IntoIterator<Item = K>,
+    ) -> Self {
+        self.kv_filter = KvFilter::Deny(keys.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Add a constant key-value to every record, e.g. the name or version of the
+    /// service. They are rendered together with the key-values of the records.
+    pub fn static_field(mut self, key: impl Into<String>, value: impl Display) -> Self {
+        self.static_fields.push((key.into(), value.to_string()));
+        self
+    }
+
+    /// What to call the [correlation id](context::set_correlation_id) in structured
+    /// output. The default is `correlation_id`.
+    pub fn correlation_id_key(mut self, key: impl Into<String>) -> Self {
+        self.correlation_key = key.into();
+        self
+    }
+
+    /// Call `key` `name` in JSON objects, e.g. `msg` instead of `message`, for collectors
+    /// that expect other names than the defaults of [`cloudwatch`](Self::cloudwatch). The
+    /// correlation id is renamed with [`correlation_id_key`](Self::correlation_id_key).
+    /// ```
+    /// use fern_format::{Format, JsonKey};
+    ///
+    /// let format = Format::cloudwatch()
+    ///     .json_key(JsonKey::Timestamp, "@timestamp")
+    ///     .json_key(JsonKey::Level, "lvl")
+    ///     .json_key(JsonKey::Message, "msg");
+    /// ```
+    pub fn json_key(mut self, key: JsonKey, name: impl Into<String>) -> Self {
+        self.json_keys.set(key, name.into());
+        self
+    }
+
+    /// Put the key-values, static fields and correlation id of JSON objects in an object
+    /// of their own called `key`, e.g. `fields` or `attributes`, so that they can't
+    /// collide with the members of the record itself
+    /// ```
+    /// use fern_format::Format;
+    ///
+    /// // {"timestamp":...,"message":"hello","fields":{"user":"ada"}}
+    /// let format = Format::cloudwatch().nest_json_fields("fields");
+    /// ```
+    pub fn nest_json_fields(mut self, key: impl Into<String>) -> Self {
+        self.json_keys.fields = Some(key.into());
+        self
+    }
+
+    /// Put the key-values of JSON objects next to the message, which is the default
+    pub fn flatten_json_fields(mut self) -> Self {
+        self.json_keys.fields = None;
+        self
+    }
+
+    /// Show the active [`scope`]s by indenting the message two spaces per scope, instead
+    /// of prefixing it with their names
+    pub fn scopes_as_indentation(self) -> Self {
+        self.scopes_as_indentation_if(true)
+    }
+
+    /// Show the active [`scope`]s by indenting if `enable`, and with their names otherwise
+    pub fn scopes_as_indentation_if(mut self, enable: bool) -> Self {
+        self.scope_style = if enable {
+            ScopeStyle::Indent
         } else {
-            Style::new()
+            ScopeStyle::Breadcrumbs
         };
+        self
+    }
 
-        if let Some(name) = cur.name() {
-            write!(f, " {}", format_args!("({})", name).style(thread_style))?;
-        } else {
-            write!(
-                f,
-                " {}",
-                format_args!("({})", threadid_as_u64(cur.id())).style(thread_style)
-            )?;
+    /// The callback to give to [`fern::Dispatch::format`]. It can be cloned cheaply to
+    /// format several dispatch chains the same way, e.g. one to stdout and one to a
+    /// file, and the clones share all state, such as which color each thread has.
+    pub fn callback(
+        self,
+    ) -> impl Fn(fern::FormatCallback<'_>, &std::fmt::Arguments<'_>, &log::Record<'_>) + Clone
+    {
+        let renderer = Arc::new(self.renderer());
+        move |out, message, record| {
+            renderer.render(record, message, |line| out.finish(line));
+        }
+    }
+
+    /// Formats records without going through fern, e.g. to show them in a GUI or to use
+    /// them with other logging frontends
+    ///
+    /// ```
+    /// use fern_format::Format;
+    ///
+    /// let renderer = Format::new().renderer();
+    /// let line = renderer.render_to_string(
+    ///     &log::Record::builder()
+    ///         .args(format_args!("hello"))
+    ///         .level(log::Level::Info)
+    ///         .target("app")
+    ///         .build(),
+    /// );
+    /// assert!(line.ends_with("[INFO] app: hello"));
+    /// ```
+    pub fn renderer(self) -> Renderer {
+        Renderer::new(self)
+    }
+
+    #[cfg(feature = "time")]
+    fn time(&self) -> Time {
+        match self.utc_offset {
+            Some(offset) => Time::with_offset(self.timestamp, offset),
+            None => Time::new(self.timestamp),
         }
-        Ok(())
+        .clock(self.clock)
+    }
+
+    #[cfg(not(feature = "time"))]
+    fn time(&self) -> Time {
+        Time::new(self.timestamp)
     }
 }
 
-struct Level {
-    level: log::Level,
+/// Formats records like [`Format::callback`], see [`Format::renderer`]
+pub struct Renderer {
     use_color: bool,
+    now: Time,
+    show_time: bool,
+    time_style: Option<Style>,
+    tick: Tick,
+    thread_name: ThreadName<'static>,
+    thread_placeholder: Option<&'static str>,
+    thread_palette: Option<Vec<Style>>,
+    thread_auto_width: Option<AutoWidth>,
+    targets: bool,
+    target_width: Option<usize>,
+    target_auto_width: Option<AutoWidth>,
+    target_style: Option<Style>,
+    punctuation_style: Option<Style>,
+    watched_targets: Vec<(String, Style)>,
+    dimmed_targets: Vec<String>,
+    dimmed_style: Style,
+    target_clock: Option<TargetClock>,
+    target_counter: Option<TargetCounter>,
+    rainbow: Option<Rainbow>,
+    basic_colors: bool,
+    level_escapes: LevelEscapes,
+    color_warnings_only: bool,
+    message_width: Option<usize>,
+    strip_escapes: bool,
+    line_ending: LineEnding,
+    severity_prefixes: bool,
+    checksums: bool,
+    max_record_bytes: Option<usize>,
+    /// The style of the elapsed time, tick, count and scopes
+    secondary_style: Option<Style>,
+    correlation_id_style: Option<Style>,
+    #[cfg(feature = "kv")]
+    kv_format: KvFormat,
+    #[cfg(feature = "kv")]
+    human_durations: bool,
+    #[cfg(feature = "kv")]
+    human_sizes: Option<Vec<String>>,
+    #[cfg(feature = "kv")]
+    kv_limits: KvLimits,
+    kv_styles: Option<fields::KvStyles>,
+    kv_filter: KvFilter,
+    /// Sorted by key
+    static_fields: StaticFields,
+    scope_style: ScopeStyle,
+    correlation_key: String,
+    layout: Layout,
+    json_keys: JsonKeys,
 }
 
-impl Level {
-    fn new(level: log::Level, use_color: bool) -> Self {
-        Self { level, use_color }
+impl Renderer {
+    fn new(format: Format) -> Self {
+        Self::with_main_thread(format, components::current_thread_number())
     }
-}
 
-impl Display for Level {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    /// Like [`new`](Self::new), with the thread numbered `main` as the main thread
+    pub(crate) fn with_main_thread(format: Format, main: u64) -> Self {
+        let use_color = format.colorize.use_color();
+        let thread_placeholder = format.thread_placeholders.then_some("thread");
+        let now = format
+            .time()
+            .weekday(format.weekday)
+            .also_utc(format.also_utc)
+            .colorize(use_color)
+            .utc_style(format.theme.secondary_style());
+        let theme = format.theme;
+        let punctuation_style = theme.punctuation.filter(|_| use_color);
+        let secondary_style = use_color.then(|| theme.secondary_style());
+        let mut static_fields = format.static_fields;
+        static_fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Self {
+            use_color,
+            now,
+            show_time: format.show_time,
+            // there is nothing to style without the time
+            time_style: theme
+                .time
+                .filter(|_| use_color && format.show_time && cfg!(feature = "time")),
+            tick: Tick {
+                print: format.ticks,
+                style: secondary_style,
+            },
+            thread_name: ThreadName::new()
+                .colorize(use_color && format.color_threads)
+                .width(format.thread_name_width)
+                .main_thread(format.label_main_thread.then_some(main))
+                .main_style(theme.main_thread)
+                .punctuation_style(punctuation_style)
+                .basic_colors(format.basic_colors)
+                .placeholder(thread_placeholder)
+                .print(format.thread_names),
+            thread_placeholder,
+            thread_palette: theme.thread_palette.clone(),
+            thread_auto_width: (format.thread_name_width.is_none())
+                .then_some(format.auto_width)
+                .flatten()
+                .map(AutoWidth::new),
+            targets: format.targets,
+            target_width: format.target_width,
+            target_auto_width: (format.target_width.is_none())
+                .then_some(format.auto_width)
+                .flatten()
+                .map(AutoWidth::new),
+            target_style: theme.target.filter(|_| use_color),
+            punctuation_style,
+            watched_targets: format.watched_targets,
+            dimmed_targets: format.dimmed_targets,
+            dimmed_style: theme.dimmed_style(),
+            target_clock: format.target_elapsed.then(TargetClock::default),
+            target_counter: format.target_counts.then(TargetCounter::default),
+            rainbow: (use_color && format.rainbow).then(Rainbow::default),
+            basic_colors: format.basic_colors,
+            // formats the escape sequences of the levels before the first record
+            level_escapes: components::level_escapes(|level| {
+                theme.level_style(level, format.basic_colors)
+            }),
+            color_warnings_only: format.color_warnings_only,
+            message_width: format.message_width,
+            strip_escapes: format.strip_escapes,
+            line_ending: format.line_ending,
+            severity_prefixes: format.severity_prefixes,
+            checksums: format.checksums,
+            max_record_bytes: format.max_record_bytes,
+            secondary_style,
+            correlation_id_style: use_color.then(|| theme.correlation_id_style()),
+            #[cfg(feature = "kv")]
+            kv_format: format.kv_format,
+            #[cfg(feature = "kv")]
+            human_durations: format.human_durations,
+            #[cfg(feature = "kv")]
+            human_sizes: format.human_sizes.then_some(format.human_size_keys),
+            #[cfg(feature = "kv")]
+            kv_limits: format.kv_limits,
+            kv_styles: use_color.then_some(theme.key_values),
+            kv_filter: format.kv_filter,
+            static_fields,
+            scope_style: format.scope_style,
+            correlation_key: format.correlation_key,
+            layout: format.layout,
+            json_keys: format.json_keys,
+        }
+    }
+
+    /// The style of the first watched target that `target` is in
+    fn watched_style(&self, target: &str) -> Option<Style> {
         if !self.use_color {
-            write!(f, " [{}]", self.level)?;
+            return None;
         }
+        self.watched_targets
+            .iter()
+            .find(|(watched, _)| in_module(target, watched))
+            .map(|(_, style)| *style)
+    }
 
-        Ok(())
+    /// If the records of `target` are dimmed
+    fn is_dimmed(&self, target: &str) -> bool {
+        self.use_color
+            && (self.dimmed_targets.iter())
+                .any(|prefix| target.starts_with(prefix.as_str()))
+    }
+
+    /// Formats `record` into a line without a line ending
+    pub fn render_to_string(&self, record: &log::Record<'_>) -> String {
+        self.render(record, record.args(), |line| line.to_string())
+            .unwrap_or_default()
+    }
+
+    /// Calls `finish` with `record` formatted with `message` as its message. Records that
+    /// are logged while formatting another one on the same thread are reported as
+    /// `[LEVEL] target: message` instead and give `None`, see [`reentrancy`].
+    fn render<R>(
+        &self,
+        record: &log::Record<'_>,
+        message: &std::fmt::Arguments<'_>,
+        finish: impl FnOnce(std::fmt::Arguments<'_>) -> R,
+    ) -> Option<R> {
+        let Some(_formatting) = Formatting::enter() else {
+            report(format_args!(
+                "[{}] {}: {}",
+                record.level(),
+                record.target(),
+                message
+            ));
+            return None;
+        };
+        let use_color = self.use_color;
+        let dimmed = self.is_dimmed(record.target()).then_some(self.dimmed_style);
+        let msg =
+            Message::new(message, record.level())
+                .colorize(use_color)
+                .basic_colors(self.basic_colors)
+                .level_escapes(&self.level_escapes)
+                .color_warnings_only(self.color_warnings_only)
+                .style(dimmed.or_else(|| {
+                    Some(self.rainbow.as_ref()?.next_style(self.basic_colors))
+                }))
+                .max_width(self.message_width)
+                .strip_escapes(self.strip_escapes)
+                .line_ending(self.line_ending);
+        let level_colored =
+            !self.color_warnings_only || record.level() <= log::Level::Warn;
+        let level = Level::new(record.level()).colorize(use_color && level_colored);
+        let thread_name = match &self.thread_palette {
+            Some(palette) => self.thread_name.palette(palette),
+            None => self.thread_name,
+        };
+        let thread_name = match &self.thread_auto_width {
+            Some(column) => thread_name.auto_width(column),
+            None => thread_name,
+        };
+        let target = Target {
+            target: self.targets.then(|| record.target()),
+            width: self.target_width.or_else(|| match &self.target_auto_width {
+                Some(column) if self.targets => Some(column.widen(record.target())),
+                _ => None,
+            }),
+            style: (self.watched_style(record.target()).or(dimmed)).or(self.target_style),
+            punctuation_style: self.punctuation_style,
+        };
+        let elapsed = Elapsed {
+            elapsed: self
+                .target_clock
+                .as_ref()
+                .and_then(|clock| clock.elapsed(record.target())),
+            style: self.secondary_style,
+        };
+        let count = Count {
+            count: (self.target_counter.as_ref())
+                .map(|counter| counter.count(record.target())),
+            style: self.secondary_style,
+        };
+
+        let time: &dyn Display = if self.show_time { &self.now } else { &"" };
+        let options = LineOptions {
+            severity: self
+                .severity_prefixes
+                .then(|| syslog::severity(record.level())),
+            checksum: self.checksums,
+            max_bytes: self.max_record_bytes,
+        };
+
+        Some(Scopes::with(
+            self.scope_style,
+            self.secondary_style,
+            |scopes| {
+                context::with(|context| {
+                    let fields =
+                        Fields::new(&self.static_fields, context, &self.correlation_key);
+                    if self.layout == Layout::Json {
+                        let json = JsonRecord {
+                            keys: &self.json_keys,
+                            time: &self.now,
+                            thread_name: self.thread_name.prints(),
+                            thread_placeholder: self.thread_placeholder,
+                            target: self.targets,
+                            record,
+                            message,
+                            strip_escapes: self.strip_escapes,
+                            fields,
+                            filter: &self.kv_filter,
+                            #[cfg(feature = "kv")]
+                            limits: self.kv_limits,
+                        };
+                        // cutting JSON objects or adding to them would break them
+                        let options = LineOptions {
+                            checksum: false,
+                            max_bytes: None,
+                            ..options
+                        };
+                        return buffered(
+                            format_args!("{}", json),
+                            format_args!(""),
+                            options,
+                            finish,
+                        );
+                    }
+
+                    let correlation_id = CorrelationId::new(
+                        fields.correlation_id,
+                        self.correlation_id_style,
+                    );
+                    #[cfg(feature = "kv")]
+                    let key_values = kv::KeyValues::new(
+                        record.key_values(),
+                        fields,
+                        &self.kv_filter,
+                        self.kv_format,
+                        self.kv_styles,
+                    )
+                    .human_durations(self.human_durations)
+                    .human_sizes(self.human_sizes.as_deref())
+                    .limits(self.kv_limits);
+                    #[cfg(not(feature = "kv"))]
+                    let key_values =
+                        fields::FieldPairs::new(fields, &self.kv_filter, self.kv_styles);
+
+                    let head = format_args!(
+                        "{}{}{}{}{}{}{}{}",
+                        Styled(time, self.time_style),
+                        self.tick,
+                        thread_name,
+                        level,
+                        correlation_id,
+                        elapsed,
+                        count,
+                        target,
+                    );
+                    let body = format_args!("{}{}{}", scopes, msg, key_values);
+                    buffered(head, body, options, finish)
+                })
+            },
+        ))
     }
 }
 
-fn supports_color(stream: Stream) -> bool {
-    supports_color::on(stream).is_some_and(|support| support.has_basic)
+/// What [`buffered`] does with a line besides assembling it
+#[derive(Clone, Copy)]
+struct LineOptions {
+    /// The syslog severity to start the line with
+    severity: Option<u8>,
+    /// If the line should end with its checksum
+    checksum: bool,
+    /// Cut the line to this many bytes after `head`
+    max_bytes: Option<usize>,
 }
 
-/// Mimics the color style of journald
-fn level_style(level: log::Level) -> Style {
-    match level {
-        log::Level::Error => Style::new().bright_red().bold(),
-        log::Level::Warn => Style::new().bright_yellow().bold(),
-        log::Level::Info => Style::new().bright_white().bold(),
-        log::Level::Debug => Style::new().white(),
-        log::Level::Trace => Style::new().dimmed(),
+/// Calls `finish` with `head` and `body` assembled in a [`LineBuffer`] first, so that
+/// they reach the output in one piece, after the severity prefix if there is one and
+/// before the checksum if there is one. Only `body` is cut to fit the budget.
+fn buffered<R>(
+    head: std::fmt::Arguments<'_>,
+    body: std::fmt::Arguments<'_>,
+    options: LineOptions,
+    finish: impl FnOnce(std::fmt::Arguments<'_>) -> R,
+) -> R {
+    let mut buffer = LineBuffer::new();
+    let written = buffer.write_fmt(head);
+    let head_len = buffer.as_str().len();
+    if written.and_then(|()| buffer.write_fmt(body)).is_err() {
+        // let fern report the error
+        return finish(format_args!("{}{}", head, body));
     }
+    let line = buffer.as_str();
+    // the line starts with the space in front of the level without a time
+    let (line, head_len) = match line.strip_prefix(' ') {
+        Some(line) => (line, head_len.saturating_sub(1)),
+        None => (line, head_len),
+    };
+    let prefix = match options.severity {
+        Some(severity) => syslog::prefix(severity),
+        None => "",
+    };
+    let (line, marker) = match options.max_bytes {
+        Some(max) => {
+            let checksum_len = if options.checksum { checksum::LEN } else { 0 };
+            budget::fit(
+                line,
+                head_len,
+                max.saturating_sub(prefix.len() + checksum_len),
+            )
+        }
+        None => (line, ""),
+    };
+    let checksum = Checksum(options.checksum.then(|| crc32(&[prefix, line, marker])));
+    finish(format_args!("{}{}{}{}", prefix, line, marker, checksum))
+}
+
+/// ` target:`, if targets are shown
+struct Target<'a> {
+    target: Option<&'a str>,
+    width: Option<usize>,
+    style: Option<Style>,
+    punctuation_style: Option<Style>,
 }
 
-// https://github.com/rust-lang/rust/issues/67939
-// TODO: use the stabilzed function when and if it is stabilized
-// TODO: error handling?
-fn threadid_as_u64(id: ThreadId) -> u64 {
-    let string = format!("{:?}", id);
-    let string = string.strip_prefix("ThreadId(").unwrap();
-    let string = string.strip_suffix(")").unwrap();
-    string.parse().unwrap()
+impl<'a> Display for Target<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let colon = Styled(":", self.punctuation_style);
+        match (self.target, self.width) {
+            (Some(target), Some(w)) => {
+                write!(f, " {}{}", Styled(width::fit(target, w), self.style), colon)
+            }
+            (Some(target), None) => write!(f, " {}{}", Styled(target, self.style), colon),
+            (None, _) => Ok(()),
+        }
+    }
 }
 
-fn gen_color(i: u8) -> Style {
-    const BOLD: u8 = 2;
-    const COLOR: u8 = 7;
-    const ITALIC: u8 = 2;
-    let total = BOLD * COLOR * ITALIC;
+/// If `target` is `module` or one of its submodules
+fn in_module(target: &str, module: &str) -> bool {
+    target
+        .strip_prefix(module)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
 
-    let style = Style::new();
+#[cfg(feature = "supports-color")]
+fn supports_color(stream: Stream) -> bool {
+    supports_color::on(stream).is_some_and(|support| support.has_basic)
+}
 
-    let i = i % total;
-    let total = total / ITALIC;
-    let style = match i / total {
-        0 => style,
-        1 => style.italic(),
-        _ => unreachable!(),
-    };
+/// The stream to detect color support of
+#[cfg(not(feature = "supports-color"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
 
-    let i = i % total;
-    let total = total / BOLD;
-    let style = match i / total {
-        0 => style,
-        1 => style.bold(),
-        _ => unreachable!(),
-    };
+#[cfg(not(feature = "supports-color"))]
+fn supports_color(stream: Stream) -> bool {
+    use std::io::IsTerminal;
 
-    let i = i % total;
-    let total = total / COLOR;
-    let style = match i / total {
-        0 => style.bright_white(),
-        1 => style.bright_blue(),
-        2 => style.bright_yellow(),
-        3 => style.bright_cyan(),
-        4 => style.bright_purple(),
-        5 => style.bright_green(),
-        6 => style.bright_red(),
-        _ => unreachable!(),
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let terminal = match stream {
+        Stream::Stdout => std::io::stdout().is_terminal(),
+        Stream::Stderr => std::io::stderr().is_terminal(),
     };
-
-    style
+    !no_color && terminal
 }
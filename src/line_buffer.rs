@@ -0,0 +1,49 @@
+use std::fmt::Write;
+
+/// How many bytes of a line are assembled on the stack before moving it to the heap
+const INLINE: usize = 256;
+
+/// Collects the pieces of a line so that fern gets the whole line at once, which it then
+/// writes with a single call instead of one per piece. Other threads can't squeeze their
+/// output in between the pieces that way.
+pub(crate) struct LineBuffer {
+    inline: [u8; INLINE],
+    len: usize,
+    /// The whole line once it doesn't fit in `inline`
+    spilled: Option<String>,
+}
+
+impl LineBuffer {
+    pub(crate) fn new() -> Self {
+        Self {
+            inline: [0; INLINE],
+            len: 0,
+            spilled: None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> &str {
+        match &self.spilled {
+            Some(line) => line,
+            // only whole `str`s are copied into it
+            None => std::str::from_utf8(&self.inline[..self.len]).unwrap_or_default(),
+        }
+    }
+}
+
+impl Write for LineBuffer {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        if let Some(line) = &mut self.spilled {
+            line.push_str(s);
+        } else if let Some(free) = self.inline.get_mut(self.len..self.len + s.len()) {
+            free.copy_from_slice(s.as_bytes());
+            self.len += s.len();
+        } else {
+            let mut line = String::with_capacity(2 * INLINE + s.len());
+            line.push_str(self.as_str());
+            line.push_str(s);
+            self.spilled = Some(line);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt::Write;
+
+/// How lines are terminated, both between records and inside multi-line messages.
+///
+/// The separator between records is written by fern, so pass [`LineEnding::as_str`] to
+/// it when creating the output:
+/// ```no_run
+/// use fern_format::{Format, LineEnding};
+///
+/// let format = Format::new().line_ending(LineEnding::CrLf);
+/// fern::Dispatch::new()
+///     .chain(fern::Output::stdout(format.get_line_ending().as_str()))
+///     .format(format.callback())
+///     .apply()
+///     .unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LineEnding {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`, for tools that expect Windows-native files
+    CrLf,
+    /// `\0` between records, with the line breaks and NULs in messages escaped as `\n`
+    /// and `\0`, so that each record is one item for `xargs -0` or `read -d ''`
+    Nul,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Nul => "\0",
+        }
+    }
+}
+
+/// Escapes `\n` and `\0` as `\\n` and `\\0`, so that NULs only ever separate records
+pub(crate) struct NulEscaper<W>(pub(crate) W);
+
+impl<W: Write> Write for NulEscaper<W> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let mut rest = s;
+        while let Some(i) = rest.find(['\n', '\0']) {
+            self.0.write_str(&rest[..i])?;
+            self.0.write_str(match rest.as_bytes()[i] {
+                b'\n' => "\\n",
+                _ => "\\0",
+            })?;
+            rest = &rest[i + 1..];
+        }
+        self.0.write_str(rest)
+    }
+}
+
+/// Translates every `\n` that isn't already preceded by `\r` into `\r\n`
+pub(crate) struct CrLfWriter<W> {
+    inner: W,
+    prev_cr: bool,
+}
+
+impl<W: Write> CrLfWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            prev_cr: false,
+        }
+    }
+}
+
+impl<W: Write> Write for CrLfWriter<W> {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let mut rest = s;
+        while let Some(i) = rest.find('\n') {
+            let (line, tail) = rest.split_at(i);
+            self.inner.write_str(line)?;
+            let prev_cr = if line.is_empty() {
+                self.prev_cr
+            } else {
+                line.ends_with('\r')
+            };
+            if !prev_cr {
+                self.inner.write_char('\r')?;
+            }
+            self.inner.write_char('\n')?;
+            self.prev_cr = false;
+            rest = &tail[1..];
+        }
+
+        if !rest.is_empty() {
+            self.prev_cr = rest.ends_with('\r');
+            self.inner.write_str(rest)?;
+        }
+        Ok(())
+    }
+}
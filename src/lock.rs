@@ -0,0 +1,84 @@
+//! The locks around the state that is shared while formatting, from parking_lot with
+//! the `parking_lot` feature. A panic while a lock is held doesn't poison it either way,
+//! so that logging keeps working afterwards.
+
+#[cfg(feature = "parking_lot")]
+use std::panic::{RefUnwindSafe, UnwindSafe};
+
+#[cfg(feature = "parking_lot")]
+pub(crate) use parking_lot::{MutexGuard, RwLockReadGuard, RwLockWriteGuard};
+#[cfg(not(feature = "parking_lot"))]
+pub(crate) use std::sync::{
+    Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard,
+};
+
+/// A parking_lot mutex that is unwind safe like the one of std. Neither is poisoned for
+/// [`lock`], so both hand out whatever state a panic left behind.
+#[cfg(feature = "parking_lot")]
+#[derive(Default)]
+pub(crate) struct Mutex<T: ?Sized>(parking_lot::Mutex<T>);
+
+#[cfg(feature = "parking_lot")]
+impl<T> Mutex<T> {
+    pub(crate) const fn new(value: T) -> Self {
+        Self(parking_lot::Mutex::new(value))
+    }
+}
+
+#[cfg(feature = "parking_lot")]
+impl<T: ?Sized> UnwindSafe for Mutex<T> {}
+#[cfg(feature = "parking_lot")]
+impl<T: ?Sized> RefUnwindSafe for Mutex<T> {}
+
+/// A parking_lot read-write lock that is unwind safe like the one of std, see [`Mutex`]
+#[cfg(feature = "parking_lot")]
+#[derive(Default)]
+pub(crate) struct RwLock<T: ?Sized>(parking_lot::RwLock<T>);
+
+#[cfg(feature = "parking_lot")]
+impl<T> RwLock<T> {
+    pub(crate) const fn new(value: T) -> Self {
+        Self(parking_lot::RwLock::new(value))
+    }
+}
+
+#[cfg(feature = "parking_lot")]
+impl<T: ?Sized> UnwindSafe for RwLock<T> {}
+#[cfg(feature = "parking_lot")]
+impl<T: ?Sized> RefUnwindSafe for RwLock<T> {}
+
+pub(crate) fn lock<T: ?Sized>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    #[cfg(feature = "parking_lot")]
+    return mutex.0.lock();
+    #[cfg(not(feature = "parking_lot"))]
+    return mutex.lock().unwrap_or_else(|e| e.into_inner());
+}
+
+pub(crate) fn read<T: ?Sized>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    #[cfg(feature = "parking_lot")]
+    return lock.0.read();
+    #[cfg(not(feature = "parking_lot"))]
+    return lock.read().unwrap_or_else(|e| e.into_inner());
+}
+
+pub(crate) fn write<T: ?Sized>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    #[cfg(feature = "parking_lot")]
+    return lock.0.write();
+    #[cfg(not(feature = "parking_lot"))]
+    return lock.write().unwrap_or_else(|e| e.into_inner());
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::RefUnwindSafe;
+
+    fn assert_ref_unwind_safe<T: RefUnwindSafe>() {}
+
+    #[test]
+    fn keeps_renderers_unwind_safe() {
+        assert_ref_unwind_safe::<crate::Renderer>();
+        assert_ref_unwind_safe::<crate::Handle>();
+        #[cfg(feature = "slog")]
+        assert_ref_unwind_safe::<crate::SlogDrain>();
+    }
+}
@@ -0,0 +1,48 @@
+use log4rs::encode::{Encode, Write};
+
+use crate::{Format, Renderer};
+
+/// A log4rs [`Encode`]r that formats records like [`Format::callback`], so that log4rs
+/// appenders can be used without switching to fern. Give it to an appender, e.g. with
+/// `ConsoleAppender::builder().encoder(Box::new(encoder))`:
+/// ```
+/// use fern_format::{Format, Log4rsEncoder};
+/// use log4rs::encode::{writer::simple::SimpleWriter, Encode};
+///
+/// let encoder = Log4rsEncoder::new(Format::new().thread_names());
+/// let mut line = SimpleWriter(Vec::new());
+/// let record = log::Record::builder().args(format_args!("hello")).build();
+/// encoder.encode(&mut line, &record)?;
+/// assert!(String::from_utf8(line.0)?.contains("hello"));
+/// # Ok::<(), anyhow::Error>(())
+/// ```
+pub struct Log4rsEncoder {
+    renderer: Renderer,
+    line_ending: &'static str,
+}
+
+impl Log4rsEncoder {
+    pub fn new(format: Format) -> Self {
+        Self {
+            line_ending: format.get_line_ending().as_str(),
+            renderer: format.renderer(),
+        }
+    }
+}
+
+impl std::fmt::Debug for Log4rsEncoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Log4rsEncoder").finish_non_exhaustive()
+    }
+}
+
+impl Encode for Log4rsEncoder {
+    fn encode(&self, w: &mut dyn Write, record: &log::Record<'_>) -> anyhow::Result<()> {
+        self.renderer
+            .render(record, record.args(), |line| {
+                write!(w, "{}{}", line, self.line_ending)
+            })
+            .unwrap_or(Ok(()))?;
+        Ok(())
+    }
+}
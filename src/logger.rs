@@ -0,0 +1,78 @@
+use std::io::Write;
+
+use log::LevelFilter;
+
+use crate::{
+    lock::{lock, Mutex},
+    report::report,
+    Format, Renderer,
+};
+
+/// A [`log::Log`] that writes records formatted like [`Format::callback`] to a writer,
+/// for small programs that don't need the filters and outputs of fern:
+/// ```no_run
+/// use fern_format::{Format, Logger, Stream};
+///
+/// Logger::new(Format::new().color_if_supported(Stream::Stderr), std::io::stderr())
+///     .level(log::LevelFilter::Info)
+///     .init()
+///     .unwrap();
+/// log::info!("hello");
+/// ```
+pub struct Logger {
+    renderer: Renderer,
+    writer: Mutex<Box<dyn Write + Send>>,
+    line_ending: &'static str,
+    level: LevelFilter,
+}
+
+impl Logger {
+    /// Logs every record to `writer`
+    pub fn new(format: Format, writer: impl Write + Send + 'static) -> Self {
+        Self {
+            line_ending: format.get_line_ending().as_str(),
+            renderer: format.renderer(),
+            writer: Mutex::new(Box::new(writer)),
+            level: LevelFilter::Trace,
+        }
+    }
+
+    /// Only log records at `level` and above
+    pub fn level(mut self, level: LevelFilter) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Makes this the logger of the `log` crate, with its level as the max level
+    pub fn init(self) -> Result<(), log::SetLoggerError> {
+        let level = self.level;
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(level);
+        Ok(())
+    }
+}
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // locked only once the record is formatted, see `reentrancy`
+        let result = self.renderer.render(record, record.args(), |line| {
+            let mut writer = lock(&self.writer);
+            write!(writer, "{}{}", line, self.line_ending)?;
+            writer.flush()
+        });
+        if let Some(Err(e)) = result {
+            report(format_args!("Failed to write a record: {e}"));
+        }
+    }
+
+    fn flush(&self) {
+        let _ = lock(&self.writer).flush();
+    }
+}
@@ -0,0 +1,142 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{json, non_blocking::with_origin, report::report};
+
+/// Writes each record as a request body for the push API of Grafana Loki, one JSON object
+/// per line. The low-cardinality parts of the record become labels of the stream, so
+/// that they can be queried without parsing the lines:
+/// - `level`, the level in lower case
+/// - `target`, the first [`target_depth`](Self::target_depth) segments of the target
+/// - the constant [`label`](Self::label)s, e.g. the name of the application
+///
+/// The formatted line is the log line of the entry, so the level and target can be left
+/// out of the format. Post the lines to `/loki/api/v1/push` with the content type
+/// `application/json`, or write them to a file for a collector that forwards push
+/// requests.
+/// ```no_run
+/// use fern_format::{Format, Loki};
+///
+/// fern::Dispatch::new()
+///     .format(Format::new().hide_targets().callback())
+///     .chain(Loki::new(std::io::stdout()).label("app", "shop"))
+///     .apply()
+///     .unwrap();
+/// log::info!("order placed");
+/// // {"streams":[{"stream":{"app":"shop","level":"info","target":"shop"},"values":[["1700000000123456789","..."]]}]}
+/// ```
+pub struct Loki {
+    writer: Mutex<Box<dyn Write + Send>>,
+    labels: Vec<(String, String)>,
+    target_depth: usize,
+}
+
+impl Loki {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+            labels: Vec::new(),
+            target_depth: 1,
+        }
+    }
+
+    /// Add a constant label to every stream. Don't use `level` or `target` as keys.
+    pub fn label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.labels.push((key.into(), value.into()));
+        self
+    }
+
+    /// Only use this many `::` separated segments of the target as the `target` label,
+    /// since every distinct label value makes a new stream. Defaults to 1, i.e. the crate,
+    /// and 0 leaves the label out.
+    pub fn target_depth(mut self, depth: usize) -> Self {
+        self.target_depth = depth;
+        self
+    }
+
+    fn body(&self, record: &log::Record<'_>) -> Result<String, std::fmt::Error> {
+        let time =
+            with_origin(|origin| origin.map(|o| o.time)).unwrap_or_else(SystemTime::now);
+        let nanos = time
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+
+        let mut body = String::with_capacity(256);
+        body.write_str("{\"streams\":[{\"stream\":{")?;
+        for (key, value) in &self.labels {
+            json::write_str(&mut body, key)?;
+            body.write_char(':')?;
+            json::write_str(&mut body, value)?;
+            body.write_char(',')?;
+        }
+        body.write_str("\"level\":")?;
+        json::write_str(&mut body, level(record.level()))?;
+        if self.target_depth > 0 {
+            body.write_str(",\"target\":")?;
+            json::write_str(
+                &mut body,
+                target_prefix(record.target(), self.target_depth),
+            )?;
+        }
+        write!(body, "}},\"values\":[[\"{}\",", nanos)?;
+        json::write_display(&mut body, record.args())?;
+        body.write_str("]]}]}\n")?;
+        Ok(body)
+    }
+}
+
+impl log::Log for Loki {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let Ok(body) = self.body(record) else {
+            return;
+        };
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let result: io::Result<()> = writer
+            .write_all(body.as_bytes())
+            .and_then(|()| writer.flush());
+        if let Err(e) = result {
+            report(format_args!("Failed to write a record for Loki: {e}"));
+        }
+    }
+
+    fn flush(&self) {
+        let _ = self
+            .writer
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .flush();
+    }
+}
+
+impl From<Loki> for fern::Output {
+    fn from(loki: Loki) -> Self {
+        fern::Output::from(Box::new(loki) as Box<dyn log::Log>)
+    }
+}
+
+/// The first `depth` segments of `target`
+fn target_prefix(target: &str, depth: usize) -> &str {
+    match target.match_indices("::").nth(depth - 1) {
+        Some((end, _)) => &target[..end],
+        None => target,
+    }
+}
+
+fn level(level: log::Level) -> &'static str {
+    match level {
+        log::Level::Error => "error",
+        log::Level::Warn => "warn",
+        log::Level::Info => "info",
+        log::Level::Debug => "debug",
+        log::Level::Trace => "trace",
+    }
+}
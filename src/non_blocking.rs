@@ -0,0 +1,340 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    io::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, SyncSender, TrySendError},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::SystemTime,
+};
+
+#[cfg(feature = "kv")]
+use crate::kv::OwnedValue;
+#[cfg(feature = "time")]
+use crate::Clock;
+use crate::{components, context, scope, thread_label, Format, Renderer};
+
+/// What to do with records that are logged while the queue is full
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Overflow {
+    /// Wait until the worker has caught up
+    #[default]
+    Block,
+    /// Drop the record. The worker logs how many records were dropped once there is
+    /// room again.
+    Drop,
+}
+
+/// Formats and writes records on a background thread, so that logging only costs
+/// copying the record onto a queue:
+/// ```no_run
+/// use fern_format::{Format, NonBlocking, Overflow};
+///
+/// let (logger, _guard) = NonBlocking::new(Format::new(), std::io::stdout())
+///     .overflow(Overflow::Drop)
+///     .start();
+/// fern::Dispatch::new()
+///     .level(log::LevelFilter::Info)
+///     .chain(Box::new(logger) as Box<dyn log::Log>)
+///     .apply()
+///     .unwrap();
+/// // the queue is written out when `_guard` is dropped at the end of main
+/// ```
+///
+/// The records are formatted as if they were on the thread that logged them, with its
+/// name, time, [`context`](crate::context) and [`scope`](crate::scope)s. The time is
+/// taken when the record is logged, from the [`clock`](Format::clock) of the format.
+pub struct NonBlocking {
+    format: Format,
+    writer: Box<dyn Write + Send>,
+    capacity: usize,
+    overflow: Overflow,
+}
+
+impl NonBlocking {
+    pub fn new(format: Format, writer: impl Write + Send + 'static) -> Self {
+        Self {
+            format,
+            writer: Box::new(writer),
+            capacity: 1024,
+            overflow: Overflow::Block,
+        }
+    }
+
+    /// Queue at most this many records. Defaults to 1024.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// What to do when the queue is full. Defaults to [`Overflow::Block`].
+    pub fn overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    /// Starts the worker thread. Keep the guard alive for as long as records are logged;
+    /// dropping it writes what is left in the queue and stops the worker.
+    pub fn start(self) -> (NonBlockingLog, WorkerGuard) {
+        let (sender, receiver) = mpsc::sync_channel(self.capacity);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let line_ending = self.format.get_line_ending().as_str();
+        #[cfg(feature = "time")]
+        let clock = self.format.clock;
+        let worker = Worker {
+            renderer: self.format.renderer(),
+            writer: self.writer,
+            line_ending,
+            dropped: Arc::clone(&dropped),
+        };
+        let handle = std::thread::Builder::new()
+            .name("fern-format worker".into())
+            .spawn(move || worker.run(receiver))
+            .expect("failed to spawn the logging thread");
+
+        let logger = NonBlockingLog {
+            #[cfg(feature = "time")]
+            clock,
+            sender: sender.clone(),
+            overflow: self.overflow,
+            dropped,
+        };
+        let guard = WorkerGuard {
+            sender,
+            handle: Some(handle),
+        };
+        (logger, guard)
+    }
+}
+
+/// The [`log::Log`] of [`NonBlocking`]
+pub struct NonBlockingLog {
+    #[cfg(feature = "time")]
+    clock: Clock,
+    sender: SyncSender<Job>,
+    overflow: Overflow,
+    dropped: Arc<AtomicU64>,
+}
+
+impl log::Log for NonBlockingLog {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        #[cfg(feature = "time")]
+        let time = SystemTime::from((self.clock)());
+        #[cfg(not(feature = "time"))]
+        let time = SystemTime::now();
+        let job = Job::Record(Box::new(OwnedRecord::new(record, time)));
+        match self.overflow {
+            Overflow::Block => {
+                let _ = self.sender.send(job);
+            }
+            Overflow::Drop => {
+                if let Err(TrySendError::Full(_)) = self.sender.try_send(job) {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Waits until everything logged so far is written
+    fn flush(&self) {
+        let (done, wait) = mpsc::sync_channel(1);
+        if self.sender.send(Job::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+}
+
+/// Writes the queued records and stops the worker of [`NonBlocking`] when dropped
+#[must_use = "the worker is stopped when the guard is dropped"]
+pub struct WorkerGuard {
+    sender: SyncSender<Job>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for WorkerGuard {
+    fn drop(&mut self) {
+        let _ = self.sender.send(Job::Shutdown);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+enum Job {
+    Record(Box<OwnedRecord>),
+    Flush(SyncSender<()>),
+    Shutdown,
+}
+
+struct Worker {
+    renderer: Renderer,
+    writer: Box<dyn Write + Send>,
+    line_ending: &'static str,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Worker {
+    fn run(mut self, receiver: Receiver<Job>) {
+        while let Ok(job) = receiver.recv() {
+            if !self.handle(job) {
+                return self.drain(receiver);
+            }
+            // batch the writes of records that were queued at the same time
+            while let Ok(job) = receiver.try_recv() {
+                if !self.handle(job) {
+                    return self.drain(receiver);
+                }
+            }
+            let _ = self.writer.flush();
+        }
+    }
+
+    /// Returns false on shutdown
+    fn handle(&mut self, job: Job) -> bool {
+        match job {
+            Job::Record(record) => {
+                self.write(record);
+                let dropped = self.dropped.swap(0, Ordering::Relaxed);
+                if dropped > 0 {
+                    self.write_dropped(dropped);
+                }
+            }
+            Job::Flush(done) => {
+                let _ = self.writer.flush();
+                let _ = done.send(());
+            }
+            Job::Shutdown => return false,
+        }
+        true
+    }
+
+    /// Writes what was logged before the shutdown
+    fn drain(mut self, receiver: Receiver<Job>) {
+        while let Ok(job) = receiver.try_recv() {
+            self.handle(job);
+        }
+        let _ = self.writer.flush();
+    }
+
+    fn write(&mut self, record: Box<OwnedRecord>) {
+        let OwnedRecord {
+            level,
+            target,
+            module_path,
+            file,
+            line,
+            message,
+            #[cfg(feature = "kv")]
+            key_values,
+            origin,
+        } = *record;
+
+        ORIGIN.set(Some(origin));
+        let mut builder = log::Record::builder();
+        builder
+            .level(level)
+            .target(&target)
+            .module_path(module_path.as_deref())
+            .file(file.as_deref())
+            .line(line);
+        #[cfg(feature = "kv")]
+        builder.key_values(&key_values);
+        self.render(&builder.args(format_args!("{}", message)).build());
+        ORIGIN.set(None);
+    }
+
+    fn write_dropped(&mut self, dropped: u64) {
+        let mut builder = log::Record::builder();
+        builder.level(log::Level::Warn).target("fern_format");
+        self.render(
+            &builder
+                .args(format_args!(
+                    "dropped {} records because the queue was full",
+                    dropped
+                ))
+                .build(),
+        );
+    }
+
+    fn render(&mut self, record: &log::Record<'_>) {
+        let (writer, line_ending) = (&mut self.writer, self.line_ending);
+        let _ = self.renderer.render(record, record.args(), |line| {
+            write!(writer, "{}{}", line, line_ending)
+        });
+    }
+}
+
+/// A copy of a record that can be sent to the worker
+struct OwnedRecord {
+    level: log::Level,
+    target: String,
+    module_path: Option<String>,
+    file: Option<String>,
+    line: Option<u32>,
+    message: String,
+    #[cfg(feature = "kv")]
+    key_values: Vec<(String, OwnedValue)>,
+    origin: Origin,
+}
+
+impl OwnedRecord {
+    fn new(record: &log::Record<'_>, time: SystemTime) -> Self {
+        Self {
+            level: record.level(),
+            target: record.target().to_string(),
+            module_path: record.module_path().map(str::to_string),
+            file: record.file().map(str::to_string),
+            line: record.line(),
+            message: record.args().to_string(),
+            #[cfg(feature = "kv")]
+            key_values: OwnedValue::collect(record.key_values()),
+            origin: Origin::capture(time),
+        }
+    }
+}
+
+/// Everything about the thread that logged a record that ends up in the line
+pub(crate) struct Origin {
+    pub(crate) time: SystemTime,
+    pub(crate) thread_name: Option<String>,
+    pub(crate) thread_number: String,
+    pub(crate) thread_style: components::ThreadStyle,
+    pub(crate) context: Vec<(String, String)>,
+    pub(crate) scopes: Vec<Cow<'static, str>>,
+    pub(crate) tick: u64,
+}
+
+impl Origin {
+    fn capture(time: SystemTime) -> Self {
+        Self {
+            time,
+            thread_name: thread_label::with_name(|name| name.map(str::to_string)),
+            thread_number: components::with_thread_number(str::to_string),
+            thread_style: components::thread_style(),
+            context: context::with(<[_]>::to_vec),
+            scopes: scope::active(),
+            tick: crate::tick(),
+        }
+    }
+}
+
+thread_local! {
+    /// The origin of the record that the worker is formatting on this thread
+    static ORIGIN: RefCell<Option<Origin>> = const { RefCell::new(None) };
+}
+
+/// Calls `f` with the origin of the record being formatted on this thread, if it was
+/// logged from another thread
+pub(crate) fn with_origin<R>(f: impl FnOnce(Option<&Origin>) -> R) -> R {
+    ORIGIN.with(|origin| match origin.try_borrow() {
+        Ok(origin) => f(origin.as_ref()),
+        Err(_) => f(None),
+    })
+}
@@ -0,0 +1,220 @@
+//! Parsing of the option strings of [`Format::from_env`]
+
+use crate::{report::report, Colorize, Format, Layout, ScopeStyle, Stream, Timestamp};
+
+/// Applies the comma separated `key=value` options in `options` on top of `format`.
+/// Invalid options are reported and skipped.
+pub(crate) fn apply(mut format: Format, options: &str) -> Format {
+    for option in options.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+        let (key, value) = match option.split_once('=') {
+            Some((key, value)) => (key.trim(), Some(value.trim())),
+            None => (option, None),
+        };
+        if let Err(e) = apply_one(&mut format, key, value) {
+            report(format_args!(
+                "Ignoring the log style option {option:?}: {e}"
+            ));
+        }
+    }
+    format
+}
+
+/// Leaves `format` as is on errors
+fn apply_one(format: &mut Format, key: &str, value: Option<&str>) -> Result<(), String> {
+    match key {
+        "color" => {
+            format.colorize = match value.unwrap_or("always") {
+                "auto" => Colorize::ColorIf(Stream::Stdout),
+                "always" => Colorize::Color,
+                "never" => Colorize::BlackWhite,
+                "env" => Colorize::FromEnv(Stream::Stdout),
+                other => return Err(expected("auto, always, never or env", other)),
+            }
+        }
+        "threads" => format.thread_names = switch(value)?,
+        "thread-colors" => {
+            format.color_threads = switch(value)?;
+            format.thread_names |= format.color_threads;
+        }
+        "thread-width" => format.thread_name_width = width(value)?,
+        "label-main-thread" => {
+            format.label_main_thread = switch(value)?;
+            format.thread_names |= format.label_main_thread;
+        }
+        "time" => {
+            format.timestamp = match value.unwrap_or_default() {
+                "time" => Timestamp::Time,
+                "datetime" => Timestamp::DateTime,
+                "rfc3339" => Timestamp::Rfc3339,
+                other => return Err(expected("time, datetime or rfc3339", other)),
+            }
+        }
+        "hide-time" => format.show_time = !switch(value)?,
+        "weekday" => format.weekday = switch(value)?,
+        "also-utc" => format.also_utc = switch(value)?,
+        "target" => format.targets = switch(value)?,
+        "target-width" => format.target_width = width(value)?,
+        "auto-width" => format.auto_width = width(value)?,
+        "ticks" => format.ticks = switch(value)?,
+        "target-elapsed" => format.target_elapsed = switch(value)?,
+        "target-counts" => format.target_counts = switch(value)?,
+        "dim" => match value {
+            Some(prefix) if !prefix.is_empty() => {
+                format.dimmed_targets.push(prefix.to_string())
+            }
+            _ => return Err("expected the prefix of the targets to dim".into()),
+        },
+        "rainbow" => format.rainbow = switch(value)?,
+        "basic-colors" => format.basic_colors = switch(value)?,
+        "color-warnings-only" => format.color_warnings_only = switch(value)?,
+        "message-width" => format.message_width = width(value)?,
+        "strip-escapes" => format.strip_escapes = switch(value)?,
+        "severity-prefixes" => format.severity_prefixes = switch(value)?,
+        "checksums" => format.checksums = switch(value)?,
+        "max-record-bytes" => format.max_record_bytes = bytes(value)?,
+        "layout" => {
+            format.layout = match value.unwrap_or_default() {
+                "line" => Layout::Line,
+                "json" => Layout::Json,
+                other => return Err(expected("line or json", other)),
+            }
+        }
+        #[cfg(feature = "kv")]
+        "kv" => {
+            format.kv_format = match value.unwrap_or_default() {
+                "pairs" => crate::KvFormat::Pairs,
+                "json" => crate::KvFormat::Json,
+                other => return Err(expected("pairs or json", other)),
+            }
+        }
+        #[cfg(feature = "kv")]
+        "human-durations" => format.human_durations = switch(value)?,
+        #[cfg(feature = "kv")]
+        "human-sizes" => format.human_sizes = switch(value)?,
+        #[cfg(feature = "kv")]
+        "kv-value-len" => format.kv_limits.value = width(value)?,
+        #[cfg(feature = "kv")]
+        "kv-len" => format.kv_limits.total = width(value)?,
+        "scopes" => {
+            format.scope_style = match value.unwrap_or_default() {
+                "breadcrumbs" => ScopeStyle::Breadcrumbs,
+                "indent" => ScopeStyle::Indent,
+                other => return Err(expected("breadcrumbs or indent", other)),
+            }
+        }
+        _ => return Err("unknown option".into()),
+    }
+    Ok(())
+}
+
+/// A flag that is on when given without a value
+fn switch(value: Option<&str>) -> Result<bool, String> {
+    match value {
+        None | Some("on") => Ok(true),
+        Some("off") => Ok(false),
+        Some(other) => Err(expected("on or off", other)),
+    }
+}
+
+/// A number of columns, or `off` for no limit
+fn width(value: Option<&str>) -> Result<Option<usize>, String> {
+    match value {
+        Some("off") => Ok(None),
+        Some(n) => n
+            .parse()
+            .map(Some)
+            .map_err(|_| expected("a number of columns or off", n)),
+        None => Err("expected a number of columns".into()),
+    }
+}
+
+/// A number of bytes, or `off` for no limit
+fn bytes(value: Option<&str>) -> Result<Option<usize>, String> {
+    match value {
+        Some("off") => Ok(None),
+        Some(n) => n
+            .parse()
+            .map(Some)
+            .map_err(|_| expected("a number of bytes or off", n)),
+        None => Err("expected a number of bytes".into()),
+    }
+}
+
+fn expected(what: &str, got: &str) -> String {
+    format!("expected {what}, got {got:?}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_options() {
+        let format = apply(
+            Format::new(),
+            "threads, time=rfc3339,target=off,target-width=20,message-width=off",
+        );
+        assert!(format.thread_names);
+        assert_eq!(format.timestamp, Timestamp::Rfc3339);
+        assert!(!format.targets);
+        assert_eq!(format.target_width, Some(20));
+        assert_eq!(format.message_width, None);
+    }
+
+    #[test]
+    fn skips_invalid_options() {
+        let format = apply(
+            Format::new(),
+            "time=never,unknown,target-width=wide,threads",
+        );
+        assert_eq!(format.timestamp, Timestamp::Time);
+        assert_eq!(format.target_width, None);
+        assert!(format.thread_names);
+    }
+
+    #[test]
+    fn applies_the_layout() {
+        let format = apply(Format::new(), "layout=json");
+        assert_eq!(format.layout, Layout::Json);
+        assert_eq!(apply(format, "layout=line").layout, Layout::Line);
+    }
+
+    #[test]
+    fn applies_each_dimmed_target() {
+        let format = apply(Format::new(), "dim=hyper,dim,dim=h2");
+        assert_eq!(format.dimmed_targets, ["hyper", "h2"]);
+    }
+
+    #[test]
+    fn applies_the_main_thread_label() {
+        let format = apply(Format::new(), "label-main-thread");
+        assert!(format.label_main_thread && format.thread_names);
+    }
+
+    #[test]
+    fn applies_the_options_of_auto() {
+        let format = apply(Format::new(), "hide-time,severity-prefixes");
+        assert!(!format.show_time);
+        assert!(format.severity_prefixes);
+
+        let format = apply(format, "hide-time=off,severity-prefixes=off");
+        assert!(format.show_time);
+        assert!(!format.severity_prefixes);
+    }
+
+    #[test]
+    fn applies_checksums() {
+        let format = apply(Format::new(), "checksums");
+        assert!(format.checksums);
+        assert!(!apply(format, "checksums=off").checksums);
+    }
+
+    #[test]
+    fn applies_the_record_budget() {
+        let format = apply(Format::new(), "max-record-bytes=512");
+        assert_eq!(format.max_record_bytes, Some(512));
+        let format = apply(format, "max-record-bytes=lots");
+        assert_eq!(format.max_record_bytes, Some(512));
+        assert_eq!(apply(format, "max-record-bytes=off").max_record_bytes, None);
+    }
+}
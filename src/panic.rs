@@ -0,0 +1,59 @@
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    panic::PanicHookInfo,
+};
+
+/// Logs panics as errors with the target `panic` instead of printing them to stderr, so
+/// that crashes look like the rest of the logs and end up in the same outputs. Install
+/// it after the logger:
+/// ```no_run
+/// fern_format::init(log::LevelFilter::Info).unwrap();
+/// fern_format::install_panic_hook();
+/// ```
+///
+/// The message contains the location of the panic, and a backtrace if `RUST_BACKTRACE`
+/// enables them. Panics are still printed by the previous hook if the logger doesn't
+/// accept errors with that target.
+pub fn install_panic_hook() {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let metadata = log::Metadata::builder()
+            .level(log::Level::Error)
+            .target("panic")
+            .build();
+        if log::Level::Error > log::max_level() || !log::logger().enabled(&metadata) {
+            previous(info);
+            return;
+        }
+        log_panic(info);
+        log::logger().flush();
+    }));
+}
+
+fn log_panic(info: &PanicHookInfo<'_>) {
+    let message = match info.payload().downcast_ref::<&str>() {
+        Some(message) => message,
+        None => match info.payload().downcast_ref::<String>() {
+            Some(message) => message.as_str(),
+            None => "Box<dyn Any>",
+        },
+    };
+    let backtrace = Backtrace::capture();
+    let (separator, backtrace): (_, &dyn std::fmt::Display) = match backtrace.status() {
+        BacktraceStatus::Captured => ("\nstack backtrace:\n", &backtrace),
+        _ => ("", &""),
+    };
+    match info.location() {
+        Some(location) => log::error!(
+            target: "panic",
+            "panicked at {}:\n{}{}{}",
+            location,
+            message,
+            separator,
+            backtrace
+        ),
+        None => {
+            log::error!(target: "panic", "panicked:\n{}{}{}", message, separator, backtrace)
+        }
+    }
+}
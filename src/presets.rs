@@ -0,0 +1,274 @@
+//! Ready-made [`fern::Dispatch`]es for common setups
+
+use std::{io::IsTerminal, path::Path};
+
+use log::LevelFilter;
+
+use crate::{
+    json_record::Layout, Colorize, Format, GithubActions, RotatingFile, Stream, Timestamp,
+};
+
+/// Logs everything at `level` and above to stdout, with colors if stdout supports them
+///
+/// ```no_run
+/// fern_format::init(log::LevelFilter::Info).unwrap();
+/// log::info!("hello");
+/// ```
+pub fn init(level: LevelFilter) -> Result<(), log::SetLoggerError> {
+    fern::Dispatch::new()
+        .level(level)
+        .format(Format::new().color_if_supported(Stream::Stdout).callback())
+        .chain(std::io::stdout())
+        .apply()
+}
+
+/// If stdout is the stream that systemd sets `JOURNAL_STREAM` to, as `device:inode`.
+/// Child processes inherit the variable even when their stdout goes elsewhere.
+#[cfg(unix)]
+fn stdout_is_journal() -> bool {
+    use std::os::{fd::AsFd, unix::fs::MetadataExt};
+
+    let Some(stream) = std::env::var_os("JOURNAL_STREAM") else {
+        return false;
+    };
+    let Some((device, inode)) = stream.to_str().and_then(|s| s.split_once(':')) else {
+        return false;
+    };
+    let metadata = std::io::stdout()
+        .as_fd()
+        .try_clone_to_owned()
+        .and_then(|fd| std::fs::File::from(fd).metadata());
+    metadata.is_ok_and(|metadata| {
+        device.parse() == Ok(metadata.dev()) && inode.parse() == Ok(metadata.ino())
+    })
+}
+
+#[cfg(not(unix))]
+fn stdout_is_journal() -> bool {
+    false
+}
+
+impl Format {
+    /// Creates a `Format` that suits where the output goes, by looking at the environment
+    /// once:
+    /// - when stdout goes to the systemd journal, i.e. it is the stream that
+    ///   `JOURNAL_STREAM` names, lines
+    ///   start with [`severity_prefixes`](Self::severity_prefixes) and without the time,
+    ///   since the journal records both by itself
+    /// - with `CI` set, as by most CI services, or in GitHub Actions, lines have the
+    ///   [basic colors](Self::basic_colors), which the log viewers of CI services show
+    ///   even though the output isn't a terminal
+    /// - if stdout is a terminal, lines have colors if it
+    ///   [supports](Self::color_if_supported) them
+    /// - otherwise, when stdout is a pipe or a file, lines have no colors and
+    ///   [RFC 3339](Timestamp::Rfc3339) timestamps with the date
+    ///
+    /// Colors are never used with `NO_COLOR` set, and `RUST_LOG_STYLE` decides about
+    /// them if it is set, like with [`color_from_env`](Self::color_from_env).
+    ///
+    /// ```no_run
+    /// use fern_format::Format;
+    ///
+    /// fern::Dispatch::new()
+    ///     .format(Format::auto().callback())
+    ///     .chain(std::io::stdout())
+    ///     .apply()
+    ///     .unwrap();
+    /// ```
+    pub fn auto() -> Self {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        let ci = std::env::var_os("CI").is_some_and(|v| !v.is_empty() && v != "false")
+            || GithubActions::detected();
+        let format = if stdout_is_journal() {
+            Self::new()
+                .force_colors_if(false)
+                .hide_time()
+                .severity_prefixes()
+        } else if ci {
+            Self::new().force_colors_if(!no_color).basic_colors()
+        } else if std::io::stdout().is_terminal() {
+            Self::new().color_if_supported(Stream::Stdout)
+        } else {
+            Self::new()
+                .force_colors_if(false)
+                .timestamp(Timestamp::Rfc3339)
+        };
+        match std::env::var_os("RUST_LOG_STYLE") {
+            Some(_) if !no_color => format.color_from_env(Stream::Stdout),
+            _ => format,
+        }
+    }
+
+    /// Creates a `Format` that writes each record as a JSON object that CloudWatch Logs
+    /// Insights parses by itself, so that e.g. `fields @timestamp, level, message` works
+    /// without any parse commands:
+    /// ```text
+    /// {"timestamp":1700000000123,"level":"INFO","target":"shop","message":"order placed","order":42}
+    /// ```
+    /// The `timestamp` is in milliseconds since the Unix epoch, and the key-values, the
+    /// [`context`](crate::context) and the static fields are members of the same object.
+    /// Thread names are added as `thread` if enabled. Colors, widths and scopes don't
+    /// apply to JSON.
+    ///
+    /// ```no_run
+    /// use fern_format::Format;
+    ///
+    /// fern::Dispatch::new()
+    ///     .format(Format::cloudwatch().static_field("service", "shop").callback())
+    ///     .chain(std::io::stdout())
+    ///     .apply()
+    ///     .unwrap();
+    /// ```
+    pub fn cloudwatch() -> Self {
+        Self {
+            layout: Layout::Json,
+            ..Self::new()
+        }
+    }
+
+    /// Sends warnings and errors to stderr and everything else to stdout. If colors
+    /// depend on [`color_if_supported`](Self::color_if_supported), they are detected for
+    /// each stream separately.
+    ///
+    /// ```no_run
+    /// use fern_format::{Format, Stream};
+    ///
+    /// Format::new()
+    ///     .color_if_supported(Stream::Stdout)
+    ///     .split_stdout_stderr()
+    ///     .level(log::LevelFilter::Info)
+    ///     .apply()
+    ///     .unwrap();
+    /// ```
+    pub fn split_stdout_stderr(self) -> fern::Dispatch {
+        let line_ending = self.line_ending.as_str();
+        let stderr = self.clone().for_stream(Stream::Stderr);
+        let stdout = self.for_stream(Stream::Stdout);
+
+        fern::Dispatch::new()
+            .chain(
+                fern::Dispatch::new()
+                    .filter(|metadata| metadata.level() > log::Level::Warn)
+                    .format(stdout.callback())
+                    .chain(fern::Output::stdout(line_ending)),
+            )
+            .chain(
+                fern::Dispatch::new()
+                    .level(LevelFilter::Warn)
+                    .format(stderr.callback())
+                    .chain(fern::Output::stderr(line_ending)),
+            )
+    }
+
+    /// Logs to stdout as configured, and appends the same lines without colors and with
+    /// dates to the file at `path`
+    ///
+    /// ```no_run
+    /// use fern_format::{Format, Stream};
+    ///
+    /// Format::new()
+    ///     .color_if_supported(Stream::Stdout)
+    ///     .terminal_and_file("app.log")
+    ///     .unwrap()
+    ///     .apply()
+    ///     .unwrap();
+    /// ```
+    pub fn terminal_and_file(
+        self,
+        path: impl AsRef<Path>,
+    ) -> std::io::Result<fern::Dispatch> {
+        let line_ending = self.line_ending.as_str();
+        let file = self.clone().for_file();
+
+        Ok(fern::Dispatch::new()
+            .chain(
+                fern::Dispatch::new()
+                    .format(self.callback())
+                    .chain(fern::Output::stdout(line_ending)),
+            )
+            .chain(
+                fern::Dispatch::new()
+                    .format(file.callback())
+                    .chain(fern::Output::file(fern::log_file(path)?, line_ending)),
+            ))
+    }
+
+    /// Logs to stdout as configured, and appends copies of the warnings and errors to
+    /// the file at `path`, without colors and with [RFC 3339](Timestamp::Rfc3339)
+    /// timestamps, so that they can be looked through without the rest
+    ///
+    /// ```no_run
+    /// use fern_format::{Format, Stream};
+    ///
+    /// Format::new()
+    ///     .color_if_supported(Stream::Stdout)
+    ///     .terminal_and_errors_file("errors.log")
+    ///     .unwrap()
+    ///     .level(log::LevelFilter::Info)
+    ///     .apply()
+    ///     .unwrap();
+    /// ```
+    pub fn terminal_and_errors_file(
+        self,
+        path: impl AsRef<Path>,
+    ) -> std::io::Result<fern::Dispatch> {
+        let line_ending = self.line_ending.as_str();
+        let mut errors = self.clone().for_file();
+        errors.timestamp = Timestamp::Rfc3339;
+
+        Ok(fern::Dispatch::new()
+            .chain(
+                fern::Dispatch::new()
+                    .format(self.callback())
+                    .chain(fern::Output::stdout(line_ending)),
+            )
+            .chain(
+                fern::Dispatch::new()
+                    .level(LevelFilter::Warn)
+                    .format(errors.callback())
+                    .chain(fern::Output::file(fern::log_file(path)?, line_ending)),
+            ))
+    }
+
+    /// Writes the records to `file` without colors and with dates, like the file of
+    /// [`terminal_and_file`](Self::terminal_and_file)
+    ///
+    /// ```no_run
+    /// use fern_format::{Format, RotatingFile};
+    ///
+    /// let file = RotatingFile::new("app.log").unwrap().daily().keep(7);
+    /// Format::new()
+    ///     .thread_names()
+    ///     .rotating_file(file)
+    ///     .level(log::LevelFilter::Debug)
+    ///     .apply()
+    ///     .unwrap();
+    /// ```
+    pub fn rotating_file(self, file: RotatingFile) -> fern::Dispatch {
+        let file = file.line_ending(self.line_ending);
+        fern::Dispatch::new()
+            .format(self.for_file().callback())
+            .chain(file)
+    }
+
+    /// Without colors, and with the time and date even if only the time or neither
+    /// would be shown
+    fn for_file(mut self) -> Self {
+        self.colorize = Colorize::BlackWhite;
+        self.show_time = true;
+        if self.timestamp == Timestamp::Time {
+            self.timestamp = Timestamp::DateTime;
+        }
+        self
+    }
+
+    /// Detect colors for `stream` if they are detected at all
+    fn for_stream(mut self, stream: Stream) -> Self {
+        match self.colorize {
+            Colorize::ColorIf(_) => self.colorize = Colorize::ColorIf(stream),
+            Colorize::FromEnv(_) => self.colorize = Colorize::FromEnv(stream),
+            Colorize::BlackWhite | Colorize::Color => {}
+        }
+        self
+    }
+}
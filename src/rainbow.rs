@@ -0,0 +1,48 @@
+use std::sync::atomic::{AtomicU16, Ordering};
+
+use owo_colors::Style;
+
+/// How far the hue moves between records, in degrees
+const STEP: u16 = 12;
+
+/// Hands out colors along the hue circle, one per record
+#[derive(Default)]
+pub(crate) struct Rainbow {
+    hue: AtomicU16,
+}
+
+impl Rainbow {
+    /// The color of the next record, or the closest of the 8 basic colors
+    pub(crate) fn next_style(&self, basic_colors: bool) -> Style {
+        let next = |hue| Some((hue + STEP) % 360);
+        let hue = (self.hue)
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, next)
+            .unwrap_or_default();
+        if basic_colors {
+            return match (hue + 30) % 360 / 60 {
+                0 => Style::new().red(),
+                1 => Style::new().yellow(),
+                2 => Style::new().green(),
+                3 => Style::new().cyan(),
+                4 => Style::new().blue(),
+                _ => Style::new().magenta(),
+            };
+        }
+        let (r, g, b) = hue_to_rgb(hue);
+        Style::new().truecolor(r, g, b)
+    }
+}
+
+/// The fully saturated and bright color of `hue`, which is in degrees
+fn hue_to_rgb(hue: u16) -> (u8, u8, u8) {
+    // how far into the current sixth of the circle the hue is, from 0 to 255
+    let x = ((hue % 60) as u32 * 255 / 60) as u8;
+    match hue / 60 {
+        0 => (255, x, 0),
+        1 => (255 - x, 255, 0),
+        2 => (0, 255, x),
+        3 => (0, 255 - x, 255),
+        4 => (x, 0, 255),
+        _ => (255, 0, 255 - x),
+    }
+}
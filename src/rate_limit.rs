@@ -0,0 +1,140 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use crate::lock::{lock, Mutex};
+
+/// A [`log::Log`] that lets at most a number of records through per second and drops the
+/// rest, so that a storm of records can't flood the terminal or fill the disk. The first
+/// record after the second is over is preceded by a `suppressed N records from target`
+/// warning for each target that had records dropped. Nothing is checked in between, so
+/// a target that went quiet only gets its warning with the next record that shares its
+/// second, which with [`per_target`](Self::per_target) is its own next record, or on
+/// [`log::Log::flush`].
+///
+/// Like [`Dedup`](crate::Dedup), it sits in front of the dispatch that does the
+/// formatting:
+/// ```no_run
+/// use fern_format::{Format, RateLimit};
+///
+/// let (_, output) = fern::Dispatch::new()
+///     .format(Format::new().callback())
+///     .chain(std::io::stdout())
+///     .into_log();
+///
+/// fern::Dispatch::new()
+///     .level(log::LevelFilter::Info)
+///     .chain(RateLimit::new(output, 100).per_target())
+///     .apply()
+///     .unwrap();
+/// ```
+pub struct RateLimit<L> {
+    inner: L,
+    per_second: u64,
+    per_target: bool,
+    /// By target, or all under `""` if the limit isn't per target
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+/// The records of the current second
+struct Window {
+    start: Instant,
+    count: u64,
+    /// By target
+    suppressed: BTreeMap<String, u64>,
+}
+
+impl<L: log::Log> RateLimit<L> {
+    /// Lets `per_second` records through to `inner` each second, from all targets
+    /// together
+    pub fn new(inner: L, per_second: u64) -> Self {
+        Self {
+            inner,
+            per_second,
+            per_target: false,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Count the records of each target on their own, so that a noisy target doesn't
+    /// drown out the others
+    pub fn per_target(mut self) -> Self {
+        self.per_target = true;
+        self
+    }
+
+    /// Logs the suppressed records of a window once its lock is released, so that the
+    /// inner logger never runs while it is held
+    fn log_summaries(&self, suppressed: BTreeMap<String, u64>) {
+        for (target, count) in suppressed {
+            self.inner.log(
+                &log::Record::builder()
+                    .level(log::Level::Warn)
+                    .target(&target)
+                    .args(format_args!("suppressed {} records from {}", count, target))
+                    .build(),
+            );
+        }
+    }
+}
+
+impl<L: log::Log> log::Log for RateLimit<L> {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let key = if self.per_target { record.target() } else { "" };
+        let now = Instant::now();
+        let mut windows = lock(&self.windows);
+        let window = match windows.get_mut(key) {
+            Some(window) => window,
+            None => windows.entry(key.to_string()).or_insert(Window {
+                start: now,
+                count: 0,
+                suppressed: BTreeMap::new(),
+            }),
+        };
+
+        let mut suppressed = BTreeMap::new();
+        if now.duration_since(window.start) >= Duration::from_secs(1) {
+            suppressed = std::mem::take(&mut window.suppressed);
+            window.start = now;
+            window.count = 0;
+        }
+        let let_through = window.count < self.per_second;
+        if let_through {
+            window.count += 1;
+        } else {
+            match window.suppressed.get_mut(record.target()) {
+                Some(count) => *count += 1,
+                None => {
+                    window.suppressed.insert(record.target().to_string(), 1);
+                }
+            }
+        }
+        drop(windows);
+
+        self.log_summaries(suppressed);
+        if let_through {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        let suppressed: Vec<_> = (lock(&self.windows).values_mut())
+            .map(|window| std::mem::take(&mut window.suppressed))
+            .collect();
+        for suppressed in suppressed {
+            self.log_summaries(suppressed);
+        }
+        self.inner.flush();
+    }
+}
+
+impl<L: log::Log + 'static> From<RateLimit<L>> for fern::Output {
+    fn from(limit: RateLimit<L>) -> Self {
+        (Box::new(limit) as Box<dyn log::Log>).into()
+    }
+}
@@ -0,0 +1,29 @@
+//! Records that are logged while another one is formatted on the same thread, e.g. by
+//! the `Display` of a key-value, are reported instead of written, see
+//! [`set_error_handler`](crate::set_error_handler). Borrowing the thread-local state of
+//! the outer record twice would panic, and the output may be locked while the outer
+//! record is written, so waiting for it would deadlock.
+
+use std::cell::Cell;
+
+thread_local! {
+    /// If a record is being formatted on this thread
+    static FORMATTING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Marks the thread as formatting a record until it is dropped, also when formatting
+/// panics
+pub(crate) struct Formatting(());
+
+impl Formatting {
+    /// `None` if the thread is formatting a record already
+    pub(crate) fn enter() -> Option<Self> {
+        (!FORMATTING.replace(true)).then(|| Self(()))
+    }
+}
+
+impl Drop for Formatting {
+    fn drop(&mut self) {
+        FORMATTING.set(false);
+    }
+}
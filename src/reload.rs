@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+#[cfg(feature = "time")]
+use crate::timestamp;
+use crate::{
+    components,
+    lock::{self, Mutex, RwLock},
+    Format, Renderer,
+};
+
+impl Format {
+    /// Like [`callback`](Self::callback), with a [`Handle`] to change the format while
+    /// the callback is in use, e.g. when the configuration is reloaded on `SIGHUP`:
+    /// ```
+    /// use fern_format::{Format, Timestamp};
+    ///
+    /// let (callback, handle) = Format::new().reloadable_callback();
+    /// fern::Dispatch::new()
+    ///     .format(callback)
+    ///     .chain(std::io::stdout())
+    ///     .apply()
+    ///     .unwrap();
+    ///
+    /// handle.modify(|format| format.thread_names().timestamp(Timestamp::Rfc3339));
+    /// ```
+    pub fn reloadable_callback(
+        self,
+    ) -> (
+        impl Fn(fern::FormatCallback<'_>, &std::fmt::Arguments<'_>, &log::Record<'_>) + Clone,
+        Handle,
+    ) {
+        let handle = Handle::new(self);
+        let renderer = Arc::clone(&handle.renderer);
+        let callback = move |out: fern::FormatCallback<'_>,
+                             message: &std::fmt::Arguments<'_>,
+                             record: &log::Record<'_>| {
+            // the lock is released before writing, so that a reload never waits for it
+            let renderer = Arc::clone(&lock::read(&renderer));
+            renderer.render(record, message, |line| out.finish(line));
+        };
+        (callback, handle)
+    }
+}
+
+/// Changes the format of a [`reloadable_callback`](Format::reloadable_callback). The
+/// records that are being formatted keep the old format.
+///
+/// The state of the format starts over with each change, e.g. the
+/// [elapsed](Format::target_elapsed) times and the [counts](Format::target_counts) of
+/// targets. The thread that created the callback stays the
+/// [main thread](Format::label_main_thread), and the local time zone is the one from
+/// when it was created.
+#[derive(Clone)]
+pub struct Handle {
+    format: Arc<Mutex<Format>>,
+    renderer: Arc<RwLock<Arc<Renderer>>>,
+    origin: Origin,
+}
+
+impl Handle {
+    fn new(format: Format) -> Self {
+        let origin = Origin::current(&format);
+        Self {
+            renderer: Arc::new(RwLock::new(Arc::new(origin.renderer(format.clone())))),
+            format: Arc::new(Mutex::new(format)),
+            origin,
+        }
+    }
+
+    /// Formats the following records with `format`
+    pub fn set(&self, format: Format) {
+        self.modify(|_| format);
+    }
+
+    /// Formats the following records with the current format changed by `f`
+    pub fn modify(&self, f: impl FnOnce(Format) -> Format) {
+        let mut format = lock::lock(&self.format);
+        *format = f(format.clone());
+        let renderer = self.origin.renderer(format.clone());
+        *lock::write(&self.renderer) = Arc::new(renderer);
+    }
+
+    /// A copy of the current format
+    pub fn format(&self) -> Format {
+        lock::lock(&self.format).clone()
+    }
+}
+
+/// What the formats of a [`Handle`] keep from when it was created
+#[derive(Clone, Copy)]
+struct Origin {
+    /// The number of the main thread
+    main: u64,
+    /// Looked up once, since that fails on some platforms when there are several
+    /// threads, which there usually are by the time the format changes
+    #[cfg(feature = "time")]
+    local_offset: Option<time::UtcOffset>,
+}
+
+impl Origin {
+    #[cfg_attr(not(feature = "time"), allow(unused_variables))]
+    fn current(format: &Format) -> Self {
+        Self {
+            main: components::current_thread_number(),
+            #[cfg(feature = "time")]
+            local_offset: (format.utc_offset.is_none()).then(timestamp::local_offset),
+        }
+    }
+
+    fn renderer(self, format: Format) -> Renderer {
+        #[cfg(feature = "time")]
+        let format = Format {
+            utc_offset: format.utc_offset.or(self.local_offset),
+            ..format
+        };
+        Renderer::with_main_thread(format, self.main)
+    }
+}
@@ -0,0 +1,22 @@
+use std::{fmt::Arguments, sync::RwLock};
+
+type Handler = Box<dyn Fn(Arguments<'_>) + Send + Sync>;
+
+static HANDLER: RwLock<Option<Handler>> = RwLock::new(None);
+
+/// Handle the errors of this crate with `handler` instead of printing them to stderr,
+/// e.g. failures to send records to syslog and invalid options in
+/// [`Format::from_env`](crate::Format::from_env). This is where the messages go on
+/// targets without a useful stderr, such as WebAssembly in the browser. Pass `|_| {}` to
+/// ignore them.
+pub fn set_error_handler(handler: impl Fn(Arguments<'_>) + Send + Sync + 'static) {
+    *HANDLER.write().unwrap_or_else(|e| e.into_inner()) = Some(Box::new(handler));
+}
+
+/// Reports an error of this crate, see [`set_error_handler`]
+pub(crate) fn report(message: Arguments<'_>) {
+    match &*HANDLER.read().unwrap_or_else(|e| e.into_inner()) {
+        Some(handler) => handler(message),
+        None => eprintln!("{message}"),
+    }
+}
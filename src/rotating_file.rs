@@ -0,0 +1,190 @@
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use crate::{
+    lock::{lock, Mutex},
+    report::report,
+    LineEnding,
+};
+
+/// Appends records to a file that is rotated when it would grow past a size or when a
+/// new day begins. The rotated files are kept next to it, `app.log.1` being the newest,
+/// and the oldest are deleted. See [`Format::rotating_file`](crate::Format::rotating_file)
+/// for a dispatch that writes to it without colors:
+/// ```no_run
+/// use fern_format::{Format, RotatingFile};
+///
+/// let file = RotatingFile::new("app.log")
+///     .unwrap()
+///     .max_size(10 * 1024 * 1024)
+///     .keep(3);
+/// Format::new().rotating_file(file).apply().unwrap();
+/// ```
+///
+/// Days begin at midnight in the local time zone with the `time` feature, and in UTC
+/// otherwise.
+pub struct RotatingFile {
+    state: Mutex<State>,
+    path: PathBuf,
+    max_size: Option<u64>,
+    daily: bool,
+    keep: usize,
+    line_ending: LineEnding,
+    /// Of the local time zone, in seconds
+    offset: i64,
+}
+
+/// The file that is being written
+struct State {
+    file: File,
+    size: u64,
+    /// The day the file was started, see [`day`]
+    day: i64,
+}
+
+impl RotatingFile {
+    /// Appends to the file at `path`, which is created if it doesn't exist. It isn't
+    /// rotated until [`max_size`](Self::max_size) or [`daily`](Self::daily) is set.
+    pub fn new(path: impl Into<PathBuf>) -> io::Result<Self> {
+        #[cfg(feature = "time")]
+        let offset = time::UtcOffset::current_local_offset()
+            .map_or(0, |offset| offset.whole_seconds().into());
+        #[cfg(not(feature = "time"))]
+        let offset = 0;
+
+        let path = path.into();
+        let (file, size, modified) = open(&path)?;
+        Ok(Self {
+            state: Mutex::new(State {
+                file,
+                size,
+                day: day(modified, offset),
+            }),
+            path,
+            max_size: None,
+            daily: false,
+            keep: 5,
+            line_ending: LineEnding::Lf,
+            offset,
+        })
+    }
+
+    /// Rotate the file before it grows past `bytes`. Records longer than that get a
+    /// file of their own.
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Rotate the file when the first record of a new day is written, including when
+    /// the file is from an earlier day to begin with
+    pub fn daily(mut self) -> Self {
+        self.daily = true;
+        self
+    }
+
+    /// Keep this many rotated files, 5 by default. With 0 the file starts over empty.
+    pub fn keep(mut self, files: usize) -> Self {
+        self.keep = files;
+        self
+    }
+
+    /// Separate records with `ending`, see [`Format::get_line_ending`](crate::Format::get_line_ending)
+    pub fn line_ending(mut self, ending: LineEnding) -> Self {
+        self.line_ending = ending;
+        self
+    }
+
+    /// The path of the `n`th rotated file, e.g. `app.log.1`
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{n}"));
+        path.into()
+    }
+
+    /// Moves the file to `app.log.1` and the rotated files one further, and starts a
+    /// new one
+    fn rotate(&self, state: &mut State) -> io::Result<()> {
+        let ignore_missing = |result: io::Result<()>| match result {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+        if self.keep == 0 {
+            ignore_missing(fs::remove_file(&self.path))?;
+        } else {
+            // renaming onto an existing file fails on Windows
+            ignore_missing(fs::remove_file(self.rotated(self.keep)))?;
+            for n in (1..self.keep).rev() {
+                ignore_missing(fs::rename(self.rotated(n), self.rotated(n + 1)))?;
+            }
+            fs::rename(&self.path, self.rotated(1))?;
+        }
+        let (file, size, _) = open(&self.path)?;
+        state.file = file;
+        state.size = size;
+        Ok(())
+    }
+}
+
+impl log::Log for RotatingFile {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let line = format!("{}{}", record.args(), self.line_ending.as_str());
+        let mut state = lock(&self.state);
+        let today = day(SystemTime::now(), self.offset);
+        let new_day = self.daily && state.day != today;
+        let too_big = self
+            .max_size
+            .is_some_and(|max| state.size > 0 && state.size + line.len() as u64 > max);
+        if new_day || too_big {
+            match self.rotate(&mut state) {
+                Ok(()) => state.day = today,
+                Err(e) => report(format_args!(
+                    "Failed to rotate the log file {}: {e}",
+                    self.path.display()
+                )),
+            }
+        }
+        match state.file.write_all(line.as_bytes()) {
+            Ok(()) => state.size += line.len() as u64,
+            Err(e) => report(format_args!(
+                "Failed to write to the log file {}: {e}",
+                self.path.display()
+            )),
+        }
+    }
+
+    fn flush(&self) {
+        let _ = lock(&self.state).file.flush();
+    }
+}
+
+impl From<RotatingFile> for fern::Output {
+    fn from(file: RotatingFile) -> Self {
+        fern::Output::from(Box::new(file) as Box<dyn log::Log>)
+    }
+}
+
+/// Opens the file at `path` for appending, with its size and when it was last changed
+fn open(path: &Path) -> io::Result<(File, u64, SystemTime)> {
+    let file = File::options().create(true).append(true).open(path)?;
+    let metadata = file.metadata()?;
+    let modified = metadata.modified().unwrap_or_else(|_| SystemTime::now());
+    Ok((file, metadata.len(), modified))
+}
+
+/// The number of days between the Unix epoch and `time`, `offset` seconds east of UTC
+fn day(time: SystemTime, offset: i64) -> i64 {
+    let seconds = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+    (seconds + offset).div_euclid(24 * 60 * 60)
+}
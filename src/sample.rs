@@ -0,0 +1,118 @@
+use std::fmt::Display;
+
+use crate::{
+    in_module,
+    lock::{lock, Mutex},
+};
+
+/// A [`log::Log`] that only lets a fraction of the records of very chatty targets
+/// through, e.g. of every database query. The messages of the records that are let
+/// through end with the rate, e.g. ` [sampled 1/100]`, so that readers know that they
+/// see a subset. The records of the other targets all go through.
+///
+/// Like [`Dedup`](crate::Dedup), it sits in front of the dispatch that does the
+/// formatting:
+/// ```no_run
+/// use fern_format::{Format, Sample};
+///
+/// let (_, output) = fern::Dispatch::new()
+///     .format(Format::new().callback())
+///     .chain(std::io::stdout())
+///     .into_log();
+///
+/// fern::Dispatch::new()
+///     .level(log::LevelFilter::Debug)
+///     .chain(Sample::new(output).sample_target("sqlx::query", 1.0 / 100.0))
+///     .apply()
+///     .unwrap();
+/// ```
+///
+/// The records are picked evenly instead of at random, e.g. every 100th one.
+pub struct Sample<L> {
+    inner: L,
+    /// The targets with their rates and how much of the next record is due
+    targets: Vec<(String, f64, Mutex<f64>)>,
+}
+
+impl<L: log::Log> Sample<L> {
+    /// Wraps `inner`, letting all records through until targets are sampled
+    pub fn new(inner: L) -> Self {
+        Self {
+            inner,
+            targets: Vec::new(),
+        }
+    }
+
+    /// Let only the fraction `rate` of the records of `target` and its submodules
+    /// through, where 1 is all of them and 0 none. The first matching target applies.
+    pub fn sample_target(mut self, target: impl Into<String>, rate: f64) -> Self {
+        let rate = rate.clamp(0.0, 1.0);
+        // the first record goes through, unless none do
+        let due = if rate > 0.0 { 1.0 } else { 0.0 };
+        self.targets.push((target.into(), rate, Mutex::new(due)));
+        self
+    }
+}
+
+impl<L: log::Log> log::Log for Sample<L> {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let sampled = self
+            .targets
+            .iter()
+            .find(|(target, _, _)| in_module(record.target(), target));
+        let Some((_, rate, due)) = sampled else {
+            return self.inner.log(record);
+        };
+
+        {
+            let mut due = lock(due);
+            if *due < 1.0 {
+                *due += rate;
+                return;
+            }
+            *due += rate - 1.0;
+        }
+        let mut builder = log::Record::builder();
+        builder
+            .level(record.level())
+            .target(record.target())
+            .module_path(record.module_path())
+            .file(record.file())
+            .line(record.line());
+        #[cfg(feature = "kv")]
+        builder.key_values(record.key_values());
+        self.inner.log(
+            &builder
+                .args(format_args!("{} [sampled {}]", record.args(), Rate(*rate)))
+                .build(),
+        );
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+impl<L: log::Log + 'static> From<Sample<L>> for fern::Output {
+    fn from(sample: Sample<L>) -> Self {
+        (Box::new(sample) as Box<dyn log::Log>).into()
+    }
+}
+
+/// `1/100` for a rate of 0.01, or `1/2.5` for 0.4
+struct Rate(f64);
+
+impl Display for Rate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let every = 1.0 / self.0;
+        if (every - every.round()).abs() < 1e-6 {
+            write!(f, "1/{:.0}", every)
+        } else {
+            write!(f, "1/{:.1}", every)
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::fmt::{Display, Write};
+
+const ESC: u8 = 0x1b;
+const BEL: char = '\x07';
+
+/// Writes `.0` without the ANSI escape sequences in it, so that messages can't move the
+/// cursor, clear the screen or fake colors. Text without escapes is written as is after a
+/// single `memchr` over it.
+pub(crate) struct StripEscapes<T>(pub(crate) T);
+
+impl<T: Display> Display for StripEscapes<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            Stripper {
+                out: f,
+                state: State::Text
+            },
+            "{}",
+            self.0
+        )
+    }
+}
+
+/// Where in an escape sequence the text written so far ends. A sequence can be split
+/// across several writes.
+#[derive(Clone, Copy)]
+enum State {
+    Text,
+    /// Right after `ESC`
+    Escape,
+    /// In a `ESC [` control sequence, which ends with a byte in `@..=~`
+    Csi,
+    /// In a `ESC ]` operating system command, which ends with `BEL` or `ESC \`
+    Osc,
+    /// After an `ESC` in an operating system command
+    OscEscape,
+}
+
+struct Stripper<W> {
+    out: W,
+    state: State,
+}
+
+impl<W: Write> Write for Stripper<W> {
+    fn write_str(&mut self, mut s: &str) -> std::fmt::Result {
+        while !s.is_empty() {
+            if let State::Text = self.state {
+                let Some(i) = memchr::memchr(ESC, s.as_bytes()) else {
+                    return self.out.write_str(s);
+                };
+                self.out.write_str(&s[..i])?;
+                self.state = State::Escape;
+                s = &s[i + 1..];
+                continue;
+            }
+
+            let mut chars = s.chars();
+            let Some(c) = chars.next() else {
+                break;
+            };
+            self.state = match (self.state, c) {
+                (State::Escape, '[') => State::Csi,
+                (State::Escape, ']') => State::Osc,
+                // two character sequences, like `ESC c`
+                (State::Escape, _) => State::Text,
+                (State::Csi, '@'..='~') => State::Text,
+                (State::Csi, _) => State::Csi,
+                (State::Osc, BEL) => State::Text,
+                (State::Osc, '\x1b') => State::OscEscape,
+                (State::Osc, _) => State::Osc,
+                (State::OscEscape, '\\') => State::Text,
+                (State::OscEscape, _) => State::Osc,
+                (State::Text, _) => unreachable!(),
+            };
+            s = chars.as_str();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+use std::{borrow::Cow, cell::RefCell, fmt::Display, marker::PhantomData};
+
+use owo_colors::Style;
+
+use crate::{non_blocking::with_origin, styled::Styled};
+
+thread_local! {
+    static SCOPES: RefCell<Vec<Cow<'static, str>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Enters a named scope on this thread until the returned guard is dropped. Records
+/// logged inside of it are prefixed with the names of all active scopes, or indented by
+/// their depth, see [`Format::scopes_as_indentation`](crate::Format::scopes_as_indentation).
+///
+/// ```
+/// let _config = fern_format::scope("loading config");
+/// let _parse = fern_format::scope("parsing");
+/// log::info!("done"); // ... loading config > parsing: done
+/// ```
+pub fn scope(name: impl Into<Cow<'static, str>>) -> ScopeGuard {
+    SCOPES.with_borrow_mut(|scopes| {
+        let depth = scopes.len();
+        scopes.push(name.into());
+        ScopeGuard {
+            depth,
+            not_send: PhantomData,
+        }
+    })
+}
+
+/// The names of the active scopes of this thread
+pub(crate) fn active() -> Vec<Cow<'static, str>> {
+    SCOPES.with(|scopes| {
+        scopes
+            .try_borrow()
+            .map_or_else(|_| Vec::new(), |s| s.clone())
+    })
+}
+
+/// Calls `f` with the name of the outermost active scope of the current thread
+pub(crate) fn with_outermost<R>(f: impl FnOnce(Option<&str>) -> R) -> R {
+    Scopes::with(ScopeStyle::Breadcrumbs, None, |scopes| {
+        f(scopes.scopes.first().map(|scope| scope.as_ref()))
+    })
+}
+
+/// Leaves a scope when dropped, see [`scope`]. It leaves the scope of the thread it was
+/// created on, so it can't be sent to another one:
+/// ```compile_fail
+/// let guard = fern_format::scope("loading config");
+/// std::thread::spawn(move || drop(guard));
+/// ```
+#[must_use = "the scope is left again when the guard is dropped"]
+pub struct ScopeGuard {
+    depth: usize,
+    not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        // also leaves inner scopes whose guards were leaked or dropped out of order
+        SCOPES.with_borrow_mut(|scopes| scopes.truncate(self.depth));
+    }
+}
+
+/// How active scopes are shown
+#[derive(Clone, Copy, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub(crate) enum ScopeStyle {
+    /// `outer > inner: message`
+    #[default]
+    Breadcrumbs,
+    /// Two spaces per level before the message
+    Indent,
+}
+
+/// Renders the active scopes of the current thread in a [`ScopeStyle`]
+pub(crate) struct Scopes<'a> {
+    scopes: &'a [Cow<'static, str>],
+    style: ScopeStyle,
+    color: Option<Style>,
+}
+
+impl<'a> Scopes<'a> {
+    /// Calls `f` with the active scopes of the current thread
+    pub(crate) fn with<R>(
+        style: ScopeStyle,
+        color: Option<Style>,
+        f: impl FnOnce(Scopes<'_>) -> R,
+    ) -> R {
+        with_origin(|origin| match origin {
+            Some(origin) => f(Scopes {
+                scopes: &origin.scopes,
+                style,
+                color,
+            }),
+            None => SCOPES.with(|scopes| {
+                let scopes = scopes.try_borrow();
+                f(Scopes {
+                    scopes: scopes.as_deref().map_or(&[], Vec::as_slice),
+                    style,
+                    color,
+                })
+            }),
+        })
+    }
+}
+
+impl<'a> Display for Scopes<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.scopes.is_empty() {
+            return Ok(());
+        }
+
+        match self.style {
+            ScopeStyle::Breadcrumbs => {
+                let style = self.color;
+                f.write_str(" ")?;
+                for (i, scope) in self.scopes.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "{}", Styled(" > ", style))?;
+                    }
+                    write!(f, "{}", Styled(scope, style))?;
+                }
+                write!(f, "{}", Styled(":", style))
+            }
+            ScopeStyle::Indent => write!(f, "{:1$}", "", self.scopes.len() * 2),
+        }
+    }
+}
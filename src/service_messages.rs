@@ -0,0 +1,193 @@
+use std::{
+    fmt::{Display, Write as _},
+    io::{self, Write},
+    sync::Mutex,
+};
+
+use crate::{report::report, sanitize::StripEscapes};
+
+/// Writes records to stdout, with warnings and errors as service messages of TeamCity or
+/// logging commands of Azure DevOps, so that the build highlights them without any
+/// parsing rules. Other records are written as plain lines.
+/// ```no_run
+/// use fern_format::{Format, ServiceMessages};
+///
+/// let output: fern::Output = match ServiceMessages::detect() {
+///     Some(messages) => messages.into(),
+///     None => std::io::stdout().into(),
+/// };
+/// fern::Dispatch::new()
+///     .format(Format::new().callback())
+///     .chain(output)
+///     .apply()
+///     .unwrap();
+/// ```
+pub struct ServiceMessages {
+    server: Server,
+    stdout: Mutex<io::Stdout>,
+}
+
+#[derive(Clone, Copy)]
+enum Server {
+    TeamCity,
+    AzureDevOps,
+}
+
+impl ServiceMessages {
+    /// `##teamcity[message text='...' status='WARNING']`
+    pub fn teamcity() -> Self {
+        Self::new(Server::TeamCity)
+    }
+
+    /// `##vso[task.logissue type=warning;sourcepath=...;linenumber=...]...`
+    pub fn azure_devops() -> Self {
+        Self::new(Server::AzureDevOps)
+    }
+
+    /// The messages of the build server the program runs on, if it is TeamCity or Azure
+    /// DevOps
+    pub fn detect() -> Option<Self> {
+        if std::env::var_os("TEAMCITY_VERSION").is_some() {
+            Some(Self::teamcity())
+        } else if std::env::var_os("TF_BUILD")
+            .is_some_and(|v| v.eq_ignore_ascii_case("true"))
+        {
+            Some(Self::azure_devops())
+        } else {
+            None
+        }
+    }
+
+    fn new(server: Server) -> Self {
+        Self {
+            server,
+            stdout: Mutex::new(io::stdout()),
+        }
+    }
+
+    fn message(&self, record: &log::Record<'_>) -> Result<String, std::fmt::Error> {
+        let mut out = String::with_capacity(256);
+        if record.level() > log::Level::Warn {
+            writeln!(out, "{}", record.args())?;
+            return Ok(out);
+        }
+
+        // the build servers don't show colors in their messages
+        let text = StripEscapes(record.args());
+        let error = record.level() == log::Level::Error;
+        match self.server {
+            Server::TeamCity => {
+                let status = if error { "ERROR" } else { "WARNING" };
+                writeln!(
+                    out,
+                    "##teamcity[message text='{}' status='{}']",
+                    TeamCity(text),
+                    status
+                )?;
+            }
+            Server::AzureDevOps => {
+                let kind = if error { "error" } else { "warning" };
+                write!(out, "##vso[task.logissue type={}", kind)?;
+                if let Some(file) = record.file() {
+                    write!(out, ";sourcepath={}", Azure(file, true))?;
+                    if let Some(line) = record.line() {
+                        write!(out, ";linenumber={}", line)?;
+                    }
+                }
+                writeln!(out, "]{}", Azure(text, false))?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl log::Log for ServiceMessages {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let Ok(message) = self.message(record) else {
+            return;
+        };
+        let mut stdout = self.stdout.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = stdout
+            .write_all(message.as_bytes())
+            .and_then(|()| stdout.flush())
+        {
+            report(format_args!("Failed to write a service message: {e}"));
+        }
+    }
+
+    fn flush(&self) {
+        let _ = self
+            .stdout
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .flush();
+    }
+}
+
+impl From<ServiceMessages> for fern::Output {
+    fn from(messages: ServiceMessages) -> Self {
+        fern::Output::from(Box::new(messages) as Box<dyn log::Log>)
+    }
+}
+
+/// A value escaped for TeamCity with `|`
+struct TeamCity<T>(T);
+
+impl<T: Display> Display for TeamCity<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        struct Escape<'a, 'b>(&'a mut std::fmt::Formatter<'b>);
+
+        impl std::fmt::Write for Escape<'_, '_> {
+            fn write_str(&mut self, s: &str) -> std::fmt::Result {
+                for c in s.chars() {
+                    match c {
+                        '|' => self.0.write_str("||")?,
+                        '\'' => self.0.write_str("|'")?,
+                        '\n' => self.0.write_str("|n")?,
+                        '\r' => self.0.write_str("|r")?,
+                        '[' => self.0.write_str("|[")?,
+                        ']' => self.0.write_str("|]")?,
+                        '\u{85}' => self.0.write_str("|x")?,
+                        '\u{2028}' => self.0.write_str("|l")?,
+                        '\u{2029}' => self.0.write_str("|p")?,
+                        c => self.0.write_char(c)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        write!(Escape(f), "{}", self.0)
+    }
+}
+
+/// A message, or a property if `.1`, escaped for Azure DevOps
+struct Azure<T>(T, bool);
+
+impl<T: Display> Display for Azure<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        struct Escape<'a, 'b>(&'a mut std::fmt::Formatter<'b>, bool);
+
+        impl std::fmt::Write for Escape<'_, '_> {
+            fn write_str(&mut self, s: &str) -> std::fmt::Result {
+                for c in s.chars() {
+                    match c {
+                        '%' => self.0.write_str("%AZP25")?,
+                        '\r' => self.0.write_str("%0D")?,
+                        '\n' => self.0.write_str("%0A")?,
+                        ';' if self.1 => self.0.write_str("%3B")?,
+                        ']' if self.1 => self.0.write_str("%5D")?,
+                        c => self.0.write_char(c)?,
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        write!(Escape(f, self.1), "{}", self.0)
+    }
+}
@@ -0,0 +1,134 @@
+use std::{io::Write, sync::Mutex};
+
+#[cfg(feature = "kv")]
+use slog::KV;
+use slog::{Drain, OwnedKVList};
+
+#[cfg(feature = "kv")]
+use crate::kv::OwnedValue;
+use crate::{report::report, Format, Renderer};
+
+/// A [`slog::Drain`] that formats records like [`Format::callback`], so that code that
+/// logs with slog looks the same as code that logs with `log`:
+/// ```
+/// use fern_format::{Format, SlogDrain};
+///
+/// let drain = SlogDrain::new(Format::new(), std::io::stdout());
+/// let logger = slog::Logger::root(drain, slog::o!());
+/// ```
+///
+/// The module of a record is used as its target, unless it has a tag. Key-values are
+/// rendered with the `kv` feature, those of the record before those of the logger. Write
+/// errors are reported, see [`set_error_handler`](crate::set_error_handler).
+pub struct SlogDrain {
+    renderer: Renderer,
+    writer: Mutex<Box<dyn Write + Send>>,
+    line_ending: &'static str,
+}
+
+impl SlogDrain {
+    pub fn new(format: Format, writer: impl Write + Send + 'static) -> Self {
+        Self {
+            line_ending: format.get_line_ending().as_str(),
+            renderer: format.renderer(),
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+
+    fn write(&self, record: &log::Record<'_>) -> std::io::Result<()> {
+        self.renderer
+            .render(record, record.args(), |line| {
+                let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+                write!(writer, "{}{}", line, self.line_ending)?;
+                writer.flush()
+            })
+            .unwrap_or(Ok(()))
+    }
+}
+
+impl Drain for SlogDrain {
+    type Ok = ();
+    type Err = slog::Never;
+
+    #[cfg_attr(not(feature = "kv"), allow(unused_variables))]
+    fn log(
+        &self,
+        record: &slog::Record<'_>,
+        values: &OwnedKVList,
+    ) -> Result<(), slog::Never> {
+        let target = match record.tag() {
+            "" => record.module(),
+            tag => tag,
+        };
+
+        let mut builder = log::Record::builder();
+        builder
+            .level(level(record.level()))
+            .target(target)
+            .module_path_static(Some(record.module()))
+            .file_static(Some(record.file()))
+            .line(Some(record.line()));
+        #[cfg(feature = "kv")]
+        let key_values = {
+            let mut collect = Collect(Vec::new());
+            let _ = record.kv().serialize(record, &mut collect);
+            let _ = values.serialize(record, &mut collect);
+            collect.0
+        };
+        #[cfg(feature = "kv")]
+        builder.key_values(&key_values);
+        if let Err(e) = self.write(&builder.args(*record.msg()).build()) {
+            report(format_args!("Failed to write a slog record: {e}"));
+        }
+        Ok(())
+    }
+}
+
+fn level(level: slog::Level) -> log::Level {
+    match level {
+        slog::Level::Critical | slog::Level::Error => log::Level::Error,
+        slog::Level::Warning => log::Level::Warn,
+        slog::Level::Info => log::Level::Info,
+        slog::Level::Debug => log::Level::Debug,
+        slog::Level::Trace => log::Level::Trace,
+    }
+}
+
+/// Collects the key-values of a record
+#[cfg(feature = "kv")]
+struct Collect(Vec<(String, OwnedValue)>);
+
+#[cfg(feature = "kv")]
+impl Collect {
+    fn push(&mut self, key: slog::Key, value: OwnedValue) -> slog::Result {
+        self.0.push((key.to_string(), value));
+        Ok(())
+    }
+}
+
+#[cfg(feature = "kv")]
+impl slog::Serializer for Collect {
+    fn emit_u64(&mut self, key: slog::Key, val: u64) -> slog::Result {
+        self.push(key, OwnedValue::U64(val))
+    }
+
+    fn emit_i64(&mut self, key: slog::Key, val: i64) -> slog::Result {
+        self.push(key, OwnedValue::I64(val))
+    }
+
+    fn emit_f64(&mut self, key: slog::Key, val: f64) -> slog::Result {
+        self.push(key, OwnedValue::F64(val))
+    }
+
+    fn emit_bool(&mut self, key: slog::Key, val: bool) -> slog::Result {
+        self.push(key, OwnedValue::Bool(val))
+    }
+
+    fn emit_arguments(
+        &mut self,
+        key: slog::Key,
+        val: &std::fmt::Arguments<'_>,
+    ) -> slog::Result {
+        self.push(key, OwnedValue::Str(val.to_string()))
+    }
+}
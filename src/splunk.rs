@@ -0,0 +1,126 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    json,
+    non_blocking::with_origin,
+    report::report,
+    syslog::{executable_name, hostname},
+};
+
+/// Writes each record as an event object for the HTTP Event Collector of Splunk, one JSON
+/// object per line, with the formatted line as the `event`. Splunk accepts several of
+/// them concatenated in one request, so a forwarder can post batches of lines as they
+/// are to `/services/collector/event`.
+/// ```no_run
+/// use fern_format::{Format, SplunkHec};
+///
+/// fern::Dispatch::new()
+///     .format(Format::new().callback())
+///     .chain(SplunkHec::new(std::io::stdout()).sourcetype("shop:log"))
+///     .apply()
+///     .unwrap();
+/// log::info!("order placed");
+/// // {"time":1700000000.123,"host":"vm","source":"shop","sourcetype":"shop:log","event":"..."}
+/// ```
+pub struct SplunkHec {
+    writer: Mutex<Box<dyn Write + Send>>,
+    host: Option<String>,
+    source: Option<String>,
+    sourcetype: Option<String>,
+}
+
+impl SplunkHec {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+            host: hostname(),
+            source: executable_name(),
+            sourcetype: None,
+        }
+    }
+
+    /// Defaults to the name of this machine
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = Some(host.into());
+        self
+    }
+
+    /// Defaults to the name of the executable
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Left out by default, so that the collector uses the one of its token
+    pub fn sourcetype(mut self, sourcetype: impl Into<String>) -> Self {
+        self.sourcetype = Some(sourcetype.into());
+        self
+    }
+
+    fn event(&self, record: &log::Record<'_>) -> Result<String, std::fmt::Error> {
+        let time =
+            with_origin(|origin| origin.map(|o| o.time)).unwrap_or_else(SystemTime::now);
+        let time = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+        let mut event = String::with_capacity(256);
+        write!(
+            event,
+            "{{\"time\":{}.{:03}",
+            time.as_secs(),
+            time.subsec_millis()
+        )?;
+        let metadata = [
+            ("host", &self.host),
+            ("source", &self.source),
+            ("sourcetype", &self.sourcetype),
+        ];
+        for (key, value) in metadata {
+            if let Some(value) = value {
+                write!(event, ",\"{}\":", key)?;
+                json::write_str(&mut event, value)?;
+            }
+        }
+        event.write_str(",\"event\":")?;
+        json::write_display(&mut event, record.args())?;
+        event.write_str("}\n")?;
+        Ok(event)
+    }
+}
+
+impl log::Log for SplunkHec {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let Ok(event) = self.event(record) else {
+            return;
+        };
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let result: io::Result<()> = writer
+            .write_all(event.as_bytes())
+            .and_then(|()| writer.flush());
+        if let Err(e) = result {
+            report(format_args!("Failed to write a record for Splunk: {e}"));
+        }
+    }
+
+    fn flush(&self) {
+        let _ = self
+            .writer
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .flush();
+    }
+}
+
+impl From<SplunkHec> for fern::Output {
+    fn from(splunk: SplunkHec) -> Self {
+        fern::Output::from(Box::new(splunk) as Box<dyn log::Log>)
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use crate::elapsed::Short;
+
+/// Starts a named stopwatch, which logs how long it ran when it is dropped, e.g.
+/// `startup took 1.2s`:
+/// ```
+/// let startup = fern_format::checkpoint("startup");
+/// // ...
+/// log::info!("config loaded after {}", startup); // config loaded after 850µs
+/// drop(startup); // startup took 1.2ms
+/// ```
+///
+/// With the `kv` feature the elapsed time can also be a key-value, e.g.
+/// `log::info!(startup; "ready")`.
+pub fn checkpoint(name: impl Into<Cow<'static, str>>) -> Stopwatch {
+    Stopwatch {
+        name: name.into(),
+        start: Instant::now(),
+        level: Some(log::Level::Info),
+    }
+}
+
+/// Measures the time since it was started, see [`checkpoint`]. Displays the elapsed time
+/// in the same way as [`Format::target_elapsed`](crate::Format::target_elapsed).
+pub struct Stopwatch {
+    name: Cow<'static, str>,
+    start: Instant,
+    level: Option<log::Level>,
+}
+
+impl Stopwatch {
+    /// Log the total time at `level` when dropped, or not at all if `None`. Defaults to
+    /// info.
+    pub fn log_on_drop(mut self, level: Option<log::Level>) -> Self {
+        self.level = level;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The time since the stopwatch was started
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl Display for Stopwatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Short(self.elapsed()))
+    }
+}
+
+#[cfg(feature = "kv")]
+impl log::kv::ToValue for Stopwatch {
+    fn to_value(&self) -> log::kv::Value<'_> {
+        log::kv::Value::from_display(self)
+    }
+}
+
+impl Drop for Stopwatch {
+    fn drop(&mut self) {
+        if let Some(level) = self.level {
+            log::log!(target: "stopwatch", level, "{} took {}", self.name, self);
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use std::fmt::Display;
+
+use owo_colors::{OwoColorize, Style};
+
+/// Writes `.0` in the style `.1`, or as is without going through owo-colors at all when
+/// colors are disabled
+pub(crate) struct Styled<T>(pub(crate) T, pub(crate) Option<Style>);
+
+impl<T: Display> Display for Styled<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.1 {
+            Some(style) => self.0.style(style).fmt(f),
+            None => self.0.fmt(f),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    io::{self, Write},
+    sync::Mutex,
+};
+
+use crate::{report::report, LineEnding};
+
+type Hook = Box<dyn Fn(&mut dyn FnMut()) + Send + Sync>;
+
+/// An output that writes each line from inside a hook, which can hide what else is drawn
+/// on the terminal while the line is written. This keeps log lines from tearing through
+/// progress bars. With the `indicatif` feature there are hooks for its progress bars:
+/// ```no_run
+/// # #[cfg(feature = "indicatif")]
+/// # {
+/// use fern_format::{Format, SuspendOutput};
+/// use indicatif::{MultiProgress, ProgressBar};
+///
+/// let bars = MultiProgress::new();
+/// fern::Dispatch::new()
+///     .format(Format::new().callback())
+///     .chain(SuspendOutput::multi_progress(std::io::stderr(), bars.clone()))
+///     .apply()
+///     .unwrap();
+///
+/// let bar = bars.add(ProgressBar::new(100));
+/// log::info!("downloading");
+/// # }
+/// ```
+pub struct SuspendOutput {
+    writer: Mutex<Box<dyn Write + Send>>,
+    hook: Hook,
+    line_ending: LineEnding,
+}
+
+impl SuspendOutput {
+    /// Writes to `writer` from inside of `hook`, which must call the function it is given
+    pub fn new(
+        writer: impl Write + Send + 'static,
+        hook: impl Fn(&mut dyn FnMut()) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+            hook: Box::new(hook),
+            line_ending: LineEnding::Lf,
+        }
+    }
+
+    /// Hides `bar` while writing
+    #[cfg(feature = "indicatif")]
+    pub fn progress_bar(
+        writer: impl Write + Send + 'static,
+        bar: indicatif::ProgressBar,
+    ) -> Self {
+        Self::new(writer, move |write| bar.suspend(write))
+    }
+
+    /// Hides all bars of `bars` while writing
+    #[cfg(feature = "indicatif")]
+    pub fn multi_progress(
+        writer: impl Write + Send + 'static,
+        bars: indicatif::MultiProgress,
+    ) -> Self {
+        Self::new(writer, move |write| bars.suspend(write))
+    }
+
+    /// Separate records with `ending`, see [`Format::get_line_ending`](crate::Format::get_line_ending)
+    pub fn line_ending(mut self, ending: LineEnding) -> Self {
+        self.line_ending = ending;
+        self
+    }
+}
+
+impl log::Log for SuspendOutput {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let mut result: io::Result<()> = Ok(());
+        (self.hook)(&mut || {
+            result = write!(writer, "{}{}", record.args(), self.line_ending.as_str())
+                .and_then(|()| writer.flush());
+        });
+        if let Err(e) = result {
+            report(format_args!("Failed to write a record: {e}"));
+        }
+    }
+
+    fn flush(&self) {
+        let _ = self
+            .writer
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .flush();
+    }
+}
+
+impl From<SuspendOutput> for fern::Output {
+    fn from(output: SuspendOutput) -> Self {
+        fern::Output::from(Box::new(output) as Box<dyn log::Log>)
+    }
+}
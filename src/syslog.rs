@@ -0,0 +1,278 @@
+use std::{
+    io::{self, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
+    sync::Mutex,
+};
+#[cfg(unix)]
+use std::{os::unix::net::UnixDatagram, path::Path};
+
+use crate::report::report;
+
+/// Sends records to a syslog collector as RFC 5424 messages, with the formatted line as
+/// the message:
+/// ```no_run
+/// use fern_format::{Facility, Format, Syslog};
+///
+/// fern::Dispatch::new()
+///     .format(Format::new().callback())
+///     .chain(Syslog::udp("logs.example.com:514").unwrap().facility(Facility::Daemon))
+///     .apply()
+///     .unwrap();
+/// ```
+pub struct Syslog {
+    transport: Transport,
+    facility: Facility,
+    hostname: String,
+    app_name: String,
+    pid: u32,
+}
+
+enum Transport {
+    Udp(UdpSocket),
+    /// Messages are framed by their length, see RFC 6587
+    Tcp(Mutex<TcpStream>),
+    #[cfg(unix)]
+    Unix(UnixDatagram),
+}
+
+/// The facility of [`Syslog`] messages, which tells the collector what kind of program
+/// sent them
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Facility {
+    Kern = 0,
+    #[default]
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    AuthPriv = 10,
+    Ftp = 11,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+impl Syslog {
+    /// Sends datagrams to `server`, usually on port 514
+    pub fn udp(server: impl ToSocketAddrs) -> io::Result<Self> {
+        let server = resolve(server)?;
+        let local: SocketAddr = match server {
+            SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+            SocketAddr::V6(_) => ([0; 8], 0).into(),
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(server)?;
+        Ok(Self::new(Transport::Udp(socket)))
+    }
+
+    /// Sends messages over a TCP connection to `server`, usually on port 601
+    pub fn tcp(server: impl ToSocketAddrs) -> io::Result<Self> {
+        let stream = TcpStream::connect(resolve(server)?)?;
+        Ok(Self::new(Transport::Tcp(Mutex::new(stream))))
+    }
+
+    /// Sends datagrams to the unix socket at `path`
+    #[cfg(unix)]
+    pub fn unix(path: impl AsRef<Path>) -> io::Result<Self> {
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path)?;
+        Ok(Self::new(Transport::Unix(socket)))
+    }
+
+    /// Sends datagrams to the syslog daemon of this machine at `/dev/log`
+    #[cfg(unix)]
+    pub fn local() -> io::Result<Self> {
+        Self::unix("/dev/log")
+    }
+
+    fn new(transport: Transport) -> Self {
+        Self {
+            transport,
+            facility: Facility::User,
+            hostname: header_field(&hostname().unwrap_or_default(), MAX_HOSTNAME),
+            app_name: header_field(&executable_name().unwrap_or_default(), MAX_APP_NAME),
+            pid: std::process::id(),
+        }
+    }
+
+    /// Defaults to [`Facility::User`]
+    pub fn facility(mut self, facility: Facility) -> Self {
+        self.facility = facility;
+        self
+    }
+
+    /// Defaults to the name of the executable. Like the hostname, it is cut to the
+    /// length that RFC 5424 allows, and characters other than printable ASCII become `_`.
+    pub fn app_name(mut self, name: impl Into<String>) -> Self {
+        self.app_name = header_field(&name.into(), MAX_APP_NAME);
+        self
+    }
+
+    /// Defaults to the name of this machine
+    pub fn hostname(mut self, name: impl Into<String>) -> Self {
+        self.hostname = header_field(&name.into(), MAX_HOSTNAME);
+        self
+    }
+
+    /// `<PRI>1 TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA MSG`. The time
+    /// is left out without the `time` feature, the collector then uses the time it got
+    /// the message.
+    fn message(&self, record: &log::Record<'_>) -> String {
+        let priority = self.facility as u8 * 8 + severity(record.level());
+        format!(
+            "<{}>1 {} {} {} {} - - {}",
+            priority,
+            timestamp(),
+            self.hostname,
+            self.app_name,
+            self.pid,
+            record.args()
+        )
+    }
+
+    fn send(&self, message: &str) -> io::Result<()> {
+        match &self.transport {
+            Transport::Udp(socket) => socket.send(message.as_bytes()).map(drop),
+            Transport::Tcp(stream) => {
+                let mut stream = stream.lock().unwrap_or_else(|e| e.into_inner());
+                write!(stream, "{} {}", message.len(), message)
+            }
+            #[cfg(unix)]
+            Transport::Unix(socket) => socket.send(message.as_bytes()).map(drop),
+        }
+    }
+}
+
+impl log::Log for Syslog {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if let Err(e) = self.send(&self.message(record)) {
+            report(format_args!("Failed to send a record to syslog: {e}"));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+impl From<Syslog> for fern::Output {
+    fn from(syslog: Syslog) -> Self {
+        fern::Output::from(Box::new(syslog) as Box<dyn log::Log>)
+    }
+}
+
+/// The syslog severity of `level`
+pub(crate) fn severity(level: log::Level) -> u8 {
+    match level {
+        log::Level::Error => 3,
+        log::Level::Warn => 4,
+        log::Level::Info => 6,
+        log::Level::Debug | log::Level::Trace => 7,
+    }
+}
+
+/// `severity` in angle brackets, as it starts lines for the systemd journal
+pub(crate) fn prefix(severity: u8) -> &'static str {
+    ["<0>", "<1>", "<2>", "<3>", "<4>", "<5>", "<6>", "<7>"][usize::from(severity & 7)]
+}
+
+/// The longest hostname that RFC 5424 allows
+const MAX_HOSTNAME: usize = 255;
+
+/// The longest app name that RFC 5424 allows
+const MAX_APP_NAME: usize = 48;
+
+/// `value` as a field of the header, which is printable ASCII without spaces, at most
+/// `max` characters long, and `-` if empty
+fn header_field(value: &str, max: usize) -> String {
+    if value.is_empty() {
+        return "-".into();
+    }
+    value
+        .chars()
+        .take(max)
+        .map(|c| match c {
+            '!'..='~' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+fn resolve(server: impl ToSocketAddrs) -> io::Result<SocketAddr> {
+    server.to_socket_addrs()?.next().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "the address resolved to nothing",
+        )
+    })
+}
+
+/// The name of this machine
+pub(crate) fn hostname() -> Option<String> {
+    let name = std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())?;
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// The file name of the running executable
+pub(crate) fn executable_name() -> Option<String> {
+    let exe = std::env::current_exe().ok()?;
+    Some(exe.file_name()?.to_string_lossy().into_owned())
+}
+
+#[cfg(feature = "time")]
+fn timestamp() -> String {
+    time::OffsetDateTime::now_utc()
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap_or_else(|_| "-".into())
+}
+
+#[cfg(not(feature = "time"))]
+fn timestamp() -> String {
+    "-".into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_fields_are_printable_ascii() {
+        assert_eq!(header_field("my app\tv2\u{e9}", MAX_APP_NAME), "my_app_v2_");
+        assert_eq!(header_field("", MAX_APP_NAME), "-");
+        assert_eq!(header_field(&"a".repeat(60), MAX_APP_NAME).len(), 48);
+        assert_eq!(header_field(&"h".repeat(300), MAX_HOSTNAME).len(), 255);
+    }
+
+    #[test]
+    fn header_keeps_its_fields() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let syslog = Syslog::udp(socket.local_addr().unwrap())
+            .unwrap()
+            .hostname("web 1")
+            .app_name("");
+        let record = log::Record::builder()
+            .args(format_args!("hello world"))
+            .level(log::Level::Info)
+            .build();
+        let message = syslog.message(&record);
+        let fields: Vec<_> = message.splitn(8, ' ').collect();
+        assert_eq!(fields[2..4], ["web_1", "-"]);
+        assert_eq!(fields[7], "hello world");
+    }
+}
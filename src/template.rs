@@ -0,0 +1,255 @@
+//! Formats laid out by a template that is checked at compile time, see
+//! [`format_template!`](crate::format_template)
+
+use std::{fmt::Display, sync::Arc};
+
+#[cfg(feature = "time")]
+use crate::Clock;
+use crate::{
+    components::{default_level_escapes, with_thread_name, LevelStyled, Time},
+    Timestamp,
+};
+
+/// Creates a [`Template`] from a string literal with the placeholders `{time}`,
+/// `{thread}`, `{level}`, `{target}` and `{msg}`. Braces are escaped by doubling them.
+///
+/// The template is parsed at compile time, so typos are compile errors and nothing is
+/// parsed while logging.
+///
+/// ```no_run
+/// fern::Dispatch::new()
+///     .format(fern_format::format_template!("{time} [{level}] {target}: {msg}").callback())
+///     .chain(std::io::stdout())
+///     .apply()
+///     .unwrap();
+/// ```
+///
+/// ```compile_fail
+/// fern_format::format_template!("{time} [{levle}] {msg}");
+/// ```
+#[macro_export]
+macro_rules! format_template {
+    ($template:literal) => {{
+        const TEMPLATE: &str = $template;
+        const SEGMENTS: [$crate::template::Segment; $crate::template::count(TEMPLATE)] =
+            $crate::template::parse(TEMPLATE);
+        $crate::template::Template::new(TEMPLATE, &SEGMENTS)
+    }};
+}
+
+/// A part of a template
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug)]
+pub enum Segment {
+    /// The bytes between these indices of the template
+    Literal(usize, usize),
+    Time,
+    Thread,
+    Level,
+    Target,
+    Message,
+}
+
+/// The number of segments in `template`
+#[doc(hidden)]
+pub const fn count(template: &str) -> usize {
+    let mut n = 0;
+    let mut i = 0;
+    while i < template.len() {
+        i = next(template.as_bytes(), i).1;
+        n += 1;
+    }
+    n
+}
+
+/// The `N` segments of `template`, see [`count`]
+#[doc(hidden)]
+pub const fn parse<const N: usize>(template: &str) -> [Segment; N] {
+    let mut segments = [Segment::Literal(0, 0); N];
+    let mut n = 0;
+    let mut i = 0;
+    while i < template.len() {
+        let (segment, end) = next(template.as_bytes(), i);
+        segments[n] = segment;
+        n += 1;
+        i = end;
+    }
+    segments
+}
+
+/// The segment that starts at `i`, and where the next one starts
+const fn next(template: &[u8], i: usize) -> (Segment, usize) {
+    match template[i] {
+        b'{' if i + 1 < template.len() && template[i + 1] == b'{' => {
+            (Segment::Literal(i, i + 1), i + 2)
+        }
+        b'}' if i + 1 < template.len() && template[i + 1] == b'}' => {
+            (Segment::Literal(i, i + 1), i + 2)
+        }
+        b'}' => panic!("unmatched `}}` in the template, escape it as `}}}}`"),
+        b'{' => {
+            let mut end = i + 1;
+            while end < template.len() && template[end] != b'}' {
+                end += 1;
+            }
+            if end == template.len() {
+                panic!("unclosed `{{` in the template, escape it as `{{{{`");
+            }
+            (placeholder(template, i + 1, end), end + 1)
+        }
+        _ => {
+            let mut end = i + 1;
+            while end < template.len() && template[end] != b'{' && template[end] != b'}' {
+                end += 1;
+            }
+            (Segment::Literal(i, end), end)
+        }
+    }
+}
+
+const fn placeholder(template: &[u8], start: usize, end: usize) -> Segment {
+    if name_is(template, start, end, b"time") {
+        Segment::Time
+    } else if name_is(template, start, end, b"thread") {
+        Segment::Thread
+    } else if name_is(template, start, end, b"level") {
+        Segment::Level
+    } else if name_is(template, start, end, b"target") {
+        Segment::Target
+    } else if name_is(template, start, end, b"msg") {
+        Segment::Message
+    } else {
+        panic!("unknown placeholder in the template, expected one of {{time}}, {{thread}}, {{level}}, {{target}} and {{msg}}")
+    }
+}
+
+const fn name_is(template: &[u8], start: usize, end: usize, name: &[u8]) -> bool {
+    if end - start != name.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < name.len() {
+        if template[start + i] != name[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// A format made with [`format_template!`](crate::format_template)
+#[derive(Clone, Copy, Debug)]
+pub struct Template {
+    template: &'static str,
+    segments: &'static [Segment],
+    timestamp: Timestamp,
+    #[cfg(feature = "time")]
+    utc_offset: Option<time::UtcOffset>,
+    #[cfg(feature = "time")]
+    clock: Clock,
+    colorize: bool,
+}
+
+impl Template {
+    #[doc(hidden)]
+    pub const fn new(template: &'static str, segments: &'static [Segment]) -> Self {
+        Self {
+            template,
+            segments,
+            timestamp: Timestamp::Time,
+            #[cfg(feature = "time")]
+            utc_offset: None,
+            #[cfg(feature = "time")]
+            clock: time::OffsetDateTime::now_utc,
+            colorize: false,
+        }
+    }
+
+    /// Show `{time}` like this
+    pub fn timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Show `{time}` in the time zone `offset`, see [`Format::utc_offset`](crate::Format::utc_offset)
+    #[cfg(feature = "time")]
+    pub fn utc_offset(mut self, offset: time::UtcOffset) -> Self {
+        self.utc_offset = Some(offset);
+        self
+    }
+
+    /// Get the current time from `clock`, see [`Format::clock`](crate::Format::clock)
+    #[cfg(feature = "time")]
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Color `{msg}` by level
+    pub fn colorize(mut self, colorize: bool) -> Self {
+        self.colorize = colorize;
+        self
+    }
+
+    /// The callback to give to [`fern::Dispatch::format`]
+    pub fn callback(
+        self,
+    ) -> impl Fn(fern::FormatCallback<'_>, &std::fmt::Arguments<'_>, &log::Record<'_>) + Clone
+    {
+        #[cfg(feature = "time")]
+        let time = match self.utc_offset {
+            Some(offset) => Time::with_offset(self.timestamp, offset),
+            None => Time::new(self.timestamp),
+        }
+        .clock(self.clock);
+        #[cfg(not(feature = "time"))]
+        let time = Time::new(self.timestamp);
+        let time = Arc::new(time);
+        if self.colorize {
+            default_level_escapes(false);
+        }
+        move |out, message, record| {
+            out.finish(format_args!(
+                "{}",
+                Rendered {
+                    template: &self,
+                    time: &time,
+                    message,
+                    record,
+                }
+            ))
+        }
+    }
+}
+
+struct Rendered<'a> {
+    template: &'a Template,
+    time: &'a Time,
+    message: &'a std::fmt::Arguments<'a>,
+    record: &'a log::Record<'a>,
+}
+
+impl<'a> Display for Rendered<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for segment in self.template.segments {
+            match *segment {
+                Segment::Literal(start, end) => {
+                    f.write_str(&self.template.template[start..end])?
+                }
+                Segment::Time => write!(f, "{}", self.time)?,
+                Segment::Thread => with_thread_name(|name| f.write_str(name))?,
+                Segment::Level => write!(f, "{}", self.record.level())?,
+                Segment::Target => f.write_str(self.record.target())?,
+                Segment::Message => {
+                    let level = self.template.colorize.then(|| self.record.level());
+                    write!(
+                        f,
+                        "{}",
+                        LevelStyled(self.message, level, default_level_escapes(false))
+                    )?
+                }
+            }
+        }
+        Ok(())
+    }
+}
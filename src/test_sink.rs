@@ -0,0 +1,124 @@
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+/// Captures formatted lines so that tests can check what an application logs. Clones
+/// share the same lines, so keep one and chain the other:
+/// ```
+/// use fern_format::{Format, TestSink};
+///
+/// let sink = TestSink::new();
+/// let (_, logger) = fern::Dispatch::new()
+///     .format(Format::new().callback())
+///     .chain(sink.clone())
+///     .into_log();
+///
+/// logger.log(
+///     &log::Record::builder()
+///         .args(format_args!("hello"))
+///         .level(log::Level::Info)
+///         .target("app")
+///         .build(),
+/// );
+/// sink.assert_contains("[INFO] app: hello");
+/// ```
+#[derive(Clone, Default)]
+pub struct TestSink {
+    inner: Arc<Mutex<Captured>>,
+}
+
+#[derive(Default)]
+struct Captured {
+    lines: Vec<String>,
+    /// Written bytes that aren't a whole line yet
+    partial: Vec<u8>,
+}
+
+impl TestSink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All lines captured so far, without line endings
+    pub fn lines(&self) -> Vec<String> {
+        self.inner.lock().unwrap().lines.clone()
+    }
+
+    /// Removes and returns all lines captured so far
+    pub fn take(&self) -> Vec<String> {
+        std::mem::take(&mut self.inner.lock().unwrap().lines)
+    }
+
+    /// Forgets all lines captured so far
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().lines.clear();
+    }
+
+    /// If any captured line contains `needle`
+    pub fn contains(&self, needle: &str) -> bool {
+        self.inner
+            .lock()
+            .unwrap()
+            .lines
+            .iter()
+            .any(|line| line.contains(needle))
+    }
+
+    /// Panics with all captured lines unless one of them contains `needle`
+    #[track_caller]
+    pub fn assert_contains(&self, needle: &str) {
+        if !self.contains(needle) {
+            panic!("no line contains {:?}, got:\n{}", needle, self.dump());
+        }
+    }
+
+    /// Panics with the offending line if any captured line contains `needle`
+    #[track_caller]
+    pub fn assert_not_contains(&self, needle: &str) {
+        if let Some(line) = self.lines().iter().find(|line| line.contains(needle)) {
+            panic!("a line contains {:?}: {:?}", needle, line);
+        }
+    }
+
+    /// Panics with all captured lines unless there are exactly `count` of them
+    #[track_caller]
+    pub fn assert_line_count(&self, count: usize) {
+        let lines = self.lines().len();
+        if lines != count {
+            panic!("expected {} lines, got {}:\n{}", count, lines, self.dump());
+        }
+    }
+
+    fn dump(&self) -> String {
+        self.lines().join("\n")
+    }
+}
+
+impl Write for TestSink {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let mut captured = self.inner.lock().unwrap();
+        captured.partial.extend_from_slice(buf);
+        while let Some(i) = captured.partial.iter().position(|&b| b == b'\n') {
+            let mut line: Vec<u8> = captured.partial.drain(..=i).collect();
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            captured
+                .lines
+                .push(String::from_utf8_lossy(&line).into_owned());
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl From<TestSink> for fern::Output {
+    fn from(sink: TestSink) -> Self {
+        fern::Output::writer(Box::new(sink), "\n")
+    }
+}
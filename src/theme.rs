@@ -0,0 +1,130 @@
+use owo_colors::Style;
+
+use crate::{components, fields::KvStyles};
+
+/// All styles of a [`Format`](crate::Format) that are used when colors are enabled, so
+/// that they can be put together once and shared, e.g. between the crates of a project:
+/// ```
+/// use fern_format::{Format, Style, Theme};
+///
+/// let theme = Theme::new()
+///     .level(log::Level::Info, Style::new().green())
+///     .thread_palette([Style::new().blue(), Style::new().magenta()])
+///     .punctuation(Style::new().dimmed());
+/// let format = Format::new().uniquely_color_threads().theme(theme);
+/// ```
+///
+/// The styles that aren't set keep their defaults, which only use the 8 basic colors
+/// with [`basic_colors`](crate::Format::basic_colors).
+///
+/// With the `serde` feature it can be deserialized, e.g. from the `[theme]` table of a
+/// configuration file, with the styles written as for `Theme::from_file`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "crate::config::ThemeOptions"))]
+pub struct Theme {
+    /// In the order of [`log::Level`]
+    pub(crate) levels: [Option<Style>; 5],
+    pub(crate) thread_palette: Option<Vec<Style>>,
+    pub(crate) main_thread: Option<Style>,
+    pub(crate) time: Option<Style>,
+    pub(crate) target: Option<Style>,
+    pub(crate) punctuation: Option<Style>,
+    pub(crate) key_values: KvStyles,
+    secondary: Option<Style>,
+    correlation_id: Option<Style>,
+    dimmed: Option<Style>,
+}
+
+impl Theme {
+    /// The default styles
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Color the messages of `level` with `style`
+    pub fn level(mut self, level: log::Level, style: Style) -> Self {
+        self.levels[level as usize - 1] = Some(style);
+        self
+    }
+
+    /// Give threads these styles in the order they first log, instead of the
+    /// [`THREAD_STYLES`](components::THREAD_STYLES) built-in ones. See
+    /// [`set_thread_style_overflow`](components::set_thread_style_overflow) for the
+    /// threads after that.
+    pub fn thread_palette(mut self, palette: impl IntoIterator<Item = Style>) -> Self {
+        self.thread_palette = Some(palette.into_iter().collect());
+        self
+    }
+
+    /// The style of the main thread, see
+    /// [`Format::main_thread_style`](crate::Format::main_thread_style)
+    pub fn main_thread(mut self, style: Style) -> Self {
+        self.main_thread = Some(style);
+        self
+    }
+
+    /// The style of the time, see [`Format::style_time`](crate::Format::style_time)
+    pub fn time(mut self, style: Style) -> Self {
+        self.time = Some(style);
+        self
+    }
+
+    /// The style of targets, see [`Format::style_target`](crate::Format::style_target)
+    pub fn target(mut self, style: Style) -> Self {
+        self.target = Some(style);
+        self
+    }
+
+    /// The style of parentheses and colons, see
+    /// [`Format::style_punctuation`](crate::Format::style_punctuation)
+    pub fn punctuation(mut self, style: Style) -> Self {
+        self.punctuation = Some(style);
+        self
+    }
+
+    /// The styles of keys and values, see
+    /// [`Format::key_value_styles`](crate::Format::key_value_styles)
+    pub fn key_values(mut self, key: Style, value: Style) -> Self {
+        self.key_values = KvStyles { key, value };
+        self
+    }
+
+    /// The style of what is shown next to the records, i.e. the time since the previous
+    /// record of the target, the tick, the count, the scopes and the time in UTC. Dimmed
+    /// by default.
+    pub fn secondary(mut self, style: Style) -> Self {
+        self.secondary = Some(style);
+        self
+    }
+
+    /// The style of the correlation id, cyan by default
+    pub fn correlation_id(mut self, style: Style) -> Self {
+        self.correlation_id = Some(style);
+        self
+    }
+
+    /// The style of the records of [dimmed](crate::Format::dim_target_prefix) targets.
+    /// Dimmed by default.
+    pub fn dimmed(mut self, style: Style) -> Self {
+        self.dimmed = Some(style);
+        self
+    }
+
+    pub(crate) fn level_style(&self, level: log::Level, basic_colors: bool) -> Style {
+        self.levels[level as usize - 1]
+            .unwrap_or_else(|| components::level_style(level, basic_colors))
+    }
+
+    pub(crate) fn secondary_style(&self) -> Style {
+        self.secondary.unwrap_or_else(|| Style::new().dimmed())
+    }
+
+    pub(crate) fn correlation_id_style(&self) -> Style {
+        self.correlation_id.unwrap_or_else(|| Style::new().cyan())
+    }
+
+    pub(crate) fn dimmed_style(&self) -> Style {
+        self.dimmed.unwrap_or_else(|| Style::new().dimmed())
+    }
+}
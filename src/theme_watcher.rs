@@ -0,0 +1,78 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread::JoinHandle,
+    time::{Duration, SystemTime},
+};
+
+use crate::{report::report, Handle, Theme};
+
+/// How often the theme file is checked for changes
+const INTERVAL: Duration = Duration::from_secs(1);
+
+impl Handle {
+    /// Applies the theme in the TOML file at `path`, and again whenever the file
+    /// changes, so that the colors of a running program can be tuned. See
+    /// [`Theme::from_file`] for the file. Invalid themes are reported and the previous
+    /// one is kept.
+    /// ```no_run
+    /// use fern_format::Format;
+    ///
+    /// let (callback, handle) = Format::new().reloadable_callback();
+    /// fern::Dispatch::new()
+    ///     .format(callback)
+    ///     .chain(std::io::stdout())
+    ///     .apply()
+    ///     .unwrap();
+    /// let _watcher = handle.watch_theme("/etc/my-service/log-theme.toml");
+    /// ```
+    pub fn watch_theme(&self, path: impl Into<PathBuf>) -> ThemeWatcher {
+        let path = path.into();
+        let handle = self.clone();
+        let (stop, stopped) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("fern-format theme watcher".into())
+            .spawn(move || {
+                let mut last = None;
+                loop {
+                    let modified = modified(&path);
+                    if last != Some(modified) {
+                        last = Some(modified);
+                        match Theme::from_file(&path) {
+                            Ok(theme) => handle.modify(|format| format.theme(theme)),
+                            Err(e) => report(format_args!("Keeping the log theme: {e}")),
+                        }
+                    }
+                    match stopped.recv_timeout(INTERVAL) {
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Ok(()) | Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+            })
+            .expect("failed to spawn the theme watcher thread");
+        ThemeWatcher {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+/// Watches a theme file until it is dropped, see [`Handle::watch_theme`]
+pub struct ThemeWatcher {
+    stop: mpsc::Sender<()>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for ThemeWatcher {
+    fn drop(&mut self) {
+        let _ = self.stop.send(());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// When the file at `path` was last changed, if it can be read
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,31 @@
+use std::cell::RefCell;
+
+thread_local! {
+    static LABEL: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Shows `label` as the name of the current thread instead of the name it got from
+/// [`std::thread::Builder::name`], until it is replaced or [cleared](clear_thread_label).
+/// This is useful for pooled threads that work on one job at a time:
+/// ```
+/// fern_format::set_thread_label("conn-1234");
+/// log::info!("accepted"); // ... (conn-1234) accepted
+/// fern_format::clear_thread_label();
+/// ```
+pub fn set_thread_label(label: impl Into<String>) {
+    let label = label.into();
+    LABEL.with(|current| *current.borrow_mut() = Some(label));
+}
+
+/// Shows the name of the current thread again, see [`set_thread_label`]
+pub fn clear_thread_label() {
+    LABEL.with(|current| current.borrow_mut().take());
+}
+
+/// Calls `f` with the label of the current thread, or its name if it has no label
+pub(crate) fn with_name<R>(f: impl FnOnce(Option<&str>) -> R) -> R {
+    LABEL.with(|label| match label.try_borrow().as_deref() {
+        Ok(Some(label)) => f(Some(label)),
+        _ => f(std::thread::current().name()),
+    })
+}
@@ -0,0 +1,45 @@
+use std::{
+    fmt::Display,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use owo_colors::Style;
+
+use crate::{non_blocking::with_origin, styled::Styled};
+
+static TICK: AtomicU64 = AtomicU64::new(0);
+
+/// Sets the tick that is shown on records with [`Format::ticks`](crate::Format::ticks),
+/// e.g. the number of the frame of a game or the step of a simulation, so that the logs
+/// can be matched up with them instead of with the time
+/// ```
+/// for frame in 0..3 {
+///     fern_format::set_tick(frame);
+///     log::debug!("updating"); // ... @2 updating
+/// }
+/// ```
+pub fn set_tick(tick: u64) {
+    TICK.store(tick, Ordering::Relaxed);
+}
+
+/// The current tick, see [`set_tick`]
+pub fn tick() -> u64 {
+    TICK.load(Ordering::Relaxed)
+}
+
+/// ` @tick` of the record
+pub(crate) struct Tick {
+    pub(crate) print: bool,
+    pub(crate) style: Option<Style>,
+}
+
+impl Display for Tick {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.print {
+            return Ok(());
+        }
+
+        let tick = with_origin(|origin| origin.map(|o| o.tick)).unwrap_or_else(tick);
+        write!(f, " {}", Styled(format_args!("@{}", tick), self.style))
+    }
+}
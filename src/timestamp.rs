@@ -0,0 +1,323 @@
+#[cfg(feature = "time")]
+use std::cell::RefCell;
+use std::fmt::Display;
+
+use owo_colors::Style;
+#[cfg(feature = "time")]
+use time::{OffsetDateTime, UtcOffset};
+
+#[cfg(feature = "time")]
+use crate::{report::report, styled::Styled};
+
+/// How the time of records is shown, in the local time zone unless another offset is
+/// configured
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Timestamp {
+    /// `15:04:05.123456`
+    #[default]
+    Time,
+    /// `2024-01-02 15:04:05.123456`
+    DateTime,
+    /// `2024-01-02T15:04:05.123456789+01:00`
+    Rfc3339,
+}
+
+/// The current time, by default according to [`OffsetDateTime::now_utc`]
+#[cfg(feature = "time")]
+pub type Clock = fn() -> OffsetDateTime;
+
+/// The current time in the local time zone. Nothing is written without the `time`
+/// feature.
+pub struct Time {
+    #[cfg(feature = "time")]
+    offset: UtcOffset,
+    #[cfg(feature = "time")]
+    clock: Clock,
+    #[cfg_attr(not(feature = "time"), allow(dead_code))]
+    format: Timestamp,
+    #[cfg_attr(not(feature = "time"), allow(dead_code))]
+    weekday: bool,
+    #[cfg_attr(not(feature = "time"), allow(dead_code))]
+    also_utc: bool,
+    #[cfg_attr(not(feature = "time"), allow(dead_code))]
+    colorize: bool,
+    #[cfg_attr(not(feature = "time"), allow(dead_code))]
+    utc_style: Style,
+}
+
+impl Time {
+    #[cfg(feature = "time")]
+    pub fn new(format: Timestamp) -> Self {
+        Self::with_offset(format, local_offset())
+    }
+
+    /// Like [`new`](Self::new), but in the time zone `offset` instead of the local one.
+    /// The local offset can't be found out on some targets, such as WebAssembly.
+    #[cfg(feature = "time")]
+    pub fn with_offset(format: Timestamp, offset: UtcOffset) -> Self {
+        Self {
+            offset,
+            clock: OffsetDateTime::now_utc,
+            format,
+            weekday: false,
+            also_utc: false,
+            colorize: false,
+            utc_style: Style::new().dimmed(),
+        }
+    }
+
+    /// Get the current time from `clock` instead of the system, e.g. from JavaScript on
+    /// WebAssembly targets without a system clock
+    #[cfg(feature = "time")]
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    #[cfg(not(feature = "time"))]
+    pub fn new(format: Timestamp) -> Self {
+        Self {
+            format,
+            weekday: false,
+            also_utc: false,
+            colorize: false,
+            utc_style: Style::new().dimmed(),
+        }
+    }
+
+    /// Start the time with the abbreviated weekday, e.g. `Tue 15:04:05.123456`
+    pub fn weekday(mut self, weekday: bool) -> Self {
+        self.weekday = weekday;
+        self
+    }
+
+    /// Follow the time with the same time in UTC, without the subsecond, e.g.
+    /// `15:04:05.123456 14:04:05Z`. Nothing is added if the time is in UTC already.
+    pub fn also_utc(mut self, also_utc: bool) -> Self {
+        self.also_utc = also_utc;
+        self
+    }
+
+    /// Dim the UTC time of [`also_utc`](Self::also_utc)
+    pub fn colorize(mut self, colorize: bool) -> Self {
+        self.colorize = colorize;
+        self
+    }
+
+    /// Show the UTC time in `style` instead of dimmed, if colorized
+    pub fn utc_style(mut self, style: Style) -> Self {
+        self.utc_style = style;
+        self
+    }
+}
+
+/// The offset of the local time zone, or UTC if it can't be found out
+#[cfg(feature = "time")]
+pub(crate) fn local_offset() -> UtcOffset {
+    match UtcOffset::current_local_offset() {
+        Ok(offset) => offset,
+        Err(e) => {
+            report(format_args!("Failed to get the current UTC offset: {e:?}"));
+            UtcOffset::UTC
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+thread_local! {
+    /// The last second that was formatted on this thread
+    static CACHE: RefCell<Option<Cached>> = const { RefCell::new(None) };
+}
+
+/// Everything of a timestamp up to the subsecond, which is the same for all records
+/// logged during the same second
+#[cfg(feature = "time")]
+struct Cached {
+    second: i64,
+    offset: UtcOffset,
+    format: Timestamp,
+    weekday: bool,
+    prefix: String,
+}
+
+#[cfg(feature = "time")]
+impl Time {
+    /// Formats everything up to the subsecond into `prefix`, reusing its buffer
+    fn format_prefix(&self, now: OffsetDateTime, prefix: &mut String) {
+        const TIME_FORMAT: &[time::format_description::FormatItem<'_>] =
+            time::macros::format_description!("[hour repr:24]:[minute]:[second]");
+        const DATE_TIME_FORMAT: &[time::format_description::FormatItem<'_>] = time::macros::format_description!(
+            "[year]-[month]-[day] [hour repr:24]:[minute]:[second]"
+        );
+        const RFC3339_FORMAT: &[time::format_description::FormatItem<'_>] = time::macros::format_description!(
+            "[year]-[month]-[day]T[hour repr:24]:[minute]:[second]"
+        );
+        const WEEKDAY_FORMAT: &[time::format_description::FormatItem<'_>] =
+            time::macros::format_description!("[weekday repr:short] ");
+
+        let (format, fallback) = match self.format {
+            Timestamp::Time => (TIME_FORMAT, "??:??:??"),
+            Timestamp::DateTime => (DATE_TIME_FORMAT, "????-??-?? ??:??:??"),
+            Timestamp::Rfc3339 => (RFC3339_FORMAT, "????-??-??T??:??:??"),
+        };
+        let mut bytes = std::mem::take(prefix).into_bytes();
+        bytes.clear();
+        let weekday = if self.weekday {
+            now.format_into(&mut bytes, WEEKDAY_FORMAT)
+        } else {
+            Ok(0)
+        };
+        let formatted = weekday.and_then(|_| now.format_into(&mut bytes, format));
+        // time only writes UTF-8, so this takes the buffer back without copying it
+        *prefix = String::from_utf8(bytes).unwrap_or_default();
+        if formatted.is_err() || prefix.is_empty() {
+            prefix.clear();
+            prefix.push_str(fallback);
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+impl Time {
+    /// The time of the record that is being formatted
+    fn now(&self) -> OffsetDateTime {
+        crate::non_blocking::with_origin(|origin| origin.map(|o| o.time))
+            .map_or_else(self.clock, OffsetDateTime::from)
+    }
+
+    /// The time of the record in milliseconds since the Unix epoch
+    pub(crate) fn unix_millis(&self) -> i128 {
+        self.now().unix_timestamp_nanos() / 1_000_000
+    }
+}
+
+#[cfg(not(feature = "time"))]
+impl Time {
+    /// The time of the record in milliseconds since the Unix epoch
+    pub(crate) fn unix_millis(&self) -> i128 {
+        let now = crate::non_blocking::with_origin(|origin| origin.map(|o| o.time))
+            .unwrap_or_else(std::time::SystemTime::now);
+        match now.duration_since(std::time::UNIX_EPOCH) {
+            Ok(since) => since.as_millis() as i128,
+            Err(e) => -(e.duration().as_millis() as i128),
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+impl Display for Time {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let now = self.now().to_offset(self.offset);
+        let second = now.unix_timestamp();
+        CACHE.with_borrow_mut(|cache| {
+            let cached = cache.get_or_insert_with(|| Cached {
+                second: i64::MIN,
+                offset: self.offset,
+                format: self.format,
+                weekday: self.weekday,
+                prefix: String::new(),
+            });
+            if cached.second != second
+                || cached.offset != self.offset
+                || cached.format != self.format
+                || cached.weekday != self.weekday
+            {
+                self.format_prefix(now, &mut cached.prefix);
+                cached.second = second;
+                cached.offset = self.offset;
+                cached.format = self.format;
+                cached.weekday = self.weekday;
+            }
+            f.write_str(&cached.prefix)
+        })?;
+
+        self.write_subsecond(f, now)?;
+        if self.also_utc && !self.offset.is_utc() {
+            let style = self.colorize.then_some(self.utc_style);
+            let utc = Utc(now.to_offset(UtcOffset::UTC), self.format);
+            write!(f, " {}", Styled(utc, style))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "time")]
+impl Time {
+    fn write_subsecond(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        now: OffsetDateTime,
+    ) -> std::fmt::Result {
+        match self.format {
+            Timestamp::Time | Timestamp::DateTime => {
+                write!(f, ".{:06}", now.microsecond())
+            }
+            Timestamp::Rfc3339 => {
+                // like time, without trailing zeros
+                let mut subsecond = now.nanosecond();
+                if subsecond != 0 {
+                    let mut digits = 9;
+                    while subsecond.is_multiple_of(10) {
+                        subsecond /= 10;
+                        digits -= 1;
+                    }
+                    write!(f, ".{:01$}", subsecond, digits)?;
+                }
+                if self.offset.is_utc() {
+                    f.write_str("Z")
+                } else {
+                    let (hours, minutes, _) = self.offset.as_hms();
+                    let sign = if self.offset.is_negative() { '-' } else { '+' };
+                    write!(f, "{}{:02}:{:02}", sign, hours.abs(), minutes.abs())
+                }
+            }
+        }
+    }
+}
+
+/// A time in UTC up to the second, as `.1` but with a `Z`
+#[cfg(feature = "time")]
+struct Utc(OffsetDateTime, Timestamp);
+
+#[cfg(feature = "time")]
+impl Display for Utc {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Utc(time, format) = self;
+        let date = |f: &mut std::fmt::Formatter<'_>| {
+            write!(
+                f,
+                "{:04}-{:02}-{:02}",
+                time.year(),
+                time.month() as u8,
+                time.day()
+            )
+        };
+        match format {
+            Timestamp::Time => {}
+            Timestamp::DateTime => {
+                date(f)?;
+                f.write_str(" ")?;
+            }
+            Timestamp::Rfc3339 => {
+                date(f)?;
+                f.write_str("T")?;
+            }
+        }
+        write!(
+            f,
+            "{:02}:{:02}:{:02}Z",
+            time.hour(),
+            time.minute(),
+            time.second()
+        )
+    }
+}
+
+#[cfg(not(feature = "time"))]
+impl Display for Time {
+    fn fmt(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Ok(())
+    }
+}
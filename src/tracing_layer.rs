@@ -0,0 +1,153 @@
+use std::{fmt::Debug, io::Write, sync::Mutex};
+
+use tracing_core::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::layer::{Context, Layer};
+
+#[cfg(feature = "kv")]
+use crate::kv::OwnedValue;
+use crate::{Format, Renderer};
+
+/// A [`tracing_subscriber::Layer`] that renders events exactly like [`Format::callback`]
+/// renders records, so that the output of `log` and `tracing` looks the same:
+/// ```no_run
+/// use fern_format::{Format, FormatLayer};
+/// use tracing_subscriber::prelude::*;
+///
+/// tracing_subscriber::registry()
+///     .with(FormatLayer::new(Format::new(), std::io::stdout()))
+///     .init();
+/// ```
+///
+/// The fields of events are rendered as key-values with the `kv` feature and left out
+/// otherwise. Spans aren't shown, use [`scope`](crate::scope) for that.
+pub struct FormatLayer {
+    renderer: Renderer,
+    writer: Mutex<Box<dyn Write + Send>>,
+    line_ending: &'static str,
+}
+
+impl FormatLayer {
+    pub fn new(format: Format, writer: impl Write + Send + 'static) -> Self {
+        Self {
+            line_ending: format.get_line_ending().as_str(),
+            renderer: format.renderer(),
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+
+    fn write(&self, record: &log::Record<'_>) {
+        let _ = self.renderer.render(record, record.args(), |line| {
+            let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+            write!(writer, "{}{}", line, self.line_ending)?;
+            writer.flush()
+        });
+    }
+}
+
+impl<S: Subscriber> Layer<S> for FormatLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut fields = EventFields::default();
+        event.record(&mut fields);
+
+        let mut builder = log::Record::builder();
+        builder
+            .level(level(metadata.level()))
+            .target(fields.target.as_deref().unwrap_or(metadata.target()))
+            .module_path(fields.module_path.as_deref().or(metadata.module_path()))
+            .file(fields.file.as_deref().or(metadata.file()))
+            .line(fields.line.or(metadata.line()));
+        #[cfg(feature = "kv")]
+        builder.key_values(&fields.key_values);
+        self.write(&builder.args(format_args!("{}", fields.message)).build());
+    }
+}
+
+fn level(level: &tracing_core::Level) -> log::Level {
+    match *level {
+        tracing_core::Level::ERROR => log::Level::Error,
+        tracing_core::Level::WARN => log::Level::Warn,
+        tracing_core::Level::INFO => log::Level::Info,
+        tracing_core::Level::DEBUG => log::Level::Debug,
+        _ => log::Level::Trace,
+    }
+}
+
+/// The fields of an event. The `log.*` fields of records that were forwarded from `log`
+/// by `tracing-log` replace the metadata of the event.
+#[derive(Default)]
+struct EventFields {
+    message: String,
+    target: Option<String>,
+    module_path: Option<String>,
+    file: Option<String>,
+    line: Option<u32>,
+    #[cfg(feature = "kv")]
+    key_values: Vec<(String, OwnedValue)>,
+}
+
+impl EventFields {
+    #[cfg_attr(not(feature = "kv"), allow(unused_variables))]
+    fn record_string(&mut self, field: &Field, value: String) {
+        match field.name() {
+            "message" => self.message = value,
+            "log.target" => self.target = Some(value),
+            "log.module_path" => self.module_path = Some(value),
+            "log.file" => self.file = Some(value),
+            "log.line" => self.line = value.parse().ok(),
+            name if name.starts_with("log.") => {}
+            #[cfg(feature = "kv")]
+            name => self
+                .key_values
+                .push((name.to_string(), OwnedValue::Str(value))),
+            #[cfg(not(feature = "kv"))]
+            _ => {}
+        }
+    }
+
+    #[cfg(feature = "kv")]
+    fn record_value(&mut self, field: &Field, value: OwnedValue) {
+        match field.name() {
+            "log.line" => {
+                if let OwnedValue::U64(line) = value {
+                    self.line = u32::try_from(line).ok();
+                }
+            }
+            name if name.starts_with("log.") => {}
+            name => self.key_values.push((name.to_string(), value)),
+        }
+    }
+}
+
+impl Visit for EventFields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record_string(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.record_string(field, format!("{:?}", value));
+    }
+
+    #[cfg(feature = "kv")]
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record_value(field, OwnedValue::U64(value));
+    }
+
+    #[cfg(feature = "kv")]
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record_value(field, OwnedValue::I64(value));
+    }
+
+    #[cfg(feature = "kv")]
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record_value(field, OwnedValue::F64(value));
+    }
+
+    #[cfg(feature = "kv")]
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record_value(field, OwnedValue::Bool(value));
+    }
+}
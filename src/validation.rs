@@ -0,0 +1,77 @@
+use std::fmt::Display;
+
+use crate::Format;
+
+/// A configuration of [`Format`] that would misbehave when logging, see
+/// [`Format::validate`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FormatError {
+    /// A width of zero columns, which would hide the named part of every line
+    ZeroWidth(&'static str),
+    /// A static field with an empty key
+    EmptyFieldKey,
+    /// A static field with this key was added more than once
+    DuplicateField(String),
+    /// The correlation id was given an empty key
+    EmptyCorrelationIdKey,
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::ZeroWidth(what) => write!(f, "the {what} width is zero"),
+            FormatError::EmptyFieldKey => f.write_str("a static field has an empty key"),
+            FormatError::DuplicateField(key) => {
+                write!(f, "the static field {key:?} is added more than once")
+            }
+            FormatError::EmptyCorrelationIdKey => {
+                f.write_str("the correlation id key is empty")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FormatError {}
+
+impl Format {
+    /// Checks for combinations of options that would silently misbehave at log time
+    pub fn validate(&self) -> Result<(), FormatError> {
+        let widths = [
+            ("thread name", self.thread_name_width),
+            ("target", self.target_width),
+            ("auto", self.auto_width),
+            ("message", self.message_width),
+        ];
+        if let Some((what, _)) = widths.into_iter().find(|(_, w)| *w == Some(0)) {
+            return Err(FormatError::ZeroWidth(what));
+        }
+
+        for (i, (key, _)) in self.static_fields.iter().enumerate() {
+            if key.is_empty() {
+                return Err(FormatError::EmptyFieldKey);
+            }
+            if self.static_fields[..i].iter().any(|(k, _)| k == key) {
+                return Err(FormatError::DuplicateField(key.clone()));
+            }
+        }
+
+        if self.correlation_key.is_empty() {
+            return Err(FormatError::EmptyCorrelationIdKey);
+        }
+
+        Ok(())
+    }
+
+    /// Like [`callback`](Self::callback), but [validates](Self::validate) the
+    /// configuration first
+    pub fn try_callback(
+        self,
+    ) -> Result<
+        impl Fn(fern::FormatCallback<'_>, &std::fmt::Arguments<'_>, &log::Record<'_>) + Clone,
+        FormatError,
+    > {
+        self.validate()?;
+        Ok(self.callback())
+    }
+}
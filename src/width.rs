@@ -0,0 +1,363 @@
+//! Display widths of strings, counted in terminal columns over grapheme clusters so that
+//! emoji and combining characters are never split or miscounted.
+//!
+//! With the `unicode` feature the clusters and widths come from the tables of the
+//! unicode-segmentation and unicode-width crates. Without it, they come from a small
+//! approximation of UAX #29 and UAX #11 that covers combining marks, zero width joiner
+//! sequences, variation selectors, skin tone modifiers, flags and the common wide ranges,
+//! which is enough for padding columns in a log line.
+
+use std::{
+    borrow::Cow,
+    fmt::{Display, Write},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(not(feature = "unicode"))]
+use approximate::{grapheme_width, graphemes};
+#[cfg(feature = "unicode")]
+use tables::{grapheme_width, graphemes};
+
+const ELLIPSIS: char = '…';
+
+/// The number of terminal columns `s` occupies
+pub(crate) fn width(s: &str) -> usize {
+    graphemes(s).map(grapheme_width).sum()
+}
+
+/// Pads `s` with spaces or truncates it with an ellipsis so that it is exactly `width`
+/// columns wide
+pub(crate) fn fit(s: &str, width: usize) -> Fit<'_> {
+    Fit { s, width }
+}
+
+/// See [`fit`]
+pub(crate) struct Fit<'a> {
+    s: &'a str,
+    width: usize,
+}
+
+impl<'a> Display for Fit<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let current = self::width(self.s);
+        if current > self.width {
+            write_truncated(f, self.s, self.width)
+        } else {
+            f.write_str(self.s)?;
+            write!(f, "{:1$}", "", self.width - current)
+        }
+    }
+}
+
+/// A column that widens to the widest value it has shown, up to a maximum
+pub(crate) struct AutoWidth {
+    widest: AtomicUsize,
+    max: usize,
+}
+
+impl AutoWidth {
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            widest: AtomicUsize::new(0),
+            max,
+        }
+    }
+
+    /// Widens the column to fit `s`, and returns the width to show it in
+    pub(crate) fn widen(&self, s: &str) -> usize {
+        let width = width(s).min(self.max);
+        self.widest.fetch_max(width, Ordering::Relaxed).max(width)
+    }
+}
+
+/// Truncates `s` with an ellipsis if it is wider than `width` columns
+pub(crate) fn truncate(s: &str, width: usize) -> Cow<'_, str> {
+    if self::width(s) <= width {
+        return Cow::Borrowed(s);
+    }
+
+    let mut out = String::with_capacity(s.len().min(width * 4));
+    // writing to a String can't fail
+    let _ = write_truncated(&mut out, s, width);
+    Cow::Owned(out)
+}
+
+/// Writes the start of `s` and an ellipsis, padded to exactly `width` columns
+fn write_truncated(out: &mut impl Write, s: &str, width: usize) -> std::fmt::Result {
+    let mut used = 0;
+    for g in graphemes(s) {
+        let w = grapheme_width(g);
+        if used + w + 1 > width {
+            break;
+        }
+        used += w;
+        out.write_str(g)?;
+    }
+    if width > 0 {
+        out.write_char(ELLIPSIS)?;
+        used += 1;
+    }
+    // a wide grapheme might not have fit exactly
+    write!(out, "{:1$}", "", width.saturating_sub(used))
+}
+
+/// Grapheme clusters and their widths from the Unicode tables
+#[cfg(feature = "unicode")]
+mod tables {
+    use unicode_segmentation::UnicodeSegmentation;
+    use unicode_width::UnicodeWidthStr;
+
+    /// Iterates the grapheme clusters of `s`
+    pub(super) fn graphemes(s: &str) -> impl Iterator<Item = &str> {
+        s.graphemes(true)
+    }
+
+    pub(super) fn grapheme_width(g: &str) -> usize {
+        if g.starts_with(char::is_control) {
+            0
+        } else {
+            g.width()
+        }
+    }
+}
+
+/// An approximation of the Unicode tables, see the module documentation
+#[cfg(not(feature = "unicode"))]
+mod approximate {
+    const ZWJ: char = '\u{200D}';
+
+    /// Iterates the grapheme clusters of `s`
+    pub(super) fn graphemes(s: &str) -> impl Iterator<Item = &str> {
+        let mut rest = s;
+        std::iter::from_fn(move || {
+            let mut chars = rest.char_indices();
+            let (_, first) = chars.next()?;
+            let mut end = first.len_utf8();
+            let mut prev = first;
+            let mut regional_pair = is_regional_indicator(first);
+
+            for (i, c) in chars {
+                let joins = is_extend(c)
+                    || prev == ZWJ
+                    || (regional_pair && is_regional_indicator(c));
+                if !joins {
+                    break;
+                }
+                regional_pair = false;
+                end = i + c.len_utf8();
+                prev = c;
+            }
+
+            let (cluster, tail) = rest.split_at(end);
+            rest = tail;
+            Some(cluster)
+        })
+    }
+
+    pub(super) fn grapheme_width(g: &str) -> usize {
+        let mut chars = g.chars();
+        let Some(first) = chars.next() else {
+            return 0;
+        };
+        if first.is_control() || is_extend(first) {
+            0
+        } else if is_wide(first) || chars.any(|c| c == '\u{FE0F}') {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn is_regional_indicator(c: char) -> bool {
+        matches!(c, '\u{1F1E6}'..='\u{1F1FF}')
+    }
+
+    /// Characters that never start a grapheme cluster of their own
+    fn is_extend(c: char) -> bool {
+        matches!(c,
+            '\u{0300}'..='\u{036F}'
+            | '\u{0483}'..='\u{0489}'
+            | '\u{0591}'..='\u{05BD}'
+            | '\u{05BF}'
+            | '\u{05C1}'..='\u{05C2}'
+            | '\u{05C4}'..='\u{05C5}'
+            | '\u{05C7}'
+            | '\u{0610}'..='\u{061A}'
+            | '\u{064B}'..='\u{065F}'
+            | '\u{0670}'
+            | '\u{06D6}'..='\u{06DC}'
+            | '\u{06DF}'..='\u{06E4}'
+            | '\u{06E7}'..='\u{06E8}'
+            | '\u{06EA}'..='\u{06ED}'
+            | '\u{0900}'..='\u{0903}'
+            | '\u{093A}'..='\u{094F}'
+            | '\u{0951}'..='\u{0957}'
+            | '\u{0962}'..='\u{0963}'
+            | '\u{0E31}'
+            | '\u{0E34}'..='\u{0E3A}'
+            | '\u{0E47}'..='\u{0E4E}'
+            | '\u{1AB0}'..='\u{1AFF}'
+            | '\u{1DC0}'..='\u{1DFF}'
+            | '\u{200C}'..='\u{200D}'
+            | '\u{20D0}'..='\u{20FF}'
+            | '\u{302A}'..='\u{302F}'
+            | '\u{3099}'..='\u{309A}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FE20}'..='\u{FE2F}'
+            | '\u{1F3FB}'..='\u{1F3FF}'
+            | '\u{E0020}'..='\u{E007F}'
+            | '\u{E0100}'..='\u{E01EF}'
+        )
+    }
+
+    /// East Asian wide and fullwidth characters, and emoji that are presented as such by
+    /// default
+    fn is_wide(c: char) -> bool {
+        matches!(c,
+            '\u{1100}'..='\u{115F}'
+            | '\u{231A}'..='\u{231B}'
+            | '\u{23E9}'..='\u{23EC}'
+            | '\u{23F0}'
+            | '\u{23F3}'
+            | '\u{25FD}'..='\u{25FE}'
+            | '\u{2614}'..='\u{2615}'
+            | '\u{2648}'..='\u{2653}'
+            | '\u{267F}'
+            | '\u{2693}'
+            | '\u{26A1}'
+            | '\u{26AA}'..='\u{26AB}'
+            | '\u{26BD}'..='\u{26BE}'
+            | '\u{26C4}'..='\u{26C5}'
+            | '\u{26CE}'
+            | '\u{26D4}'
+            | '\u{26EA}'
+            | '\u{26F2}'..='\u{26F3}'
+            | '\u{26F5}'
+            | '\u{26FA}'
+            | '\u{26FD}'
+            | '\u{2705}'
+            | '\u{270A}'..='\u{270B}'
+            | '\u{2728}'
+            | '\u{274C}'
+            | '\u{274E}'
+            | '\u{2753}'..='\u{2755}'
+            | '\u{2757}'
+            | '\u{2795}'..='\u{2797}'
+            | '\u{27B0}'
+            | '\u{27BF}'
+            | '\u{2B1B}'..='\u{2B1C}'
+            | '\u{2B50}'
+            | '\u{2B55}'
+            | '\u{2E80}'..='\u{303E}'
+            | '\u{3041}'..='\u{33FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{A000}'..='\u{A4CF}'
+            | '\u{A960}'..='\u{A97F}'
+            | '\u{AC00}'..='\u{D7A3}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FE10}'..='\u{FE19}'
+            | '\u{FE30}'..='\u{FE6F}'
+            | '\u{FF00}'..='\u{FF60}'
+            | '\u{FFE0}'..='\u{FFE6}'
+            | '\u{1F004}'
+            | '\u{1F0CF}'
+            | '\u{1F18E}'
+            | '\u{1F191}'..='\u{1F19A}'
+            | '\u{1F1E6}'..='\u{1F1FF}'
+            | '\u{1F200}'..='\u{1F251}'
+            | '\u{1F300}'..='\u{1F320}'
+            | '\u{1F32D}'..='\u{1F335}'
+            | '\u{1F337}'..='\u{1F37C}'
+            | '\u{1F37E}'..='\u{1F393}'
+            | '\u{1F3A0}'..='\u{1F3CA}'
+            | '\u{1F3CF}'..='\u{1F3D3}'
+            | '\u{1F3E0}'..='\u{1F3F0}'
+            | '\u{1F3F4}'
+            | '\u{1F3F8}'..='\u{1F43E}'
+            | '\u{1F440}'
+            | '\u{1F442}'..='\u{1F4FC}'
+            | '\u{1F4FF}'..='\u{1F53D}'
+            | '\u{1F54B}'..='\u{1F54E}'
+            | '\u{1F550}'..='\u{1F567}'
+            | '\u{1F57A}'
+            | '\u{1F595}'..='\u{1F596}'
+            | '\u{1F5A4}'
+            | '\u{1F5FB}'..='\u{1F64F}'
+            | '\u{1F680}'..='\u{1F6C5}'
+            | '\u{1F6CC}'
+            | '\u{1F6D0}'..='\u{1F6D2}'
+            | '\u{1F6D5}'..='\u{1F6D7}'
+            | '\u{1F6EB}'..='\u{1F6EC}'
+            | '\u{1F6F4}'..='\u{1F6FC}'
+            | '\u{1F7E0}'..='\u{1F7EB}'
+            | '\u{1F90C}'..='\u{1F93A}'
+            | '\u{1F93C}'..='\u{1F945}'
+            | '\u{1F947}'..='\u{1F9FF}'
+            | '\u{1FA70}'..='\u{1FAFF}'
+            | '\u{20000}'..='\u{2FFFD}'
+            | '\u{30000}'..='\u{3FFFD}'
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cjk_is_wide() {
+        assert_eq!(width("日本語"), 6);
+        assert_eq!(width("한글"), 4);
+        assert_eq!(width("ｆｕｌｌ"), 8);
+        assert_eq!(width("ascii"), 5);
+    }
+
+    #[test]
+    fn combining_marks_join_their_base() {
+        let s = "e\u{301}a\u{308}";
+        assert_eq!(graphemes(s).collect::<Vec<_>>(), ["e\u{301}", "a\u{308}"]);
+        assert_eq!(width(s), 2);
+    }
+
+    #[test]
+    fn emoji_sequences_are_one_grapheme() {
+        let family = "👨\u{200D}👩\u{200D}👧";
+        let thumbs = "👍🏽";
+        let heart = "❤\u{FE0F}";
+        let flags = "🇸🇪🇯🇵";
+        assert_eq!(graphemes(family).count(), 1);
+        assert_eq!(width(family), 2);
+        assert_eq!(graphemes(thumbs).count(), 1);
+        assert_eq!(width(thumbs), 2);
+        assert_eq!(width(heart), 2);
+        assert_eq!(graphemes(flags).collect::<Vec<_>>(), ["🇸🇪", "🇯🇵"]);
+        assert_eq!(width(flags), 4);
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn conjoining_jamo_are_one_wide_grapheme() {
+        let syllable = "\u{1100}\u{1161}\u{11A8}";
+        assert_eq!(graphemes(syllable).count(), 1);
+        assert_eq!(width(syllable), 2);
+    }
+
+    #[test]
+    fn truncation_keeps_graphemes_whole() {
+        assert_eq!(truncate("日本語", 4), "日… ");
+        assert_eq!(truncate("e\u{301}e\u{301}e\u{301}", 2), "e\u{301}…");
+        assert_eq!(
+            truncate("👨\u{200D}👩\u{200D}👧 hi", 3),
+            "👨\u{200D}👩\u{200D}👧…"
+        );
+        assert_eq!(truncate("short", 5), "short");
+    }
+
+    #[test]
+    fn fit_pads_to_the_width() {
+        assert_eq!(fit("日本", 6).to_string(), "日本  ");
+        assert_eq!(fit("a\u{308}", 3).to_string(), "a\u{308}  ");
+        assert_eq!(fit("abcdef", 4).to_string(), "abc…");
+    }
+}
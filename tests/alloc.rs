@@ -0,0 +1,115 @@
+//! Formatting a record must not allocate once the thread-local caches are warm
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use fern_format::Format;
+
+struct Counting;
+
+thread_local! {
+    /// Per thread, so that the allocations of other threads aren't counted
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn count() {
+    // the thread-local is gone while the thread exits
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn logger(format: Format) -> Box<dyn log::Log> {
+    let (_, logger) = fern::Dispatch::new()
+        .format(format.callback())
+        .chain(fern::Output::writer(Box::new(std::io::sink()), "\n"))
+        .into_log();
+    logger
+}
+
+/// The number of allocations made while logging `record` a few times
+fn allocations(logger: &dyn log::Log, record: &log::Record<'_>) -> usize {
+    // the first record fills the caches
+    logger.log(record);
+
+    let before = ALLOCATIONS.with(Cell::get);
+    for _ in 0..10 {
+        logger.log(record);
+    }
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+#[test]
+fn formatting_does_not_allocate() {
+    let formats = [
+        ("default", Format::new()),
+        (
+            "threads and colors",
+            Format::new()
+                .force_colors()
+                .thread_names()
+                .uniquely_color_threads()
+                .thread_name_width(8)
+                .target_width(10),
+        ),
+        (
+            "static field",
+            Format::new().static_field("service", "api gateway"),
+        ),
+        (
+            "cloudwatch",
+            Format::cloudwatch()
+                .thread_names()
+                .static_field("service", "api"),
+        ),
+        #[cfg(feature = "kv")]
+        ("kv pairs", Format::new().key_values_as_json_if(false)),
+        #[cfg(feature = "kv")]
+        (
+            "json",
+            Format::new()
+                .static_field("service", "api")
+                .key_values_as_json(),
+        ),
+    ];
+
+    let _context = fern_format::context::scoped("request", 1234);
+    let _scope = fern_format::scope("handling");
+    #[cfg(feature = "kv")]
+    let key_values: &[(&str, &str)] = &[
+        ("user", "alice smith"),
+        ("path", "/index.html"),
+        ("query", "q=\"a b\""),
+    ];
+
+    for (name, format) in formats {
+        let logger = logger(format);
+        let mut record = log::Record::builder();
+        record.level(log::Level::Info).target("app::server");
+        #[cfg(feature = "kv")]
+        record.key_values(&key_values);
+
+        let allocations =
+            allocations(&*logger, &record.args(format_args!("hello {}", 42)).build());
+        assert_eq!(allocations, 0, "{name} allocated");
+    }
+}
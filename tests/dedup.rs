@@ -0,0 +1,145 @@
+use std::{
+    sync::{mpsc, Arc, Mutex, Weak},
+    thread,
+    time::Duration,
+};
+
+use fern_format::{Dedup, Format, TestSink};
+use log::Log;
+
+fn deduplicated(sink: &TestSink) -> Dedup<Box<dyn Log>> {
+    let (_, output) = fern::Dispatch::new()
+        .format(Format::new().hide_time().callback())
+        .chain(sink.clone())
+        .into_log();
+    Dedup::new(output)
+}
+
+fn log(logger: &dyn Log, level: log::Level, message: &str) {
+    logger.log(
+        &log::Record::builder()
+            .args(format_args!("{message}"))
+            .level(level)
+            .target("app")
+            .build(),
+    );
+}
+
+#[test]
+fn flushing_logs_the_summary() {
+    let sink = TestSink::new();
+    let dedup = deduplicated(&sink);
+    for _ in 0..4 {
+        log(&dedup, log::Level::Warn, "disk almost full");
+    }
+    assert_eq!(sink.take(), ["[WARN] app: disk almost full"]);
+    dedup.flush();
+    assert_eq!(sink.take(), ["[WARN] app: last message repeated 3 times"]);
+    dedup.flush();
+    assert_eq!(sink.take(), [] as [String; 0]);
+}
+
+#[test]
+fn a_different_record_logs_the_summary() {
+    let sink = TestSink::new();
+    let dedup = deduplicated(&sink);
+    log(&dedup, log::Level::Info, "retrying");
+    log(&dedup, log::Level::Info, "retrying");
+    log(&dedup, log::Level::Warn, "retrying");
+    log(&dedup, log::Level::Warn, "gave up");
+    assert_eq!(
+        sink.take(),
+        [
+            "[INFO] app: retrying",
+            "[INFO] app: last message repeated 1 times",
+            "[WARN] app: retrying",
+            "[WARN] app: gave up",
+        ]
+    );
+}
+
+#[test]
+fn logs_the_summary_after_max_repeats() {
+    let sink = TestSink::new();
+    let dedup = deduplicated(&sink).max_repeats(2);
+    for _ in 0..6 {
+        log(&dedup, log::Level::Info, "tick");
+    }
+    assert_eq!(
+        sink.take(),
+        [
+            "[INFO] app: tick",
+            "[INFO] app: last message repeated 2 times",
+            "[INFO] app: last message repeated 2 times",
+        ]
+    );
+    dedup.flush();
+    assert_eq!(sink.take(), ["[INFO] app: last message repeated 1 times"]);
+}
+
+#[test]
+fn logs_the_summary_after_max_delay() {
+    let sink = TestSink::new();
+    let dedup = deduplicated(&sink).max_delay(Duration::from_millis(50));
+    log(&dedup, log::Level::Info, "tick");
+    log(&dedup, log::Level::Info, "tick");
+    thread::sleep(Duration::from_millis(60));
+    log(&dedup, log::Level::Info, "tick");
+    assert_eq!(
+        sink.take(),
+        [
+            "[INFO] app: tick",
+            "[INFO] app: last message repeated 2 times"
+        ]
+    );
+}
+
+/// Flushes the `Dedup` in front of it whenever it logs, like an output that logs about
+/// itself would
+struct Reentrant {
+    dedup: Weak<Dedup<Reentrant>>,
+    messages: Arc<Mutex<Vec<String>>>,
+}
+
+impl Log for Reentrant {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        self.messages
+            .lock()
+            .unwrap()
+            .push(record.args().to_string());
+        if let Some(dedup) = self.dedup.upgrade() {
+            dedup.flush();
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn the_inner_logger_can_use_the_dedup() {
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let dedup = Arc::new_cyclic(|dedup| {
+        Dedup::new(Reentrant {
+            dedup: dedup.clone(),
+            messages: Arc::clone(&messages),
+        })
+    });
+    let (done, finished) = mpsc::channel();
+    thread::spawn(move || {
+        log(&*dedup, log::Level::Info, "retrying");
+        log(&*dedup, log::Level::Info, "retrying");
+        log(&*dedup, log::Level::Info, "gave up");
+        done.send(()).unwrap();
+    });
+    finished
+        .recv_timeout(Duration::from_secs(10))
+        .expect("the inner logger deadlocked");
+    assert_eq!(
+        *messages.lock().unwrap(),
+        ["retrying", "last message repeated 1 times", "gave up"]
+    );
+}
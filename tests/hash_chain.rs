@@ -0,0 +1,140 @@
+use std::{
+    sync::{mpsc, Arc, Mutex, Weak},
+    thread,
+    time::Duration,
+};
+
+use fern_format::{verify_hash_chain, Format, HashChain, HashChainError, TestSink};
+use log::Log;
+
+/// A logger that formats records and chains them with `chain`, which writes to `sink`
+fn chained(
+    chain: impl FnOnce(Box<dyn Log>) -> HashChain<Box<dyn Log>>,
+    sink: &TestSink,
+) -> Box<dyn Log> {
+    let (_, inner) = fern::Dispatch::new().chain(sink.clone()).into_log();
+    fern::Dispatch::new()
+        .format(Format::new().hide_time().callback())
+        .chain(Box::new(chain(inner)) as Box<dyn Log>)
+        .into_log()
+        .1
+}
+
+fn log(logger: &dyn Log, message: &str) {
+    logger.log(
+        &log::Record::builder()
+            .args(format_args!("{message}"))
+            .level(log::Level::Info)
+            .target("audit")
+            .build(),
+    );
+}
+
+fn verify(lines: &[String]) -> Result<String, HashChainError> {
+    verify_hash_chain(lines.join("\n").as_bytes())
+}
+
+/// An audit log of a login, a record over two lines and a logout
+fn audit_log() -> Vec<String> {
+    let sink = TestSink::new();
+    let logger = chained(HashChain::new, &sink);
+    log(&*logger, "login");
+    log(&*logger, "granted\nadmin");
+    log(&*logger, "logout");
+    sink.take()
+}
+
+#[test]
+fn verifies_an_unbroken_chain() {
+    let lines = audit_log();
+    assert_eq!(lines.len(), 4);
+    let last = verify(&lines).unwrap();
+    assert!(lines[3].ends_with(&format!(" #{last}")));
+    assert!(!lines[1].contains(" #"));
+}
+
+#[test]
+fn finds_changed_records() {
+    let mut lines = audit_log();
+    lines[2] = lines[2].replace("admin", "root");
+    assert!(matches!(verify(&lines), Err(HashChainError::Mismatch(3))));
+}
+
+#[test]
+fn finds_removed_and_inserted_records() {
+    let mut removed = audit_log();
+    removed.remove(0);
+    assert!(matches!(verify(&removed), Err(HashChainError::Mismatch(2))));
+
+    let mut inserted = audit_log();
+    let copy = inserted[0].clone();
+    inserted.insert(1, copy);
+    assert!(matches!(
+        verify(&inserted),
+        Err(HashChainError::Mismatch(2))
+    ));
+}
+
+#[test]
+fn finds_records_without_a_hash() {
+    let mut lines = audit_log();
+    lines.push("[INFO] audit: appended".to_string());
+    assert!(matches!(verify(&lines), Err(HashChainError::Unfinished(5))));
+}
+
+#[test]
+fn continues_the_chain_of_a_file() {
+    let mut lines = audit_log();
+    let sink = TestSink::new();
+    let last = lines.last().unwrap().clone();
+    let logger = chained(|inner| HashChain::new(inner).after(&last), &sink);
+    log(&*logger, "login again");
+    lines.extend(sink.take());
+    assert!(verify(&lines).is_ok());
+}
+
+/// Logs a record of its own into the `HashChain` in front of it, like an output that
+/// logs about itself would, and keeps the lines it is given
+struct Reentrant {
+    chain: Weak<HashChain<Reentrant>>,
+    lines: Arc<Mutex<Vec<String>>>,
+}
+
+impl Log for Reentrant {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let line = record.args().to_string();
+        let first = !line.starts_with("rotated");
+        self.lines.lock().unwrap().push(line);
+        if let Some(chain) = self.chain.upgrade().filter(|_| first) {
+            log(&*chain, "rotated");
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn the_inner_logger_can_log_into_the_chain() {
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let chain = Arc::new_cyclic(|chain| {
+        HashChain::new(Reentrant {
+            chain: chain.clone(),
+            lines: Arc::clone(&lines),
+        })
+    });
+    let (done, finished) = mpsc::channel();
+    thread::spawn(move || {
+        log(&*chain, "login");
+        done.send(()).unwrap();
+    });
+    finished
+        .recv_timeout(Duration::from_secs(10))
+        .expect("the inner logger deadlocked");
+    let lines = lines.lock().unwrap();
+    assert!(lines[0].starts_with("login #") && lines[1].starts_with("rotated #"));
+    assert!(verify(&lines).is_ok());
+}
@@ -0,0 +1,87 @@
+//! Changing the format from another thread must keep the local time zone of when the
+//! format was created, since it can't be looked up anymore on some platforms once there
+//! are several threads. Without the test harness, so that the time zone can be changed
+//! while there is only the main thread.
+
+#[cfg(feature = "toml")]
+use fern_format::Handle;
+use fern_format::{Format, TestSink, Timestamp};
+
+fn main() {
+    // 3 hours east of UTC
+    set_time_zone("TST-3");
+
+    let sink = TestSink::new();
+    let (callback, handle) = Format::new()
+        .timestamp(Timestamp::Rfc3339)
+        .reloadable_callback();
+    let (_, logger) = fern::Dispatch::new()
+        .format(callback)
+        .chain(sink.clone())
+        .into_log();
+
+    log(&*logger, "before");
+    sink.assert_contains("+03:00 [INFO] app: before");
+
+    // a format that looks up the time zone again would now be 5 hours east of UTC
+    set_time_zone("TST-5");
+
+    let modifier = handle.clone();
+    std::thread::spawn(move || modifier.modify(|format| format.thread_names()))
+        .join()
+        .unwrap();
+    log(&*logger, "modified");
+    sink.assert_contains("+03:00 (main) [INFO] app: modified");
+
+    #[cfg(feature = "toml")]
+    reload_theme(&handle, &sink, &*logger);
+}
+
+/// Changes the theme with a file, which the watcher applies from its own thread
+#[cfg(feature = "toml")]
+fn reload_theme(handle: &Handle, sink: &TestSink, logger: &dyn log::Log) {
+    let path = std::env::temp_dir()
+        .join(format!("fern-format-theme-{}.toml", std::process::id()));
+    std::fs::write(&path, "info = \"italic\"\n").unwrap();
+    handle.modify(|format| format.force_colors());
+    let _watcher = handle.watch_theme(&path);
+
+    for _ in 0..100 {
+        sink.clear();
+        log(logger, "reloaded");
+        // italic
+        if sink.contains("\x1b[3m") {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    std::fs::remove_file(&path).unwrap();
+    sink.assert_contains("\x1b[3mreloaded");
+    sink.assert_contains("+03:00");
+}
+
+fn log(logger: &dyn log::Log, message: &str) {
+    logger.log(
+        &log::Record::builder()
+            .args(format_args!("{message}"))
+            .level(log::Level::Info)
+            .target("app")
+            .build(),
+    );
+}
+
+#[cfg(unix)]
+extern "C" {
+    fn tzset();
+}
+
+/// Sets `TZ` while there are no other threads that could read it, and makes the C
+/// library read it again, which it otherwise only does once
+fn set_time_zone(tz: &str) {
+    std::env::set_var("TZ", tz);
+    #[cfg(unix)]
+    // SAFETY: there is only the main thread
+    unsafe {
+        tzset()
+    };
+}
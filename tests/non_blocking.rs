@@ -0,0 +1,21 @@
+use fern_format::{Format, NonBlocking, TestSink};
+use log::Log;
+
+#[test]
+fn records_are_timed_with_the_clock_of_the_format() {
+    let sink = TestSink::new();
+    let (logger, guard) =
+        NonBlocking::new(Format::new().deterministic_for_tests(), sink.clone()).start();
+    logger.log(
+        &log::Record::builder()
+            .args(format_args!("hello"))
+            .level(log::Level::Info)
+            .target("app")
+            .build(),
+    );
+    drop(guard);
+    #[cfg(feature = "time")]
+    assert_eq!(sink.lines(), ["00:00:00.000000 [INFO] app: hello"]);
+    #[cfg(not(feature = "time"))]
+    assert_eq!(sink.lines(), ["[INFO] app: hello"]);
+}
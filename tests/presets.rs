@@ -0,0 +1,48 @@
+use std::path::PathBuf;
+
+use fern_format::{Format, RotatingFile};
+
+fn temp_file(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("fern-format-{}-{name}.log", std::process::id()))
+}
+
+fn log(logger: &dyn log::Log) {
+    logger.log(
+        &log::Record::builder()
+            .args(format_args!("hello"))
+            .level(log::Level::Info)
+            .target("app")
+            .build(),
+    );
+    logger.flush();
+}
+
+#[test]
+fn files_keep_the_time_and_date_when_the_terminal_hides_them() {
+    let path = temp_file("terminal");
+    let (_, logger) = Format::new()
+        .deterministic_for_tests()
+        .hide_time()
+        .terminal_and_file(&path)
+        .unwrap()
+        .into_log();
+    log(&*logger);
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written, "1970-01-01 00:00:00.000000 [INFO] app: hello\n");
+}
+
+#[test]
+fn rotating_files_keep_the_time_and_date_when_the_format_hides_them() {
+    let path = temp_file("rotating");
+    let file = RotatingFile::new(&path).unwrap();
+    let (_, logger) = Format::new()
+        .deterministic_for_tests()
+        .hide_time()
+        .rotating_file(file)
+        .into_log();
+    log(&*logger);
+    let written = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(written, "1970-01-01 00:00:00.000000 [INFO] app: hello\n");
+}
@@ -0,0 +1,143 @@
+use std::{
+    sync::{mpsc, Arc, Mutex, Weak},
+    thread,
+    time::Duration,
+};
+
+use fern_format::{Format, RateLimit, TestSink};
+use log::Log;
+
+fn limited(sink: &TestSink, per_second: u64) -> RateLimit<Box<dyn Log>> {
+    let (_, output) = fern::Dispatch::new()
+        .format(Format::new().hide_time().callback())
+        .chain(sink.clone())
+        .into_log();
+    RateLimit::new(output, per_second)
+}
+
+fn log(logger: &dyn Log, target: &str, n: u32) {
+    logger.log(
+        &log::Record::builder()
+            .args(format_args!("record {n}"))
+            .level(log::Level::Info)
+            .target(target)
+            .build(),
+    );
+}
+
+#[test]
+fn drops_records_over_the_limit_until_the_second_is_over() {
+    let sink = TestSink::new();
+    let limit = limited(&sink, 2);
+    for n in 0..5 {
+        log(&limit, "app", n);
+    }
+    assert_eq!(
+        sink.take(),
+        ["[INFO] app: record 0", "[INFO] app: record 1"]
+    );
+
+    thread::sleep(Duration::from_millis(1100));
+    log(&limit, "app", 5);
+    assert_eq!(
+        sink.take(),
+        [
+            "[WARN] app: suppressed 3 records from app",
+            "[INFO] app: record 5"
+        ]
+    );
+}
+
+#[test]
+fn summarizes_the_dropped_records_of_each_target_on_flush() {
+    let sink = TestSink::new();
+    let limit = limited(&sink, 1);
+    log(&limit, "a", 0);
+    log(&limit, "b", 1);
+    log(&limit, "a", 2);
+    log(&limit, "b", 3);
+    log(&limit, "c", 4);
+    limit.flush();
+    assert_eq!(
+        sink.take(),
+        [
+            "[INFO] a: record 0",
+            "[WARN] a: suppressed 1 records from a",
+            "[WARN] b: suppressed 2 records from b",
+            "[WARN] c: suppressed 1 records from c",
+        ]
+    );
+    limit.flush();
+    assert_eq!(sink.take(), [] as [String; 0]);
+}
+
+#[test]
+fn counts_targets_on_their_own() {
+    let sink = TestSink::new();
+    let limit = limited(&sink, 1).per_target();
+    log(&limit, "a", 0);
+    log(&limit, "a", 1);
+    log(&limit, "b", 2);
+    limit.flush();
+    assert_eq!(
+        sink.take(),
+        [
+            "[INFO] a: record 0",
+            "[INFO] b: record 2",
+            "[WARN] a: suppressed 1 records from a",
+        ]
+    );
+}
+
+/// Flushes the `RateLimit` in front of it whenever it logs, like an output that logs
+/// about itself would
+struct Reentrant {
+    limit: Weak<RateLimit<Reentrant>>,
+    messages: Arc<Mutex<Vec<String>>>,
+}
+
+impl Log for Reentrant {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        self.messages
+            .lock()
+            .unwrap()
+            .push(record.args().to_string());
+        if let Some(limit) = self.limit.upgrade() {
+            limit.flush();
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn the_inner_logger_can_use_the_limit() {
+    let messages = Arc::new(Mutex::new(Vec::new()));
+    let limit = Arc::new_cyclic(|limit| {
+        RateLimit::new(
+            Reentrant {
+                limit: limit.clone(),
+                messages: Arc::clone(&messages),
+            },
+            1,
+        )
+    });
+    let (done, finished) = mpsc::channel();
+    thread::spawn(move || {
+        log(&*limit, "app", 0);
+        log(&*limit, "app", 1);
+        limit.flush();
+        done.send(()).unwrap();
+    });
+    finished
+        .recv_timeout(Duration::from_secs(10))
+        .expect("the inner logger deadlocked");
+    assert_eq!(
+        *messages.lock().unwrap(),
+        ["record 0", "suppressed 1 records from app"]
+    );
+}
@@ -0,0 +1,45 @@
+use std::{
+    fmt,
+    sync::{mpsc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use fern_format::{set_error_handler, Format, Logger, TestSink};
+
+static REPORTED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Logs a record of its own when it is displayed
+struct Chatty;
+
+impl fmt::Display for Chatty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        log::warn!(target: "inner", "displaying");
+        f.write_str("chatty")
+    }
+}
+
+#[test]
+fn records_logged_while_formatting_another_one_are_reported() {
+    set_error_handler(|message| REPORTED.lock().unwrap().push(message.to_string()));
+    let sink = TestSink::new();
+    Logger::new(Format::new().hide_time(), sink.clone())
+        .init()
+        .unwrap();
+
+    let (done, finished) = mpsc::channel();
+    thread::spawn(move || {
+        log::info!(target: "outer", value:% = Chatty; "hello");
+        done.send(()).unwrap();
+    });
+    finished
+        .recv_timeout(Duration::from_secs(10))
+        .expect("logging from a key-value deadlocked");
+
+    assert_eq!(sink.take(), ["[INFO] outer: hello value=chatty"]);
+    let reported = REPORTED.lock().unwrap();
+    assert!(!reported.is_empty());
+    assert!(reported
+        .iter()
+        .all(|line| line == "[WARN] inner: displaying"));
+}
@@ -0,0 +1,75 @@
+use fern_format::{Format, Sample, TestSink};
+use log::Log;
+
+fn sampled(sink: &TestSink) -> Sample<Box<dyn Log>> {
+    let (_, output) = fern::Dispatch::new()
+        .format(Format::new().hide_time().callback())
+        .chain(sink.clone())
+        .into_log();
+    Sample::new(output)
+}
+
+fn log(logger: &dyn Log, target: &str, n: u32) {
+    logger.log(
+        &log::Record::builder()
+            .args(format_args!("{n}"))
+            .level(log::Level::Debug)
+            .target(target)
+            .build(),
+    );
+}
+
+/// The numbers of the records that `sample` lets through out of `count`
+fn let_through(rate: f64, count: u32) -> Vec<u32> {
+    let sink = TestSink::new();
+    let sample = sampled(&sink).sample_target("db", rate);
+    for n in 0..count {
+        log(&sample, "db::query", n);
+    }
+    sink.lines()
+        .iter()
+        .map(|line| {
+            let message = line.strip_prefix("[DEBUG] db::query: ").unwrap();
+            message.split(' ').next().unwrap().parse().unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn picks_records_evenly() {
+    assert_eq!(let_through(0.25, 12), [0, 4, 8]);
+    assert_eq!(let_through(1.0, 3), [0, 1, 2]);
+    assert_eq!(let_through(0.0, 3), []);
+}
+
+#[test]
+fn lets_the_same_fraction_through_every_time() {
+    let first = let_through(0.3, 1000);
+    assert_eq!(first, let_through(0.3, 1000));
+    assert!((299..=301).contains(&first.len()), "{}", first.len());
+    // at most one record apart from an even spread
+    for pair in first.windows(2) {
+        assert!((3..=4).contains(&(pair[1] - pair[0])), "{pair:?}");
+    }
+}
+
+#[test]
+fn marks_sampled_records_with_their_rate() {
+    let sink = TestSink::new();
+    let sample = sampled(&sink)
+        .sample_target("db", 0.01)
+        .sample_target("http", 0.4);
+    log(&sample, "db", 0);
+    log(&sample, "http::client", 1);
+    log(&sample, "app", 2);
+    log(&sample, "database", 3);
+    assert_eq!(
+        sink.take(),
+        [
+            "[DEBUG] db: 0 [sampled 1/100]",
+            "[DEBUG] http::client: 1 [sampled 1/2.5]",
+            "[DEBUG] app: 2",
+            "[DEBUG] database: 3",
+        ]
+    );
+}